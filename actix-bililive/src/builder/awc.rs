@@ -0,0 +1,77 @@
+use awc::http::header::{CONTENT_TYPE, COOKIE, USER_AGENT};
+use awc::http::Method as AwcMethod;
+use awc::Client;
+
+use crate::core::builder::{BoxFuture, Method, ProxyRequester, Request, Requester, Response};
+use crate::core::config;
+use crate::core::errors::{BoxedError, BuildError};
+use crate::core::proxy::Proxy;
+
+/// Limit of response body size.
+const BODY_LIMIT: usize = 16 * 1024 * 1024;
+
+#[derive(Default)]
+pub struct AwcClient(Client);
+
+impl AwcClient {
+    /// Construct a client sending requests through given proxy.
+    #[must_use]
+    pub fn with_proxy(proxy: &Proxy) -> Self {
+        Self(crate::proxy::client(Some(proxy)))
+    }
+}
+
+impl ProxyRequester for AwcClient {
+    fn with_proxy(proxy: &Proxy) -> Result<Self, BuildError> {
+        Ok(Self::with_proxy(proxy))
+    }
+}
+
+impl From<Client> for AwcClient {
+    fn from(client: Client) -> Self {
+        Self(client)
+    }
+}
+
+impl Requester for AwcClient {
+    fn request(&self, request: Request) -> BoxFuture<'_, Result<Response, BoxedError>> {
+        Box::pin(async move {
+            let url = request.full_url()?;
+            let method = match request.method() {
+                Method::Get => AwcMethod::GET,
+                Method::Post => AwcMethod::POST,
+            };
+            let mut req = self.0.request(method, url.as_str());
+            // awc sends its own user agent by default
+            if request.has_header(USER_AGENT.as_str()) {
+                req.headers_mut().remove(USER_AGENT);
+            } else {
+                req = req.insert_header((USER_AGENT, config::USER_AGENT));
+            }
+            for (name, value) in request.headers() {
+                req = req.append_header((name.as_str(), value.as_str()));
+            }
+            if let Some(cookie) = request.cookie_header() {
+                req = req.insert_header((COOKIE, cookie));
+            }
+            let mut resp = if let Some(content_type) = request.body().content_type() {
+                req.insert_header((CONTENT_TYPE, content_type))
+                    .send_body(request.body().encode())
+                    .await?
+            } else {
+                req.send().await?
+            };
+
+            let status = resp.status().as_u16();
+            let headers = resp
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect();
+            let body = resp.body().limit(BODY_LIMIT).await?.to_vec();
+            Ok(Response::new(status, headers, body))
+        })
+    }
+}
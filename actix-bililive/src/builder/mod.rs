@@ -0,0 +1,64 @@
+//! `actix-bililive` config builder.
+//!
+//! Stream config can be built via given live room parameters (room id and user id) & danmaku server configs (server token and list).
+//!
+//! # Helper methods
+//!
+//! [`by_uid`](ConfigBuilder::by_uid) fetches room id by given user id.
+//!
+//! [`by_room_id`](ConfigBuilder::by_room_id) resolves the real room id by given short or long room id.
+//!
+//! [`by_url`](ConfigBuilder::by_url) resolves the real room id by given live room url.
+//!
+//! [`fetch_conf`](ConfigBuilder::fetch_conf) fetches danmaku server token and list without any input parameter.
+//!
+//! [`new_with_proxy`](ConfigBuilder::new_with_proxy) sends api requests through a proxy, and sets it in the built config.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use std::future::Future;
+//! #
+//! # use actix_bililive::ConfigBuilder;
+//! # use actix_bililive::core::errors::BuildError;
+//! #
+//! # let fut = async {
+//! # Ok::<_, BuildError>(
+//! ConfigBuilder::new()
+//!     .by_uid(1472906636)
+//!     .await?
+//!     .fetch_conf()
+//!     .await?
+//!     .build()?
+//! # )
+//! # };
+//! #
+//! # actix_rt::System::new().block_on(fut).unwrap();
+//! ```
+
+mod awc;
+#[cfg(test)]
+pub(crate) mod tests;
+
+/// The HTTP client used by [`ConfigBuilder`](ConfigBuilder).
+///
+/// It implements [`Requester`](bililive_core::builder::Requester), so it can also be passed to other
+/// http-based apis like [`room`](bililive_core::room).
+pub type HttpClient = awc::AwcClient;
+
+/// `actix-bililive` stream config builder.
+///
+/// Stream config can be built via given live room parameters (room id and user id) & danmaku server configs (server token and list).
+///
+/// See the generic type [`ConfigBuilder`](bililive_core::builder::ConfigBuilder) for details.
+///
+/// # Helper methods
+///
+/// [`by_uid`](ConfigBuilder::by_uid) fetches room id by given user id.
+///
+/// [`by_room_id`](ConfigBuilder::by_room_id) resolves the real room id by given short or long room id.
+///
+/// [`by_url`](ConfigBuilder::by_url) resolves the real room id by given live room url.
+///
+/// [`fetch_conf`](ConfigBuilder::fetch_conf) fetches danmaku server token and list without any input parameter.
+pub type ConfigBuilder<R, U, T, S> = bililive_core::builder::ConfigBuilder<HttpClient, R, U, T, S>;
@@ -0,0 +1,185 @@
+//! Connection related functions and types.
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::io::{self, ErrorKind};
+use std::sync::Arc;
+
+use actix_codec::Framed;
+use awc::ws::Codec;
+use awc::{BoxedSocket, Client};
+use stream_reconnect::{ReconnectStream, UnderlyingStream};
+use tokio::net::TcpStream;
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+
+use crate::core::builder::BoxFuture;
+use crate::core::config::StreamConfig;
+use crate::core::packet::Packet;
+use crate::core::retry::{RetryConfig, RetryContext, WsStream, WsStreamTrait};
+use crate::core::stream::{FramedStream, HeartbeatStream};
+use crate::errors::{StreamError, WsError};
+use crate::proxy;
+use crate::stream::{CodecStream, TransportStream};
+
+/// Limit of websocket frame size.
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Raw websocket stream type.
+pub type InnerStream = Framed<BoxedSocket, Codec>;
+/// Raw tcp socket type.
+pub type TcpInnerStream = Compat<TcpStream>;
+/// Bililive stream type.
+///
+/// It's either over websocket or raw tcp, depending on the transport of the connected server.
+pub type DefaultStream = HeartbeatStream<TransportStream<InnerStream, TcpInnerStream>, WsError>;
+/// Bililive stream type with auto-reconnect mechanism.
+pub type RetryStream = ReconnectStream<
+    WsStream<Connector, WsError>,
+    RetryContext<Connector>,
+    Result<Packet, StreamError>,
+    StreamError,
+>;
+
+type ClientFactory = Arc<dyn Fn() -> Client + Send + Sync>;
+
+/// Connector to bilibili danmaku servers.
+///
+/// By default websocket connections are made with a default awc client, or one connecting through
+/// the [`proxy`](crate::core::config::StreamConfig::proxy) if it's set. A custom client factory
+/// can be given to use clients with custom TLS configurations or connectors, in which case the
+/// proxy is left to the client.
+///
+/// Raw tcp connections can't be made with awc clients, so servers in
+/// [`Transport::Tcp`](crate::core::config::Transport::Tcp) are rejected by connectors with a custom
+/// client factory. Exclude the transport with
+/// [`StreamConfig::with_transports`](crate::core::config::StreamConfig::with_transports) to avoid
+/// trying them.
+#[derive(Clone, Default)]
+pub struct Connector {
+    client: Option<ClientFactory>,
+}
+
+impl Debug for Connector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Connector")
+            .field("client", &self.client.is_some())
+            .finish()
+    }
+}
+
+impl Connector {
+    /// Create a connector which makes websocket connections with clients built by given factory.
+    ///
+    /// A client is built for each (re)connection. Raw tcp servers are rejected with an
+    /// [`Unsupported`](std::io::ErrorKind::Unsupported) error, since the client can't be used for them.
+    #[must_use]
+    pub fn with_client(factory: impl Fn() -> Client + Send + Sync + 'static) -> Self {
+        Self {
+            client: Some(Arc::new(factory)),
+        }
+    }
+
+    /// Connect to bilibili live room with this connector.
+    ///
+    /// See [`connect`](connect) for details.
+    ///
+    /// # Errors
+    /// Returns an error when connections to all servers fail.
+    pub async fn connect(&self, config: StreamConfig) -> Result<DefaultStream, StreamError> {
+        WsStream::<Self, WsError>::establish(RetryContext::new(config, self.clone())).await
+    }
+
+    /// Connect to bilibili live room with auto retry, using this connector for every
+    /// (re)connection.
+    ///
+    /// # Errors
+    /// Returns an error when websocket connection fails.
+    pub async fn connect_with_retry(
+        &self,
+        stream_config: StreamConfig,
+        retry_config: RetryConfig,
+    ) -> Result<RetryStream, StreamError> {
+        let inner: RetryStream = ReconnectStream::connect_with_options(
+            RetryContext::new(stream_config, self.clone()),
+            retry_config.into(),
+        )
+        .await?;
+        Ok(inner)
+    }
+}
+
+impl WsStreamTrait<WsError> for Connector {
+    type Stream = DefaultStream;
+    fn connect<'a>(
+        &'a self,
+        url: &'a str,
+        config: &'a StreamConfig,
+    ) -> BoxFuture<'a, Result<Self::Stream, WsError>> {
+        Box::pin(async move {
+            let stream = if let Some(addr) = url.strip_prefix("tcp://") {
+                if self.client.is_some() {
+                    return Err(io::Error::new(
+                        ErrorKind::Unsupported,
+                        "raw tcp transport is not supported by a custom client factory",
+                    )
+                    .into());
+                }
+                let socket = match config.proxy() {
+                    Some(proxy) => {
+                        let (host, port) = addr
+                            .rsplit_once(':')
+                            .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+                            .ok_or_else(|| {
+                                io::Error::new(ErrorKind::InvalidInput, "invalid tcp address")
+                            })?;
+                        let mut socket = TcpStream::connect(proxy.addr()).await?.compat();
+                        proxy.tunnel(&mut socket, host, port).await?;
+                        socket
+                    }
+                    None => TcpStream::connect(addr).await?.compat(),
+                };
+                TransportStream::Tcp(FramedStream::new(socket))
+            } else {
+                let client = match &self.client {
+                    Some(factory) => factory(),
+                    None => proxy::client(config.proxy()),
+                };
+                let mut request = client.ws(url).max_frame_size(MAX_FRAME_SIZE);
+                for (name, value) in config.headers() {
+                    request = request.set_header(name.as_str(), value.as_str());
+                }
+                let (_, framed) = request.connect().await?;
+                TransportStream::Ws(CodecStream::new(framed))
+            };
+            Ok(HeartbeatStream::new(stream))
+        })
+    }
+}
+
+/// Connect to bilibili live room.
+///
+/// Servers are tried in the preference order of transports, see
+/// [`StreamConfig::urls`](crate::core::config::StreamConfig::urls). Websocket handshakes carry
+/// [`StreamConfig::headers`](crate::core::config::StreamConfig::headers), and connections go through
+/// [`StreamConfig::proxy`](crate::core::config::StreamConfig::proxy) if there's one.
+///
+/// This uses the default [`Connector`](Connector).
+///
+/// # Errors
+/// Returns an error when connections to all servers fail.
+pub async fn connect(config: StreamConfig) -> Result<DefaultStream, StreamError> {
+    Connector::default().connect(config).await
+}
+
+/// Connect to bilibili live room with auto retry.
+///
+/// This uses the default [`Connector`](Connector).
+///
+/// # Errors
+/// Returns an error when websocket connection fails.
+pub async fn connect_with_retry(
+    stream_config: StreamConfig,
+    retry_config: RetryConfig,
+) -> Result<RetryStream, StreamError> {
+    Connector::default()
+        .connect_with_retry(stream_config, retry_config)
+        .await
+}
@@ -0,0 +1,19 @@
+//! Error types.
+use awc::error::{WsClientError, WsProtocolError};
+use thiserror::Error;
+
+pub use crate::core::errors::{ApiError, BuildError, IncompleteResult, ParseError};
+
+/// Errors that may occur in the underlying websocket or raw tcp connection.
+#[derive(Debug, Error)]
+pub enum WsError {
+    #[error("unable to establish websocket connection: {0}")]
+    Connect(#[from] WsClientError),
+    #[error("websocket protocol error: {0}")]
+    Protocol(#[from] WsProtocolError),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Errors that may occur when consuming a stream.
+pub type StreamError = crate::core::errors::StreamError<WsError>;
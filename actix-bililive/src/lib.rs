@@ -0,0 +1,76 @@
+//! A simple stream-based bilibili live client library for the Actix ecosystem, backed by [awc](https://github.com/actix/actix-web/tree/master/awc).
+//!
+//! *Minimum supported rust version: 1.82.0*
+//!
+//! ## Runtime Support
+//!
+//! This crate runs on the `actix` runtime. Types of this crate are not `Send`, so they must be used
+//! on a single-threaded arbiter, e.g. inside an actix-web handler or actor.
+//!
+//! ## Features
+//!
+//! - Ergonomic `Stream`/`Sink` interface.
+//! - Easy establishment of connection via given live room id.
+//! - Handles heartbeat packets automatically.
+//! - Auto retry when connection fails (optional).
+//! - Decompresses `Zlib` payloads automatically.
+//! - Connects through HTTP or SOCKS5 proxies (optional).
+//! - Custom awc clients via [`Connector`](crate::connect::Connector).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use actix_bililive::connect::connect_with_retry;
+//! use actix_bililive::{ConfigBuilder, RetryConfig};
+//!
+//! use futures::StreamExt;
+//! use log::info;
+//! use serde_json::Value;
+//!
+//! # async fn test() {
+//! let config = ConfigBuilder::new()
+//!     .by_uid(1602085)
+//!     .await
+//!     .unwrap()
+//!     .fetch_conf()
+//!     .await
+//!     .unwrap()
+//!     .build()
+//!     .unwrap();
+//!
+//! let mut stream = connect_with_retry(config, RetryConfig::default()).await.unwrap();
+//! while let Some(e) = stream.next().await {
+//!     match e {
+//!         Ok(packet) => {
+//!             info!("raw: {:?}", packet);
+//!             if let Ok(json) = packet.json::<Value>() {
+//!                 info!("json: {:?}", json);
+//!             }
+//!         }
+//!         Err(e) => {
+//!             info!("err: {:?}", e);
+//!         }
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! ## Crate Features
+//!
+//! * `openssl`(default): Enables TLS support via [openssl](https://crates.io/crates/openssl).
+//! * `rustls`: Enables TLS support via [rustls](https://crates.io/crates/rustls).
+
+#![allow(clippy::default_trait_access, clippy::module_name_repetitions)]
+
+pub use bililive_core as core;
+
+#[doc(inline)]
+pub use crate::builder::{ConfigBuilder, HttpClient};
+pub use crate::core::packet::*;
+pub use crate::core::retry::RetryConfig;
+
+mod builder;
+pub mod connect;
+pub mod errors;
+mod proxy;
+pub mod stream;
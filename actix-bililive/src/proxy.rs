@@ -0,0 +1,51 @@
+//! Proxy support for awc clients.
+use std::rc::Rc;
+
+use actix_service::Service;
+use actix_tls::connect::{ConnectError, ConnectInfo, Connection};
+use awc::http::Uri;
+use awc::{Client, Connector};
+use futures::future::LocalBoxFuture;
+use tokio::net::TcpStream;
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+use crate::core::proxy::Proxy;
+
+/// An awc tcp connector which connects through a proxy.
+///
+/// TLS is applied by awc on top of the tunnelled connection.
+#[derive(Clone)]
+struct ProxyConnector(Rc<Proxy>);
+
+impl Service<ConnectInfo<Uri>> for ProxyConnector {
+    type Response = Connection<Uri, TcpStream>;
+    type Error = ConnectError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, req: ConnectInfo<Uri>) -> Self::Future {
+        let proxy = self.0.clone();
+        Box::pin(async move {
+            let socket = TcpStream::connect(proxy.addr())
+                .await
+                .map_err(ConnectError::Io)?;
+            let mut socket = socket.compat();
+            proxy
+                .tunnel(&mut socket, req.hostname(), req.port())
+                .await
+                .map_err(ConnectError::Io)?;
+            Ok(Connection::new(req.request().clone(), socket.into_inner()))
+        })
+    }
+}
+
+/// Build an awc client, which connects through the proxy if there's one.
+pub(crate) fn client(proxy: Option<&Proxy>) -> Client {
+    match proxy {
+        Some(proxy) => Client::builder()
+            .connector(Connector::new().connector(ProxyConnector(Rc::new(proxy.clone()))))
+            .finish(),
+        None => Client::default(),
+    }
+}
@@ -0,0 +1,106 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use awc::error::WsProtocolError;
+use awc::ws::{Frame, Message};
+use futures::ready;
+use futures::{Sink, Stream};
+use log::{debug, warn};
+
+use crate::core::errors::IncompleteResult;
+use crate::core::packet::Packet;
+use crate::errors::{StreamError, WsError};
+
+/// A stream/sink interface to underlying websocket frame stream. Encodes/decodes bilibili live packets.
+pub struct CodecStream<T> {
+    /// underlying awc websocket stream
+    stream: T,
+}
+
+impl<T> CodecStream<T> {
+    /// Convert an awc websocket stream into a [`CodecStream`](CodecStream).
+    ///
+    /// You may want to use `connect` or `connect_with_retry` in [`connect`](crate::connect) module instead.
+    pub const fn new(stream: T) -> Self {
+        Self { stream }
+    }
+}
+
+impl<T> Stream for CodecStream<T>
+where
+    T: Stream<Item = Result<Frame, WsProtocolError>> + Unpin,
+{
+    type Item = Result<Packet, StreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            // poll the underlying websocket stream
+            match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+                Some(Ok(Frame::Binary(input))) => {
+                    // parse the message
+                    match Packet::parse(&input) {
+                        IncompleteResult::Ok((remaining, pack)) => {
+                            debug!("packet parsed, {} bytes remaining", remaining.len());
+                            return Poll::Ready(Some(Ok(pack)));
+                        }
+                        IncompleteResult::Incomplete(needed) => {
+                            debug!("incomplete packet, {:?} needed", needed);
+                        }
+                        IncompleteResult::Err(e) => {
+                            warn!("error occurred when parsing incoming packet");
+                            return Poll::Ready(Some(Err(e.into())));
+                        }
+                    }
+                }
+                Some(Ok(Frame::Close(reason))) => {
+                    // remote closing
+                    debug!("websocket closed by remote: {:?}", reason);
+                    return Poll::Ready(None);
+                }
+                Some(Ok(_)) => {
+                    debug!("not a binary message, dropping");
+                }
+                Some(Err(e)) => {
+                    // underlying websocket error, closing connection
+                    warn!("error occurred when receiving message: {:?}", e);
+                    return Poll::Ready(None);
+                }
+                None => {
+                    // underlying websocket closing
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
+
+impl<T> Sink<Packet> for CodecStream<T>
+where
+    T: Sink<Message, Error = WsProtocolError> + Unpin,
+{
+    type Error = StreamError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream)
+            .poll_ready(cx)
+            .map_err(|e| StreamError::from_ws_error(WsError::Protocol(e)))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Packet) -> Result<(), Self::Error> {
+        Pin::new(&mut self.stream)
+            .start_send(Message::Binary(item.encode().into()))
+            .map_err(|e| StreamError::from_ws_error(WsError::Protocol(e)))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream)
+            .poll_flush(cx)
+            .map_err(|e| StreamError::from_ws_error(WsError::Protocol(e)))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream)
+            .poll_close(cx)
+            .map_err(|e| StreamError::from_ws_error(WsError::Protocol(e)))
+    }
+}
@@ -0,0 +1,8 @@
+//! Bilibili live stream.
+pub use codec::CodecStream;
+pub use transport::TransportStream;
+
+mod codec;
+#[cfg(test)]
+mod tests;
+mod transport;
@@ -0,0 +1,333 @@
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use awc::error::WsProtocolError;
+use awc::ws::{Frame, Message};
+use futures::channel::mpsc;
+use futures::{stream, Future, Sink, SinkExt, Stream, StreamExt};
+
+use crate::builder::tests::{build_real_config, serve, serve_proxy};
+use crate::core::config::{ServerEndpoint, StreamConfig, ORIGIN, USER_AGENT};
+use crate::core::errors::IncompleteResult;
+use crate::core::packet::{Operation, Packet, Protocol};
+use crate::core::proxy::ProxyKind;
+use crate::core::retry::{RetryConfig, WsStream, WsStreamTrait};
+use crate::errors::{StreamError, WsError};
+
+use super::CodecStream;
+
+async fn must_future_timeout(dur: Duration, fut: impl Future) {
+    assert!(
+        actix_rt::time::timeout(dur, fut).await.is_err(),
+        "future not timeout"
+    );
+}
+
+async fn test_stream(
+    mut stream: impl Stream<Item = Result<Packet, StreamError>>
+        + Sink<Packet, Error = StreamError>
+        + Unpin,
+) {
+    let mut msg_count = 0;
+
+    let stream_try = async {
+        while let Some(msg) = stream.next().await {
+            msg.expect("stream error");
+            msg_count += 1;
+        }
+    };
+    // err means timeout indicating there's no early stop on stream
+    must_future_timeout(Duration::from_secs(3), stream_try).await;
+
+    stream
+        .send(Packet::new(Operation::HeartBeat, Protocol::Json, vec![]))
+        .await
+        .expect("sink error");
+    let mut hb_resp_received = false;
+    let stream_try = async {
+        while let Some(msg) = stream.next().await {
+            let msg = msg.expect("stream error");
+            if msg.op() == Operation::HeartBeatResponse {
+                hb_resp_received = true;
+            }
+        }
+    };
+    // err means timeout indicating there's no early stop on stream
+    must_future_timeout(Duration::from_secs(1), stream_try).await;
+    assert!(hb_resp_received, "no heart beat response received");
+
+    stream.close().await.expect("unable to close stream");
+}
+
+/// A local tcp stand-in of the danmaku server.
+///
+/// It accepts one connection, replies the room enter packet and a notification, and then closes the
+/// connection. Websocket ports of the returned endpoint are closed, so that connectors must fall back
+/// to tcp.
+fn serve_tcp() -> StreamConfig {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("unable to bind");
+    let port = listener.local_addr().unwrap().port();
+    let closed_port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    thread::spawn(move || {
+        let (mut socket, _) = listener.accept().expect("unable to accept");
+
+        let mut buf = vec![];
+        let room_enter = loop {
+            let mut chunk = [0; 1024];
+            let n = socket.read(&mut chunk).unwrap();
+            assert_ne!(n, 0, "connection closed before room enter");
+            buf.extend_from_slice(&chunk[..n]);
+            match Packet::parse(&buf) {
+                IncompleteResult::Ok((_, packet)) => break packet,
+                IncompleteResult::Incomplete(_) => continue,
+                IncompleteResult::Err(e) => panic!("unable to parse packet: {}", e),
+            }
+        };
+        assert_eq!(room_enter.op(), Operation::RoomEnter);
+
+        let mut resp = Packet::new(
+            Operation::RoomEnterResponse,
+            Protocol::Json,
+            b"{\"code\":0}".to_vec(),
+        )
+        .encode();
+        resp.extend(
+            Packet::new(
+                Operation::Notification,
+                Protocol::Json,
+                b"{\"cmd\":\"LIVE\"}".to_vec(),
+            )
+            .encode(),
+        );
+        socket.write_all(&resp).unwrap();
+        thread::sleep(Duration::from_millis(100));
+    });
+    StreamConfig::new(
+        5440,
+        0,
+        String::new(),
+        String::new(),
+        vec![ServerEndpoint::new(
+            "127.0.0.1",
+            port,
+            closed_port,
+            closed_port,
+        )],
+    )
+}
+
+async fn test_tcp_stream(stream: impl Stream<Item = Result<Packet, StreamError>> + Unpin) {
+    let packets: Vec<_> = stream.collect().await;
+    assert_eq!(packets.len(), 2);
+    let packets: Vec<_> = packets
+        .into_iter()
+        .map(|packet| packet.expect("stream error"))
+        .collect();
+    assert_eq!(packets[0].op(), Operation::RoomEnterResponse);
+    assert_eq!(packets[1].op(), Operation::Notification);
+    assert_eq!(packets[1].bytes(), br#"{"cmd":"LIVE"}"#);
+}
+
+/// Connect to a local HTTP stand-in which rejects the upgrade with given connector, optionally
+/// through a proxy of given kind, and report the handshake it received.
+async fn handshake<T: WsStreamTrait<WsError>>(connector: &T, proxy: Option<ProxyKind>) -> String {
+    let (url, rx) = serve(concat!(
+        "HTTP/1.1 403 Forbidden\r\n",
+        "Content-Length: 0\r\n",
+        "Connection: close\r\n",
+        "\r\n"
+    ));
+    let addr = url.trim_start_matches("http://").to_string();
+    let url = format!("ws://{}/sub", addr);
+    let config = StreamConfig::new(5440, 0, String::new(), "xdd".to_string(), vec![])
+        .with_header("X-Test", "bililive");
+    let (config, targets) = match proxy {
+        Some(kind) => {
+            let (proxy, targets) = serve_proxy(kind);
+            (config.with_proxy(proxy), Some(targets))
+        }
+        None => (config, None),
+    };
+    assert!(WsStream::<T, WsError>::connect(connector, &url, &config)
+        .await
+        .is_err());
+    if let Some(targets) = targets {
+        assert_eq!(targets.recv().unwrap(), addr);
+    }
+    rx.recv().unwrap().to_ascii_lowercase()
+}
+
+/// Route the connections of given config through a local proxy stand-in.
+///
+/// Returns the proxied config, the address of the tcp server, and the targets reported by the
+/// proxy.
+fn through_proxy(
+    config: StreamConfig,
+    kind: ProxyKind,
+) -> (StreamConfig, String, Receiver<String>) {
+    let (proxy, targets) = serve_proxy(kind);
+    let addr = format!("127.0.0.1:{}", config.servers()[0].port().unwrap());
+    (config.with_proxy(proxy), addr, targets)
+}
+
+fn check_tcp_targets(addr: &str, targets: &Receiver<String>) {
+    // both websocket transports are tried and fail before tcp
+    let targets: Vec<_> = targets.try_iter().collect();
+    assert_eq!(targets.len(), 3);
+    assert_eq!(targets[2], addr);
+}
+
+fn check_handshake(req: &str) {
+    assert!(req.starts_with("get /sub http/1.1\r\n"));
+    assert_eq!(req.matches("user-agent:").count(), 1);
+    assert!(req.contains(&format!(
+        "user-agent: {}\r\n",
+        USER_AGENT.to_ascii_lowercase()
+    )));
+    assert!(req.contains(&format!("origin: {}\r\n", ORIGIN)));
+    assert!(req.contains("cookie: buvid3=xdd\r\n"));
+    assert!(req.contains("x-test: bililive\r\n"));
+}
+
+async fn test_stream_heartbeat(
+    mut stream: impl Stream<Item = Result<Packet, StreamError>>
+        + Sink<Packet, Error = StreamError>
+        + Unpin,
+) {
+    let stream_try = async {
+        while let Some(Ok(_)) = stream.next().await {}
+        panic!("connection closed (heartbeat not sent)");
+    };
+    // err means timeout indicating there's no early stop on stream
+    must_future_timeout(Duration::from_secs(120), stream_try).await;
+
+    stream.close().await.expect("unable to close stream");
+}
+
+#[actix_rt::test]
+async fn must_decode_frames() {
+    let packet = Packet::new(Operation::Notification, Protocol::Json, b"{}".to_vec());
+    let frames = stream::iter([
+        Ok(Frame::Text("dropped".into())),
+        Ok(Frame::Binary(packet.encode().into())),
+        Ok(Frame::Binary(vec![0, 0, 0].into())),
+        Ok(Frame::Close(None)),
+        Ok(Frame::Binary(packet.encode().into())),
+    ]);
+    let mut stream = CodecStream::new(frames);
+
+    let received = stream.next().await.unwrap().expect("stream error");
+    assert_eq!(received.op(), Operation::Notification);
+    assert_eq!(received.bytes(), b"{}");
+    assert!(stream.next().await.is_none(), "stream not closed");
+}
+
+#[actix_rt::test]
+async fn must_encode_packets() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let mut sink = CodecStream::new(
+        tx.sink_map_err(|e| WsProtocolError::Io(io::Error::new(io::ErrorKind::BrokenPipe, e))),
+    );
+    let packet = Packet::new(Operation::HeartBeat, Protocol::Json, vec![]);
+    sink.send(packet.clone()).await.expect("sink error");
+    sink.close().await.expect("unable to close sink");
+
+    match rx.next().await {
+        Some(Message::Binary(data)) => assert_eq!(data, packet.encode()),
+        other => panic!("unexpected message: {:?}", other),
+    }
+    assert!(rx.next().await.is_none());
+}
+
+#[actix_rt::test]
+async fn must_fallback_to_tcp() {
+    let stream = crate::connect::connect(serve_tcp())
+        .await
+        .expect("unable to establish connection");
+    test_tcp_stream(stream).await;
+}
+
+#[actix_rt::test]
+async fn must_send_handshake_headers() {
+    check_handshake(&handshake(&crate::connect::Connector::default(), None).await);
+}
+
+#[actix_rt::test]
+async fn must_connect_with_client_factory() {
+    let built = Arc::new(AtomicUsize::new(0));
+    let connector = crate::connect::Connector::with_client({
+        let built = built.clone();
+        move || {
+            built.fetch_add(1, Ordering::SeqCst);
+            awc::Client::default()
+        }
+    });
+    check_handshake(&handshake(&connector, None).await);
+    assert_eq!(built.load(Ordering::SeqCst), 1);
+}
+
+#[actix_rt::test]
+async fn must_reject_tcp_with_client_factory() {
+    let connector = crate::connect::Connector::with_client(awc::Client::default);
+    let Err(e) = connector.connect(serve_tcp()).await else {
+        panic!("tcp must be rejected");
+    };
+    assert!(e.to_string().contains("custom client factory"), "{}", e);
+}
+
+#[actix_rt::test]
+async fn must_connect_through_proxy() {
+    for kind in [ProxyKind::Http, ProxyKind::Socks5] {
+        check_handshake(&handshake(&crate::connect::Connector::default(), Some(kind)).await);
+
+        let (config, addr, targets) = through_proxy(serve_tcp(), kind);
+        let stream = crate::connect::connect(config)
+            .await
+            .expect("unable to establish connection");
+        test_tcp_stream(stream).await;
+        check_tcp_targets(&addr, &targets);
+    }
+}
+
+#[actix_rt::test]
+async fn must_stream() {
+    let config = build_real_config(true).await;
+
+    let stream = crate::connect::connect(config)
+        .await
+        .expect("unable to establish connection");
+    test_stream(stream).await;
+}
+
+#[actix_rt::test]
+async fn must_retry_stream() {
+    let config = build_real_config(false).await;
+
+    let stream = crate::connect::connect_with_retry(config, RetryConfig::default())
+        .await
+        .expect("unable to establish connection");
+    test_stream(stream).await;
+}
+
+#[actix_rt::test]
+async fn must_hb() {
+    if option_env!("FAST_TEST").is_some() {
+        return;
+    }
+
+    let config = build_real_config(true).await;
+
+    let stream = crate::connect::connect(config)
+        .await
+        .expect("unable to establish connection");
+    test_stream_heartbeat(stream).await;
+}
@@ -0,0 +1,41 @@
+//! Helpers for bilibili web api responses.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::errors::ApiError;
+
+/// Common envelope of bilibili web api responses.
+///
+/// `data` is kept as raw json so that error responses with missing or malformed data can still be
+/// reported by their `code` and `message`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiResp {
+    code: i64,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    data: Value,
+}
+
+impl ApiResp {
+    /// Response code. `0` means success.
+    pub const fn code(&self) -> i64 {
+        self.code
+    }
+    /// Response message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    /// Check the response code and deserialize the payload.
+    pub fn into_data<T: DeserializeOwned>(self) -> Result<T, ApiError> {
+        if self.code != 0 {
+            return Err(ApiError::Api {
+                code: self.code,
+                message: self.message,
+            });
+        }
+        Ok(serde_json::from_value(self.data)?)
+    }
+}
@@ -0,0 +1,272 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// HTTP request method.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+/// HTTP request body.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Body {
+    /// No body.
+    Empty,
+    /// A `application/x-www-form-urlencoded` body.
+    Form(Vec<(String, String)>),
+    /// A `application/json` body.
+    Json(Value),
+}
+
+impl Body {
+    /// The `Content-Type` of the body.
+    #[must_use]
+    pub const fn content_type(&self) -> Option<&'static str> {
+        match self {
+            Self::Empty => None,
+            Self::Form(_) => Some("application/x-www-form-urlencoded"),
+            Self::Json(_) => Some("application/json"),
+        }
+    }
+
+    /// Encode the body into bytes.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Empty => vec![],
+            Self::Form(form) => url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(form)
+                .finish()
+                .into_bytes(),
+            Self::Json(value) => serde_json::to_vec(value).unwrap_or_default(),
+        }
+    }
+}
+
+/// An HTTP request to be made by a [`Requester`](super::Requester).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Request {
+    method: Method,
+    url: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    cookies: Vec<(String, String)>,
+    body: Body,
+}
+
+impl Request {
+    /// Construct a request with given method and url.
+    #[must_use]
+    pub fn new(method: Method, url: &str) -> Self {
+        Self {
+            method,
+            url: url.to_string(),
+            query: vec![],
+            headers: vec![],
+            cookies: vec![],
+            body: Body::Empty,
+        }
+    }
+    /// Construct a `GET` request.
+    #[must_use]
+    pub fn get(url: &str) -> Self {
+        Self::new(Method::Get, url)
+    }
+    /// Construct a `POST` request.
+    #[must_use]
+    pub fn post(url: &str) -> Self {
+        Self::new(Method::Post, url)
+    }
+}
+
+impl Request {
+    /// Append a query parameter.
+    #[must_use]
+    pub fn query(mut self, key: &str, value: impl ToString) -> Self {
+        self.query.push((key.to_string(), value.to_string()));
+        self
+    }
+    /// Append a header.
+    #[must_use]
+    pub fn header(mut self, name: &str, value: impl ToString) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+    /// Append a cookie.
+    #[must_use]
+    pub fn cookie(mut self, name: &str, value: impl ToString) -> Self {
+        self.cookies.push((name.to_string(), value.to_string()));
+        self
+    }
+    /// Append cookies.
+    #[must_use]
+    pub fn cookies<K: ToString, V: ToString>(
+        mut self,
+        cookies: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.cookies.extend(
+            cookies
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+        self
+    }
+    /// Set a url-encoded form body.
+    #[must_use]
+    pub fn form<K: ToString, V: ToString>(
+        mut self,
+        form: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.body = Body::Form(
+            form.into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        );
+        self
+    }
+    /// Set a json body.
+    #[must_use]
+    pub fn json(mut self, value: Value) -> Self {
+        self.body = Body::Json(value);
+        self
+    }
+}
+
+impl Request {
+    /// Get the method.
+    #[must_use]
+    pub const fn method(&self) -> Method {
+        self.method
+    }
+    /// Get the url without query parameters appended by [`Request::query`](Request::query).
+    #[must_use]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    /// Get the query parameters.
+    #[must_use]
+    pub fn query_pairs(&self) -> &[(String, String)] {
+        &self.query
+    }
+    /// Get the headers.
+    #[must_use]
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+    /// Whether a header of given name is set, case-insensitively.
+    #[must_use]
+    pub fn has_header(&self, name: &str) -> bool {
+        self.headers
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case(name))
+    }
+    /// Get the cookies.
+    #[must_use]
+    pub fn cookie_pairs(&self) -> &[(String, String)] {
+        &self.cookies
+    }
+    /// Get the body.
+    #[must_use]
+    pub const fn body(&self) -> &Body {
+        &self.body
+    }
+
+    /// Get the full url with query parameters appended.
+    ///
+    /// # Errors
+    /// Returns an error if the url is invalid.
+    pub fn full_url(&self) -> Result<url::Url, url::ParseError> {
+        let mut url = url::Url::parse(&self.url)?;
+        if !self.query.is_empty() {
+            url.query_pairs_mut().extend_pairs(&self.query);
+        }
+        Ok(url)
+    }
+
+    /// Get the value of the `Cookie` header. `None` if there's no cookie.
+    #[must_use]
+    pub fn cookie_header(&self) -> Option<String> {
+        (!self.cookies.is_empty()).then(|| {
+            self.cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+    }
+}
+
+/// An HTTP response returned by a [`Requester`](super::Requester).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    /// Construct a response.
+    #[must_use]
+    pub fn new(status: u16, headers: Vec<(String, String)>, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    /// Get the status code.
+    #[must_use]
+    pub const fn status(&self) -> u16 {
+        self.status
+    }
+    /// Whether the status code is `2xx`.
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        self.status >= 200 && self.status < 300
+    }
+    /// Get the headers.
+    #[must_use]
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+    /// Get the first header with given name (case-insensitive).
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+    /// Get cookies set by `Set-Cookie` headers, in `(name, value)` pairs.
+    pub fn set_cookies(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case("set-cookie"))
+            .filter_map(|(_, value)| {
+                let pair = value.split(';').next()?;
+                let (name, value) = pair.split_once('=')?;
+                Some((name.trim(), value.trim()))
+            })
+    }
+    /// Get the value of a cookie set by `Set-Cookie` headers.
+    #[must_use]
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.set_cookies()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+    /// Get the body.
+    #[must_use]
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+    /// Try to deserialize the body as JSON.
+    ///
+    /// # Errors
+    /// Returns an error if the body is not a valid JSON of type `T`.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(&self.body)
+    }
+}
@@ -1,6 +1,5 @@
 //! `bililive` config builder.
 
-use std::collections::HashMap;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
@@ -16,41 +15,40 @@ use std::pin::Pin;
 /// [`fetch_conf`](ConfigBuilder::fetch_conf) fetches danmaku server token and list without any input parameter.
 ///
 /// See docs of downstream crates for details.
+use log::warn;
 use serde::de::DeserializeOwned;
 use types::UserIDResponse;
+use url::Url;
 
-use crate::builder::types::{ConfQueryInner, Resp, RoomQueryInner};
-use crate::config::StreamConfig;
+use crate::api::ApiResp;
+use crate::builder::types::{ConfQueryInner, Resp, RoomInitInner, RoomQueryInner};
+use crate::config::{ServerEndpoint, StreamConfig, Transport};
 use crate::errors::{BoxedError, BuildError};
+use crate::open::{self, AppKey, AppSession};
+use crate::proxy::Proxy;
 
+pub use http::{Body, Method, Request, Response};
+
+mod http;
 #[cfg(test)]
 mod tests;
 mod types;
 
+/// A boxed future returned by [`Requester`](Requester).
+#[cfg(feature = "not-send")]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// A boxed future returned by [`Requester`](Requester).
+#[cfg(not(feature = "not-send"))]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
 /// An abstract HTTP client.
 ///
 /// Used in [`ConfigBuilder`](ConfigBuilder) to help fetching bilibili config.
 #[cfg(feature = "not-send")]
 pub trait Requester {
-    /// Make a `GET` request to the url and try to deserialize the response body as JSON.
-    fn get_json<T: DeserializeOwned>(
-        &self,
-        url: &str,
-    ) -> Pin<Box<dyn Future<Output = Result<T, BoxedError>> + '_>>;
-
-    /// Make a `GET` request to the url and try to deserialize the response body as JSON.
-    fn get_json_with_parameters<T: DeserializeOwned>(
-        &self,
-        url: &str,
-        parameters: HashMap<String, String>,
-    ) -> Pin<Box<dyn Future<Output = Result<T, BoxedError>> + '_>>;
-
-    /// Make a `GET` request to the url and try to get a cookie from the response.
-    fn get_cookie(
-        &self,
-        url: String,
-        cookie_name: String,
-    ) -> Pin<Box<dyn Future<Output = Result<String, BoxedError>> + '_>>;
+    /// Make an HTTP request and return the full response, regardless of its status code.
+    fn request(&self, request: Request) -> BoxFuture<'_, Result<Response, BoxedError>>;
 }
 
 /// An abstract HTTP client.
@@ -58,26 +56,32 @@ pub trait Requester {
 /// Used in [`ConfigBuilder`](ConfigBuilder) to help fetching bilibili config.
 #[cfg(not(feature = "not-send"))]
 pub trait Requester: Send + Sync {
-    /// Make a `GET` request to the url and try to deserialize the response body as JSON.
-    fn get_json<T: DeserializeOwned>(
-        &self,
-        url: &str,
-    ) -> Pin<Box<dyn Future<Output = Result<T, BoxedError>> + Send + '_>>;
-
-    /// Make a `GET` request to the url and try to deserialize the response body as JSON.
-    fn get_json_with_parameters<T: DeserializeOwned>(
-        &self,
-        url: &str,
-        parameters: HashMap<String, String>,
-        cookies: HashMap<String, String>,
-    ) -> Pin<Box<dyn Future<Output = Result<T, BoxedError>> + Send + '_>>;
-
-    /// Make a `GET` request to the url and try to get a cookie from the response.
-    fn get_cookie(
-        &self,
-        url: String,
-        cookie_name: String,
-    ) -> Pin<Box<dyn Future<Output = Result<String, BoxedError>> + Send + '_>>;
+    /// Make an HTTP request and return the full response, regardless of its status code.
+    fn request(&self, request: Request) -> BoxFuture<'_, Result<Response, BoxedError>>;
+}
+
+/// An HTTP client which can send requests through a proxy.
+///
+/// Used in [`ConfigBuilder::new_with_proxy`](ConfigBuilder::new_with_proxy) to build a requester
+/// from the proxy.
+pub trait ProxyRequester: Requester + Sized {
+    /// Construct a client sending requests through given proxy.
+    ///
+    /// # Errors
+    /// Returns an error if the client can't be built with the proxy.
+    fn with_proxy(proxy: &Proxy) -> Result<Self, BuildError>;
+}
+
+/// Make a request and try to deserialize the response body as JSON.
+pub(crate) async fn request_json<H: Requester, T: DeserializeOwned>(
+    http: &H,
+    request: Request,
+) -> Result<T, BoxedError> {
+    let resp = http.request(request).await?;
+    if !resp.is_success() {
+        return Err(format!("unexpected http status {}", resp.status()).into());
+    }
+    Ok(resp.json()?)
 }
 
 #[doc(hidden)]
@@ -94,7 +98,17 @@ pub enum BN {}
 ///
 /// [`by_uid`](ConfigBuilder::by_uid) fetches room id by given user id.
 ///
+/// [`by_room_id`](ConfigBuilder::by_room_id) resolves the real room id and the anchor's uid by given short or long room id.
+///
+/// [`by_url`](ConfigBuilder::by_url) resolves the real room id and the anchor's uid by given live room url.
+///
 /// [`fetch_conf`](ConfigBuilder::fetch_conf) fetches danmaku server token and list without any input parameter.
+///
+/// [`open_platform`](ConfigBuilder::open_platform) starts an open platform app session, which fills
+/// all parameters.
+///
+/// [`new_with_proxy`](ConfigBuilder::new_with_proxy) sends api requests through a proxy, and sets it
+/// in the built config.
 #[derive(Debug)]
 pub struct ConfigBuilder<H, R, U, T, S> {
     http: H,
@@ -102,8 +116,11 @@ pub struct ConfigBuilder<H, R, U, T, S> {
     uid: Option<u64>,
     token: Option<String>,
     buvid: Option<String>,
-    servers: Option<Vec<String>>,
+    servers: Option<Result<Vec<ServerEndpoint>, BuildError>>,
+    transports: Option<Vec<Transport>>,
     sess_token: Option<String>,
+    app_session: Option<AppSession>,
+    proxy: Option<Proxy>,
     __marker: PhantomData<(R, U, T, S)>,
 }
 
@@ -116,6 +133,21 @@ impl<H: Default> ConfigBuilder<H, BN, BN, BN, BN> {
     }
 }
 
+impl<H: ProxyRequester> ConfigBuilder<H, BN, BN, BN, BN> {
+    /// Construct a new builder whose requests go through given proxy.
+    ///
+    /// The proxy is also set in the built config, see
+    /// [`StreamConfig::with_proxy`](StreamConfig::with_proxy).
+    ///
+    /// # Errors
+    /// Returns an error if the requester can't be built with the proxy.
+    pub fn new_with_proxy(proxy: Proxy) -> Result<Self, BuildError> {
+        let mut builder = Self::new_with_client(H::with_proxy(&proxy)?);
+        builder.proxy = Some(proxy);
+        Ok(builder)
+    }
+}
+
 impl<H> ConfigBuilder<H, BN, BN, BN, BN> {
     /// Construct a new builder with given requester client.
     #[must_use]
@@ -126,8 +158,11 @@ impl<H> ConfigBuilder<H, BN, BN, BN, BN> {
             uid: None,
             token: None,
             servers: None,
+            transports: None,
             sess_token: None,
             buvid: None,
+            app_session: None,
+            proxy: None,
             __marker: PhantomData,
         }
     }
@@ -142,8 +177,11 @@ impl<H, R, U, T, S> ConfigBuilder<H, R, U, T, S> {
             uid: self.uid,
             token: self.token,
             servers: self.servers,
+            transports: self.transports,
             sess_token: self.sess_token,
             buvid: self.buvid,
+            app_session: self.app_session,
+            proxy: self.proxy,
             __marker: PhantomData,
         }
     }
@@ -166,12 +204,37 @@ impl<H, R, U, T, S> ConfigBuilder<H, R, U, T, S> {
         self.cast()
     }
 
+    /// Set danmaku servers by urls, e.g. `wss://broadcastlv.chat.bilibili.com/sub`.
+    ///
+    /// Urls are validated when [`build`](ConfigBuilder::build) is called.
+    /// See [`ServerEndpoint::parse`](ServerEndpoint::parse) for accepted formats.
     #[must_use]
     pub fn servers(mut self, servers: &[String]) -> ConfigBuilder<H, R, U, T, BF> {
-        self.servers = Some(servers.to_vec());
+        self.servers = Some(
+            servers
+                .iter()
+                .map(|server| ServerEndpoint::parse(server))
+                .collect(),
+        );
         self.cast()
     }
 
+    /// Set danmaku servers by typed endpoints.
+    #[must_use]
+    pub fn endpoints(mut self, endpoints: &[ServerEndpoint]) -> ConfigBuilder<H, R, U, T, BF> {
+        self.servers = Some(Ok(endpoints.to_vec()));
+        self.cast()
+    }
+
+    /// Set the preference order of transports.
+    ///
+    /// See [`StreamConfig::with_transports`](StreamConfig::with_transports) for details.
+    #[must_use]
+    pub fn transports(mut self, transports: &[Transport]) -> Self {
+        self.transports = Some(transports.to_vec());
+        self
+    }
+
     #[must_use]
     pub fn sess_token(mut self, sess_token: &str) -> ConfigBuilder<H, R, U, BF, S> {
         self.sess_token = Some(sess_token.to_string());
@@ -198,14 +261,15 @@ where
     /// # Errors
     /// Returns an error when HTTP api request fails.
     pub async fn by_uid(mut self, uid: u64) -> Result<ConfigBuilder<H, BF, BF, T, S>, BuildError> {
-        let resp: Resp<RoomQueryInner> = self
-            .http
-            .get_json(&*format!(
+        let resp: Resp<RoomQueryInner> = request_json(
+            &self.http,
+            Request::get(&format!(
                 "https://api.live.bilibili.com/bili/living_v2/{}",
                 uid
-            ))
-            .await
-            .map_err(BuildError)?;
+            )),
+        )
+        .await
+        .map_err(BuildError::Http)?;
         let room_id = resp.room_id();
 
         self.room_id = Some(room_id);
@@ -213,50 +277,89 @@ where
         Ok(self.cast())
     }
 
+    /// Fills `room_id` and `uid` by given room id, which can be either the short or the long one.
+    ///
+    /// The real (long) room id and the uid of the anchor are resolved automatically.
+    ///
+    /// # Errors
+    /// Returns [`BuildError::Api`](BuildError::Api) when the api rejects the request, e.g. the
+    /// room doesn't exist, or an error when HTTP api request fails.
+    pub async fn by_room_id(
+        mut self,
+        room_id: u64,
+    ) -> Result<ConfigBuilder<H, BF, BF, T, S>, BuildError> {
+        let resp: ApiResp = request_json(
+            &self.http,
+            Request::get("https://api.live.bilibili.com/room/v1/Room/room_init")
+                .query("id", room_id),
+        )
+        .await
+        .map_err(BuildError::Http)?;
+        let room = resp.into_data::<RoomInitInner>()?;
+
+        self.room_id = Some(room.room_id);
+        self.uid = Some(room.uid);
+        Ok(self.cast())
+    }
+
+    /// Fills `room_id` and `uid` by given live room url.
+    ///
+    /// Urls like `https://live.bilibili.com/6`, `https://live.bilibili.com/blanc/21452505?...`,
+    /// `https://live.bilibili.com/h5/21452505`, `https://m.live.bilibili.com/21452505` or
+    /// `live.bilibili.com/21452505` are accepted. See [`by_room_id`](ConfigBuilder::by_room_id)
+    /// for details.
+    ///
+    /// # Errors
+    /// Returns [`BuildError::InvalidUrl`](BuildError::InvalidUrl) when the url is not a valid live
+    /// room url, or an error when HTTP api request fails.
+    pub async fn by_url(self, url: &str) -> Result<ConfigBuilder<H, BF, BF, T, S>, BuildError> {
+        let room_id = parse_room_url(url).ok_or_else(|| BuildError::InvalidUrl {
+            url: url.to_string(),
+        })?;
+        self.by_room_id(room_id).await
+    }
+
     /// Fetches danmaku server configs & uris
     ///
     /// # Errors
-    /// Returns an error when HTTP api request fails.
+    /// Returns [`BuildError::Api`](BuildError::Api) when the api rejects the request, or an error
+    /// when HTTP api request fails.
     pub async fn fetch_conf(mut self) -> Result<ConfigBuilder<H, R, U, BF, BF>, BuildError> {
-        let resp: Resp<ConfQueryInner> = self
-            .http
-            .get_json_with_parameters(
-                "https://api.live.bilibili.com/xlive/web-room/v1/index/getDanmuInfo",
-                HashMap::from([
-                    ("id".to_string(), self.room_id.unwrap().to_string()),
-                    ("type".to_string(), "0".to_string()),
-                ]),
-                match &self.sess_token {
-                    Some(sess_token) => {
-                        HashMap::from([("SESSDATA".to_string(), sess_token.to_string())])
-                    }
-                    None => HashMap::from([]),
-                },
-            )
-            .await
-            .map_err(BuildError)?;
+        let sess_cookie = self
+            .sess_token
+            .as_ref()
+            .map(|sess_token| ("SESSDATA", sess_token.clone()));
+
+        let resp: ApiResp = request_json(
+            &self.http,
+            Request::get("https://api.live.bilibili.com/xlive/web-room/v1/index/getDanmuInfo")
+                .query("id", self.room_id.unwrap())
+                .query("type", 0)
+                .cookies(sess_cookie.clone()),
+        )
+        .await
+        .map_err(BuildError::Http)?;
+        let conf = resp.into_data::<ConfQueryInner>()?;
 
         let resp_buvid = self
             .http
-            .get_cookie(
-                "https://www.bilibili.com/".to_string(),
-                "buvid3".to_string(),
-            )
+            .request(Request::get("https://www.bilibili.com/"))
             .await
-            .map_err(BuildError)?;
-
-        if let Some(sess_token) = &self.sess_token {
-            let resp_uid: Resp<UserIDResponse> = self
-                .http
-                .get_json_with_parameters(
-                    "https://api.bilibili.com/x/web-interface/nav",
-                    HashMap::default(),
-                    HashMap::from([("SESSDATA".to_string(), sess_token.to_string())]),
-                )
-                .await
-                .map_err(BuildError)?;
+            .map_err(BuildError::Http)?
+            .cookie("buvid3")
+            .map(ToString::to_string)
+            .unwrap_or_else(|| {
+                warn!("missing buvid3 cookie, connect without it");
+                String::new()
+            });
 
-            log::info!("help me");
+        if sess_cookie.is_some() {
+            let resp_uid: Resp<UserIDResponse> = request_json(
+                &self.http,
+                Request::get("https://api.bilibili.com/x/web-interface/nav").cookies(sess_cookie),
+            )
+            .await
+            .map_err(BuildError::Http)?;
 
             self.uid = Some(resp_uid.userid());
         } else {
@@ -264,23 +367,93 @@ where
         }
 
         self.buvid = Some(resp_buvid);
-        self.token = Some(resp.token().to_string());
-        self.servers = Some(resp.servers());
+        self.token = Some(conf.token().to_string());
+        self.servers = Some(Ok(conf.servers()));
         Ok(self.cast())
     }
 }
 
+impl<H, R, U, T, S> ConfigBuilder<H, R, U, T, S>
+where
+    H: Requester,
+    R: Send + Sync,
+    U: Send + Sync,
+    T: Send + Sync,
+    S: Send + Sync,
+{
+    /// Starts an open platform app session with the identity code (身份码) of the anchor, and
+    /// fills all parameters from it.
+    ///
+    /// The built config enters the room with the auth body of the session, see
+    /// [`StreamConfig::with_app_session`](StreamConfig::with_app_session).
+    ///
+    /// # Errors
+    /// Returns an error when HTTP api request fails or the session is rejected.
+    pub async fn open_platform(
+        mut self,
+        key: &AppKey,
+        app_id: u64,
+        code: &str,
+    ) -> Result<ConfigBuilder<H, BF, BF, BF, BF>, BuildError> {
+        let session = open::start(&self.http, key, app_id, code)
+            .await
+            .map_err(BuildError::OpenPlatform)?;
+
+        self.room_id = Some(session.anchor().room_id);
+        self.uid = Some(session.anchor().uid);
+        self.token = Some(String::new());
+        self.buvid = Some(String::new());
+        self.servers = Some(session.servers());
+        self.app_session = Some(session);
+        Ok(self.cast())
+    }
+}
+
+/// Extract the room id (short or long) from a live room url.
+fn parse_room_url(url: &str) -> Option<u64> {
+    let url = if url.contains("://") {
+        Url::parse(url)
+    } else {
+        Url::parse(&format!("https://{}", url))
+    }
+    .ok()?;
+    if !matches!(url.host_str()?, "live.bilibili.com" | "m.live.bilibili.com") {
+        return None;
+    }
+    url.path_segments()?
+        .rev()
+        .find(|segment| !segment.is_empty())?
+        .parse()
+        .ok()
+}
+
 impl<H> ConfigBuilder<H, BF, BF, BF, BF> {
     /// Consumes the builder and returns [`StreamConfig`](StreamConfig)
+    ///
+    /// # Errors
+    /// Returns an error if any server url is invalid, or no server is available in preferred transports.
     #[allow(clippy::missing_panics_doc)]
-    pub fn build(self) -> StreamConfig {
+    pub fn build(self) -> Result<StreamConfig, BuildError> {
         // SAFETY ensured by type state
-        StreamConfig::new(
+        let mut config = StreamConfig::new(
             self.room_id.unwrap(),
             self.uid.unwrap(),
             self.token.unwrap(),
             self.buvid.unwrap(),
-            self.servers.unwrap(),
-        )
+            self.servers.unwrap()?,
+        );
+        if let Some(transports) = &self.transports {
+            config = config.with_transports(transports);
+        }
+        if let Some(session) = self.app_session {
+            config = config.with_app_session(session);
+        }
+        if let Some(proxy) = self.proxy {
+            config = config.with_proxy(proxy);
+        }
+        if config.urls().is_empty() {
+            return Err(BuildError::NoServer);
+        }
+        Ok(config)
     }
 }
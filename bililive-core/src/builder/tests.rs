@@ -1,6 +1,13 @@
-use crate::builder::ConfigBuilder;
+use futures::executor::block_on;
+use serde_json::json;
 
-use super::types::{ConfQueryInner, Resp, RoomQueryInner};
+use crate::api::ApiResp;
+use crate::builder::{Body, BoxFuture, ConfigBuilder, Method, Request, Requester, Response};
+use crate::config::{ServerEndpoint, StreamConfig, Transport, ORIGIN, USER_AGENT};
+use crate::errors::{ApiError, BoxedError, BuildError};
+
+use super::parse_room_url;
+use super::types::{ConfQueryInner, Resp, RoomInitInner, RoomQueryInner};
 
 #[test]
 fn must_parse_room_id() {
@@ -10,11 +17,56 @@ fn must_parse_room_id() {
     assert_eq!(parsed.room_id(), 1016);
 }
 
+#[test]
+fn must_parse_room_init() {
+    let data = r#"{"code":0,"msg":"ok","message":"ok","data":{"room_id":5440,"short_id":6,"uid":9617619,"need_p2p":0,"is_hidden":false,"is_locked":false,"is_portrait":false,"live_status":0,"hidden_till":0,"lock_till":0,"encrypted":false,"pwd_verified":false,"live_time":-62170012800,"room_shield":0,"is_sp":0,"special_type":0}}"#;
+    let resp: ApiResp = serde_json::from_str(data).expect("unable to parse response");
+    let parsed: RoomInitInner = resp.into_data().expect("unable to parse room");
+    assert_eq!(parsed.room_id, 5440);
+    assert_eq!(parsed.short_id, 6);
+    assert_eq!(parsed.uid, 9617619);
+}
+
+#[test]
+fn must_parse_room_init_error() {
+    let data = r#"{"code":60004,"msg":"直播间不存在","message":"直播间不存在","data":{}}"#;
+    let resp: ApiResp = serde_json::from_str(data).expect("unable to parse response");
+    assert!(matches!(
+        resp.into_data::<RoomInitInner>(),
+        Err(ApiError::Api { code: 60004, message }) if message == "直播间不存在"
+    ));
+}
+
+#[test]
+fn must_parse_room_url() {
+    assert_eq!(parse_room_url("https://live.bilibili.com/6"), Some(6));
+    assert_eq!(
+        parse_room_url("https://live.bilibili.com/blanc/21452505?liteVersion=true&spm_id_from=333"),
+        Some(21452505)
+    );
+    assert_eq!(
+        parse_room_url("https://live.bilibili.com/h5/21452505"),
+        Some(21452505)
+    );
+    assert_eq!(
+        parse_room_url("http://live.bilibili.com/21452505/#/"),
+        Some(21452505)
+    );
+    assert_eq!(parse_room_url("live.bilibili.com/1016"), Some(1016));
+    assert_eq!(
+        parse_room_url("https://m.live.bilibili.com/21452505?share_source=copy_link"),
+        Some(21452505)
+    );
+    assert_eq!(parse_room_url("https://space.bilibili.com/1016"), None);
+    assert_eq!(parse_room_url("https://live.bilibili.com/"), None);
+    assert_eq!(parse_room_url("not a url"), None);
+}
+
 #[test]
 fn must_parse_conf() {
     let data = include_str!("../../tests/getConf.json");
-    let parsed: Resp<ConfQueryInner> =
-        serde_json::from_str(data).expect("unable to parse response");
+    let resp: ApiResp = serde_json::from_str(data).expect("unable to parse response");
+    let parsed: ConfQueryInner = resp.into_data().expect("unable to parse conf");
     assert_eq!(
         parsed.token(),
         "zRLe_Wb0lwdalke2_OMvIxBD7uBQ7pNKepn-fP2rIV91AyCRSAYwsw1CVYGgjtuf8IA1AHLchDXhiekQ3IMWnzBu5zqIK9CqdY-tuaCpVi1fxE_hqBEdsfdgxPJyFQAxtgqK4cdf1dm7"
@@ -22,20 +74,305 @@ fn must_parse_conf() {
     assert_eq!(
         parsed.servers(),
         [
-            "wss://tx-gz-live-comet-03.chat.bilibili.com:443/sub",
-            "wss://tx-sh-live-comet-03.chat.bilibili.com:443/sub",
-            "wss://broadcastlv.chat.bilibili.com:443/sub"
+            ServerEndpoint::new("tx-gz-live-comet-03.chat.bilibili.com", 2243, 2244, 443),
+            ServerEndpoint::new("tx-sh-live-comet-03.chat.bilibili.com", 2243, 2244, 443),
+            ServerEndpoint::new("broadcastlv.chat.bilibili.com", 2243, 2244, 443)
         ]
+    );
+    assert_eq!(
+        parsed.servers()[0].url(Transport::Tcp).as_deref(),
+        Some("tcp://tx-gz-live-comet-03.chat.bilibili.com:2243")
+    );
+}
+
+/// A requester serving the danmaku conf, and a home page setting no cookie.
+struct ConfRequester;
+
+impl Requester for ConfRequester {
+    fn request(&self, request: Request) -> BoxFuture<'_, Result<Response, BoxedError>> {
+        let body = if request.url().contains("getDanmuInfo") {
+            include_str!("../../tests/getConf.json")
+        } else {
+            ""
+        };
+        Box::pin(async move { Ok(Response::new(200, vec![], body.as_bytes().to_vec())) })
+    }
+}
+
+#[test]
+fn must_fetch_conf_without_buvid() {
+    let config = block_on(
+        ConfigBuilder::new_with_client(ConfRequester)
+            .room_id(1016)
+            .uid(0)
+            .fetch_conf(),
     )
+    .expect("unable to fetch conf")
+    .build()
+    .expect("unable to build config");
+    assert_eq!(config.buvid(), "");
+    assert_eq!(config.servers().len(), 3);
+    assert!(!config
+        .headers()
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("Cookie")));
+}
+
+/// A requester whose requests are all rejected by the api.
+struct RejectingRequester;
+
+impl Requester for RejectingRequester {
+    fn request(&self, _request: Request) -> BoxFuture<'_, Result<Response, BoxedError>> {
+        let body = r#"{"code":-412,"message":"请求被拦截","data":null}"#;
+        Box::pin(async move { Ok(Response::new(200, vec![], body.as_bytes().to_vec())) })
+    }
+}
+
+#[test]
+fn must_report_api_rejection() {
+    let is_rejected = |result: Result<_, BuildError>| {
+        matches!(
+            result,
+            Err(BuildError::Api(ApiError::Api { code: -412, .. }))
+        )
+    };
+    assert!(is_rejected(
+        block_on(ConfigBuilder::new_with_client(RejectingRequester).by_room_id(6)).map(|_| ())
+    ));
+    assert!(is_rejected(
+        block_on(
+            ConfigBuilder::new_with_client(RejectingRequester)
+                .room_id(1016)
+                .uid(0)
+                .fetch_conf()
+        )
+        .map(|_| ())
+    ));
+}
+
+#[test]
+fn must_reject_invalid_room_url() {
+    let result = block_on(
+        ConfigBuilder::new_with_client(ConfRequester).by_url("https://space.bilibili.com/1016"),
+    );
+    assert!(matches!(
+        result,
+        Err(BuildError::InvalidUrl { url }) if url == "https://space.bilibili.com/1016"
+    ));
 }
 
 #[test]
 fn must_build_config() {
-    ConfigBuilder::<(), _, _, _, _>::new()
+    let config = ConfigBuilder::<(), _, _, _, _>::new()
+        .room_id(1016)
+        .uid(0)
+        .servers(&[
+            "wss://broadcastlv.chat.bilibili.com/sub".to_string(),
+            "tcp://broadcastlv.chat.bilibili.com:2243".to_string(),
+        ])
+        .token("asdf")
+        .buvid("xdd")
+        .build()
+        .expect("unable to build config");
+    assert_eq!(
+        config.urls(),
+        [
+            "wss://broadcastlv.chat.bilibili.com:443/sub",
+            "tcp://broadcastlv.chat.bilibili.com:2243"
+        ]
+    );
+}
+
+#[test]
+fn must_prefer_transports() {
+    let builder = ConfigBuilder::<(), _, _, _, _>::new()
+        .room_id(1016)
+        .uid(0)
+        .endpoints(&[
+            ServerEndpoint::new("a.chat.bilibili.com", 2243, 2244, 443),
+            ServerEndpoint::new("b.chat.bilibili.com", 2243, 2244, 443),
+        ])
+        .token("asdf")
+        .buvid("xdd");
+    let config = builder.build().expect("unable to build config");
+    assert_eq!(config.transports(), Transport::PREFERENCE);
+    assert_eq!(
+        config.urls(),
+        [
+            "wss://a.chat.bilibili.com:443/sub",
+            "wss://b.chat.bilibili.com:443/sub",
+            "ws://a.chat.bilibili.com:2244/sub",
+            "ws://b.chat.bilibili.com:2244/sub",
+            "tcp://a.chat.bilibili.com:2243",
+            "tcp://b.chat.bilibili.com:2243"
+        ]
+    );
+
+    let config = config.with_transports(&[Transport::Tcp, Transport::Ws]);
+    assert_eq!(
+        config.urls(),
+        [
+            "tcp://a.chat.bilibili.com:2243",
+            "tcp://b.chat.bilibili.com:2243",
+            "ws://a.chat.bilibili.com:2244/sub",
+            "ws://b.chat.bilibili.com:2244/sub"
+        ]
+    );
+}
+
+#[test]
+fn must_set_handshake_headers() {
+    let header = |name: &str, value: &str| (name.to_string(), value.to_string());
+
+    let config = StreamConfig::new(1016, 0, "asdf".to_string(), "xdd".to_string(), vec![]);
+    assert_eq!(
+        config.headers(),
+        [
+            header("User-Agent", USER_AGENT),
+            header("Origin", ORIGIN),
+            header("Cookie", "buvid3=xdd")
+        ]
+    );
+
+    let config = config
+        .with_header("user-agent", "bililive")
+        .with_header("X-Test", "1")
+        .without_header("cookie");
+    assert_eq!(
+        config.headers(),
+        [
+            header("Origin", ORIGIN),
+            header("user-agent", "bililive"),
+            header("X-Test", "1")
+        ]
+    );
+
+    let config = StreamConfig::new(1016, 0, "asdf".to_string(), String::new(), vec![]);
+    assert_eq!(
+        config.headers(),
+        [header("User-Agent", USER_AGENT), header("Origin", ORIGIN)]
+    );
+}
+
+#[test]
+fn must_parse_server_url() {
+    let endpoint = ServerEndpoint::parse("wss://broadcastlv.chat.bilibili.com/sub").unwrap();
+    assert_eq!(endpoint.host(), "broadcastlv.chat.bilibili.com");
+    assert_eq!(endpoint.wss_port(), Some(443));
+    assert_eq!(endpoint.ws_port(), None);
+    assert_eq!(endpoint.port(), None);
+    assert!(!endpoint.supports(Transport::Tcp));
+
+    let endpoint = ServerEndpoint::parse("ws://127.0.0.1:2244").unwrap();
+    assert_eq!(endpoint.ws_port(), Some(2244));
+    assert_eq!(
+        endpoint.url(Transport::Ws).as_deref(),
+        Some("ws://127.0.0.1:2244/sub")
+    );
+
+    let endpoint = ServerEndpoint::parse("tcp://127.0.0.1").unwrap();
+    assert_eq!(endpoint.port(), Some(2243));
+
+    for url in [
+        "wss://",
+        "not a url",
+        "https://broadcastlv.chat.bilibili.com/sub",
+        "wss://broadcastlv.chat.bilibili.com/pub",
+    ] {
+        assert!(
+            matches!(
+                ServerEndpoint::parse(url),
+                Err(BuildError::InvalidServer { .. })
+            ),
+            "{} should be rejected",
+            url
+        );
+    }
+}
+
+#[test]
+fn must_reject_invalid_servers() {
+    let result = ConfigBuilder::<(), _, _, _, _>::new()
         .room_id(1016)
         .uid(0)
         .servers(&["wss://".to_string()])
         .token("asdf")
         .buvid("xdd")
         .build();
+    assert!(matches!(result, Err(BuildError::InvalidServer { .. })));
+
+    let result = ConfigBuilder::<(), _, _, _, _>::new()
+        .room_id(1016)
+        .uid(0)
+        .servers(&["wss://broadcastlv.chat.bilibili.com/sub".to_string()])
+        .transports(&[Transport::Tcp])
+        .token("asdf")
+        .buvid("xdd")
+        .build();
+    assert!(matches!(result, Err(BuildError::NoServer)));
+}
+
+#[test]
+fn must_build_request() {
+    let req = Request::post("https://api.live.bilibili.com/msg/send?a=1")
+        .query("id", 5440)
+        .header("User-Agent", "bililive")
+        .cookie("SESSDATA", "sess")
+        .cookies([("bili_jct", "csrf")])
+        .form([("msg", "hello world"), ("csrf", "csrf")]);
+    assert_eq!(req.method(), Method::Post);
+    assert_eq!(
+        req.full_url().unwrap().as_str(),
+        "https://api.live.bilibili.com/msg/send?a=1&id=5440"
+    );
+    assert_eq!(
+        req.headers(),
+        [("User-Agent".to_string(), "bililive".to_string())]
+    );
+    assert!(req.has_header("user-agent"));
+    assert!(!req.has_header("Origin"));
+    assert_eq!(
+        req.cookie_header().as_deref(),
+        Some("SESSDATA=sess; bili_jct=csrf")
+    );
+    assert_eq!(
+        req.body().content_type(),
+        Some("application/x-www-form-urlencoded")
+    );
+    assert_eq!(req.body().encode(), b"msg=hello+world&csrf=csrf");
+
+    let req = Request::get("https://live.bilibili.com").json(json!({"a": 1}));
+    assert_eq!(req.method(), Method::Get);
+    assert_eq!(req.cookie_header(), None);
+    assert_eq!(req.body(), &Body::Json(json!({"a": 1})));
+    assert_eq!(req.body().encode(), br#"{"a":1}"#);
+}
+
+#[test]
+fn must_parse_response() {
+    let resp = Response::new(
+        200,
+        vec![
+            ("Content-Type".to_string(), "application/json".to_string()),
+            (
+                "set-cookie".to_string(),
+                "buvid3=ABCD-1234infoc; path=/; domain=.bilibili.com".to_string(),
+            ),
+            (
+                "Set-Cookie".to_string(),
+                "b_nut=1690027262; path=/".to_string(),
+            ),
+        ],
+        br#"{"code":0}"#.to_vec(),
+    );
+    assert!(resp.is_success());
+    assert_eq!(resp.header("content-type"), Some("application/json"));
+    assert_eq!(resp.cookie("buvid3"), Some("ABCD-1234infoc"));
+    assert_eq!(resp.cookie("b_nut"), Some("1690027262"));
+    assert_eq!(resp.cookie("SESSDATA"), None);
+    assert_eq!(
+        resp.json::<serde_json::Value>().unwrap(),
+        json!({"code": 0})
+    );
+    assert!(!Response::new(412, vec![], vec![]).is_success());
 }
@@ -1,20 +1,23 @@
 use serde::Deserialize;
 use url::Url;
 
+use crate::config::ServerEndpoint;
+
 #[derive(Clone, Eq, PartialEq, Deserialize, Hash)]
 pub struct Resp<T> {
     pub data: T,
 }
 
-impl Resp<ConfQueryInner> {
+impl ConfQueryInner {
     pub fn token(&self) -> &str {
-        &self.data.token
+        &self.token
     }
-    pub fn servers(&self) -> Vec<String> {
-        self.data
-            .host_list
+    pub fn servers(&self) -> Vec<ServerEndpoint> {
+        self.host_list
             .iter()
-            .map(|server| format!("wss://{}:{}/sub", server.host, server.wss_port))
+            .map(|server| {
+                ServerEndpoint::new(&server.host, server.port, server.ws_port, server.wss_port)
+            })
             .collect()
     }
 }
@@ -44,15 +47,26 @@ pub struct RoomQueryInner {
     url: Url,
 }
 
+#[derive(Clone, Eq, PartialEq, Deserialize, Hash)]
+pub struct RoomInitInner {
+    pub room_id: u64,
+    pub short_id: u64,
+    pub uid: u64,
+}
+
 #[derive(Clone, Eq, PartialEq, Deserialize, Hash)]
 pub struct ConfQueryInner {
     token: String,
+    // named `host_server_list` by the legacy getConf endpoint
+    #[serde(alias = "host_server_list")]
     host_list: Vec<WSServer>,
 }
 
 #[derive(Clone, Eq, PartialEq, Deserialize, Hash)]
 struct WSServer {
     host: String,
+    port: u16,
+    ws_port: u16,
     wss_port: u16,
 }
 
@@ -0,0 +1,150 @@
+//! Sending danmaku.
+//!
+//! Danmaku are posted via `msg/send` with a [`Credential`](crate::config::Credential), through any
+//! [`Requester`](crate::builder::Requester).
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::api::ApiResp;
+use crate::builder::{request_json, Request, Requester};
+use crate::config::Credential;
+use crate::errors::SendError;
+
+#[cfg(test)]
+mod tests;
+
+/// Display mode of a danmaku.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[repr(u32)]
+pub enum DanmakuMode {
+    Scroll = 1,
+    Bottom = 4,
+    Top = 5,
+}
+
+/// A danmaku to be sent.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OutgoingDanmaku {
+    msg: String,
+    color: u32,
+    mode: DanmakuMode,
+    font_size: u32,
+    emoticon: bool,
+    reply_mid: Option<u64>,
+}
+
+impl OutgoingDanmaku {
+    /// A plain text danmaku.
+    #[must_use]
+    pub fn new(text: &str) -> Self {
+        Self {
+            msg: text.to_string(),
+            color: 0xff_ff_ff,
+            mode: DanmakuMode::Scroll,
+            font_size: 25,
+            emoticon: false,
+            reply_mid: None,
+        }
+    }
+    /// An emoticon danmaku, e.g. `official_147`.
+    #[must_use]
+    pub fn emoticon(emoticon_unique: &str) -> Self {
+        Self {
+            emoticon: true,
+            ..Self::new(emoticon_unique)
+        }
+    }
+    /// Set the font color in `0xRRGGBB`. By default it's white.
+    #[must_use]
+    pub const fn color(mut self, color: u32) -> Self {
+        self.color = color;
+        self
+    }
+    /// Set the display mode. By default it's [`Scroll`](DanmakuMode::Scroll).
+    #[must_use]
+    pub const fn mode(mut self, mode: DanmakuMode) -> Self {
+        self.mode = mode;
+        self
+    }
+    /// Set the font size. By default it's 25.
+    #[must_use]
+    pub const fn font_size(mut self, font_size: u32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+    /// Reply to (mention) the user with given uid.
+    #[must_use]
+    pub const fn reply_to(mut self, uid: u64) -> Self {
+        self.reply_mid = Some(uid);
+        self
+    }
+
+    fn form(&self, room_id: u64, credential: &Credential, rnd: u64) -> HashMap<String, String> {
+        let mut form: HashMap<String, String> = [
+            ("bubble", "0".to_string()),
+            ("msg", self.msg.clone()),
+            ("color", self.color.to_string()),
+            ("mode", (self.mode as u32).to_string()),
+            ("fontsize", self.font_size.to_string()),
+            ("room_type", "0".to_string()),
+            ("jumpfrom", "0".to_string()),
+            ("reply_mid", self.reply_mid.unwrap_or(0).to_string()),
+            ("rnd", rnd.to_string()),
+            ("roomid", room_id.to_string()),
+            ("csrf", credential.bili_jct().to_string()),
+            ("csrf_token", credential.bili_jct().to_string()),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+        if self.emoticon {
+            form.insert("dm_type".to_string(), "1".to_string());
+            form.insert("emoticonOptions".to_string(), "[object Object]".to_string());
+        }
+        form
+    }
+}
+
+/// Map a `msg/send` response into a result.
+fn check_response(resp: &ApiResp) -> Result<(), SendError> {
+    match (resp.code(), resp.message()) {
+        // filtered messages are silently dropped with a success code
+        (0, "f" | "fire" | "k" | "内容非法") => Err(SendError::BlockedWord),
+        (0, _) => Ok(()),
+        (-101 | -111, _) => Err(SendError::Unauthorized),
+        (10030 | 10031, _) => Err(SendError::TooFrequent),
+        (1_003_212, _) => Err(SendError::TooLong),
+        (1003 | 10024, _) => Err(SendError::Muted),
+        (11000, _) => Err(SendError::BlockedWord),
+        (code, message) => Err(SendError::Api {
+            code,
+            message: message.to_string(),
+        }),
+    }
+}
+
+/// Send a danmaku to the live room.
+///
+/// # Errors
+/// Returns an error when HTTP api request fails or the danmaku is rejected.
+/// Common rejections are mapped into variants of [`SendError`](SendError).
+pub async fn send<H: Requester>(
+    http: &H,
+    credential: &Credential,
+    room_id: u64,
+    danmaku: &OutgoingDanmaku,
+) -> Result<(), SendError> {
+    let rnd = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let resp: ApiResp = request_json(
+        http,
+        Request::post("https://api.live.bilibili.com/msg/send")
+            .form(danmaku.form(room_id, credential, rnd))
+            .cookies(credential.cookies()),
+    )
+    .await
+    .map_err(SendError::Http)?;
+    check_response(&resp)
+}
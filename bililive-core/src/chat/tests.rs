@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use futures::executor::block_on;
+
+use crate::api::ApiResp;
+use crate::builder::{Body, BoxFuture, Method, Request, Requester, Response};
+use crate::config::Credential;
+use crate::errors::{BoxedError, SendError};
+
+use super::{check_response, send, DanmakuMode, OutgoingDanmaku};
+
+/// A requester that records requests and replies with the given response.
+struct MockRequester {
+    requests: Mutex<Vec<Request>>,
+    response: &'static str,
+}
+
+impl MockRequester {
+    const fn new(response: &'static str) -> Self {
+        Self {
+            requests: Mutex::new(vec![]),
+            response,
+        }
+    }
+}
+
+impl Requester for MockRequester {
+    fn request(&self, request: Request) -> BoxFuture<'_, Result<Response, BoxedError>> {
+        self.requests.lock().unwrap().push(request);
+        let body = self.response.as_bytes().to_vec();
+        Box::pin(async move { Ok(Response::new(200, vec![], body)) })
+    }
+}
+
+fn check(data: &str) -> Result<(), SendError> {
+    let resp: ApiResp = serde_json::from_str(data).expect("unable to parse response");
+    check_response(&resp)
+}
+
+#[test]
+fn must_build_form() {
+    let credential = Credential::new("sess", "jct");
+    let form = OutgoingDanmaku::new("hello")
+        .color(0xe33fff)
+        .mode(DanmakuMode::Top)
+        .font_size(30)
+        .reply_to(174102117)
+        .form(5440, &credential, 1690027262);
+    assert_eq!(form["msg"], "hello");
+    assert_eq!(form["color"], "14893055");
+    assert_eq!(form["mode"], "5");
+    assert_eq!(form["fontsize"], "30");
+    assert_eq!(form["reply_mid"], "174102117");
+    assert_eq!(form["rnd"], "1690027262");
+    assert_eq!(form["roomid"], "5440");
+    assert_eq!(form["csrf"], "jct");
+    assert_eq!(form["csrf_token"], "jct");
+    assert!(!form.contains_key("dm_type"));
+
+    let form = OutgoingDanmaku::emoticon("official_147").form(5440, &credential, 0);
+    assert_eq!(form["msg"], "official_147");
+    assert_eq!(form["dm_type"], "1");
+    assert_eq!(form["reply_mid"], "0");
+}
+
+#[test]
+fn must_check_send_response() {
+    assert!(check(r#"{"code":0,"data":{"mode_info":{}},"message":"","msg":""}"#).is_ok());
+    assert!(matches!(
+        check(r#"{"code":0,"data":{},"message":"f","msg":"f"}"#),
+        Err(SendError::BlockedWord)
+    ));
+    assert!(matches!(
+        check(
+            r#"{"code":10030,"data":[],"message":"您发送弹幕的频率过快","msg":"您发送弹幕的频率过快"}"#
+        ),
+        Err(SendError::TooFrequent)
+    ));
+    assert!(matches!(
+        check(r#"{"code":1003212,"data":[],"message":"超出限制长度","msg":"超出限制长度"}"#),
+        Err(SendError::TooLong)
+    ));
+    assert!(matches!(
+        check(r#"{"code":1003,"data":[],"message":"你被禁言啦","msg":"你被禁言啦"}"#),
+        Err(SendError::Muted)
+    ));
+    assert!(matches!(
+        check(r#"{"code":-101,"data":[],"message":"账号未登录","msg":"账号未登录"}"#),
+        Err(SendError::Unauthorized)
+    ));
+    match check(r#"{"code":-400,"data":[],"message":"请求错误","msg":"请求错误"}"#) {
+        Err(SendError::Api { code, message }) => {
+            assert_eq!(code, -400);
+            assert_eq!(message, "请求错误");
+        }
+        _ => panic!("api error expected"),
+    }
+}
+
+#[test]
+fn must_send_request() {
+    let http = MockRequester::new(r#"{"code":0,"data":{"mode_info":{}},"message":"","msg":""}"#);
+    let credential = Credential::new("sess", "jct");
+    block_on(send(
+        &http,
+        &credential,
+        5440,
+        &OutgoingDanmaku::emoticon("official_147").reply_to(174102117),
+    ))
+    .expect("unable to send danmaku");
+
+    let requests = http.requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method(), Method::Post);
+    assert_eq!(request.url(), "https://api.live.bilibili.com/msg/send");
+    assert!(request.query_pairs().is_empty());
+
+    let mut cookies = request.cookie_pairs().to_vec();
+    cookies.sort();
+    assert_eq!(
+        cookies,
+        [
+            ("SESSDATA".to_string(), "sess".to_string()),
+            ("bili_jct".to_string(), "jct".to_string())
+        ]
+    );
+
+    let form: HashMap<&str, &str> = match request.body() {
+        Body::Form(form) => form
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect(),
+        body => panic!("form body expected, got {:?}", body),
+    };
+    assert_eq!(form["msg"], "official_147");
+    assert_eq!(form["roomid"], "5440");
+    assert_eq!(form["csrf"], "jct");
+    assert_eq!(form["csrf_token"], "jct");
+    assert_eq!(form["reply_mid"], "174102117");
+    assert_eq!(form["dm_type"], "1");
+    assert_eq!(form["emoticonOptions"], "[object Object]");
+    assert!(form["rnd"].parse::<u64>().unwrap() > 0);
+}
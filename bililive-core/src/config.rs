@@ -1,20 +1,333 @@
 //! Configuration types.
 
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+use serde_json::{Map, Value};
+use url::Url;
+
+use crate::errors::BuildError;
+use crate::open::AppSession;
+use crate::proxy::Proxy;
+
+/// User agent of a desktop browser, sent along with http requests and websocket handshakes.
+pub const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+/// Origin of the live web page, sent along with websocket handshakes.
+pub const ORIGIN: &str = "https://live.bilibili.com";
+
+/// Transport protocol used to connect to a danmaku server.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Transport {
+    /// Raw TCP, packets are framed directly over the socket.
+    Tcp,
+    /// Plain websocket.
+    Ws,
+    /// Websocket over TLS.
+    Wss,
+}
+
+impl Transport {
+    /// The default preference order of transports, i.e. secure websocket first, then plain
+    /// websocket, and raw tcp at last.
+    pub const PREFERENCE: [Self; 3] = [Self::Wss, Self::Ws, Self::Tcp];
+
+    const fn default_port(self) -> u16 {
+        match self {
+            Self::Tcp => 2243,
+            Self::Ws => 80,
+            Self::Wss => 443,
+        }
+    }
+}
+
+/// A danmaku server endpoint.
+///
+/// A danmaku server accepts connections in several transports, each on its own port. Endpoints
+/// parsed from a single url support only the transport indicated by its scheme.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ServerEndpoint {
+    host: String,
+    port: Option<u16>,
+    ws_port: Option<u16>,
+    wss_port: Option<u16>,
+}
+
+impl ServerEndpoint {
+    /// Construct an endpoint with given host, tcp port, websocket port and secure websocket port.
+    #[must_use]
+    pub fn new(host: &str, port: u16, ws_port: u16, wss_port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port: Some(port),
+            ws_port: Some(ws_port),
+            wss_port: Some(wss_port),
+        }
+    }
+
+    /// Parse an endpoint from a server url.
+    ///
+    /// Urls like `wss://host/sub`, `ws://host:2244/sub` or `tcp://host:2243` are accepted.
+    ///
+    /// # Errors
+    /// Returns an error if the url is malformed, or its scheme or path is not supported.
+    pub fn parse(url: &str) -> Result<Self, BuildError> {
+        let invalid = |reason: &str| BuildError::InvalidServer {
+            url: url.to_string(),
+            reason: reason.to_string(),
+        };
+        let parsed = Url::parse(url).map_err(|e| invalid(&e.to_string()))?;
+        let transport = match parsed.scheme() {
+            "tcp" => Transport::Tcp,
+            "ws" => Transport::Ws,
+            "wss" => Transport::Wss,
+            _ => return Err(invalid("unsupported scheme")),
+        };
+        let host = match parsed.host_str() {
+            Some(host) if !host.is_empty() => host,
+            _ => return Err(invalid("missing host")),
+        };
+        let path_ok = match transport {
+            Transport::Tcp => matches!(parsed.path(), "" | "/"),
+            Transport::Ws | Transport::Wss => matches!(parsed.path(), "" | "/" | "/sub"),
+        };
+        if !path_ok {
+            return Err(invalid("unsupported path"));
+        }
+
+        let port = Some(parsed.port().unwrap_or_else(|| transport.default_port()));
+        let mut endpoint = Self {
+            host: host.to_string(),
+            port: None,
+            ws_port: None,
+            wss_port: None,
+        };
+        match transport {
+            Transport::Tcp => endpoint.port = port,
+            Transport::Ws => endpoint.ws_port = port,
+            Transport::Wss => endpoint.wss_port = port,
+        }
+        Ok(endpoint)
+    }
+}
+
+impl ServerEndpoint {
+    /// Server host.
+    #[must_use]
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+    /// Raw TCP port.
+    #[must_use]
+    pub const fn port(&self) -> Option<u16> {
+        self.port
+    }
+    /// Websocket port.
+    #[must_use]
+    pub const fn ws_port(&self) -> Option<u16> {
+        self.ws_port
+    }
+    /// Secure websocket port.
+    #[must_use]
+    pub const fn wss_port(&self) -> Option<u16> {
+        self.wss_port
+    }
+    /// Whether the endpoint supports given transport.
+    #[must_use]
+    pub const fn supports(&self, transport: Transport) -> bool {
+        match transport {
+            Transport::Tcp => self.port.is_some(),
+            Transport::Ws => self.ws_port.is_some(),
+            Transport::Wss => self.wss_port.is_some(),
+        }
+    }
+    /// Url of the server in given transport. `None` if the transport is not supported.
+    ///
+    /// Websocket urls look like `wss://host:443/sub`, while tcp urls look like `tcp://host:2243`.
+    #[must_use]
+    pub fn url(&self, transport: Transport) -> Option<String> {
+        match transport {
+            Transport::Tcp => Some(format!("tcp://{}:{}", self.host, self.port?)),
+            Transport::Ws => Some(format!("ws://{}:{}/sub", self.host, self.ws_port?)),
+            Transport::Wss => Some(format!("wss://{}:{}/sub", self.host, self.wss_port?)),
+        }
+    }
+}
+
+/// Options of the room enter packet sent after connecting to a danmaku server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomEnterOptions {
+    protover: u32,
+    platform: String,
+    room_type: u32,
+    extra: Map<String, Value>,
+}
+
+impl Default for RoomEnterOptions {
+    fn default() -> Self {
+        Self {
+            protover: 3,
+            platform: "web".to_string(),
+            room_type: 2,
+            extra: Map::new(),
+        }
+    }
+}
+
+impl RoomEnterOptions {
+    /// Construct options the web client uses, i.e. protocol version 3 (brotli), platform `web`
+    /// and type 2.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the protocol version, which decides how the server compresses packets.
+    ///
+    /// `2` for zlib and `3` for brotli.
+    #[must_use]
+    pub const fn with_protover(mut self, protover: u32) -> Self {
+        self.protover = protover;
+        self
+    }
+
+    /// Set the client platform, e.g. `web` or `android`.
+    #[must_use]
+    pub fn with_platform(mut self, platform: &str) -> Self {
+        self.platform = platform.to_string();
+        self
+    }
+
+    /// Set the `type` field.
+    #[must_use]
+    pub const fn with_room_type(mut self, room_type: u32) -> Self {
+        self.room_type = room_type;
+        self
+    }
+
+    /// Set an additional field of the payload.
+    ///
+    /// Additional fields take precedence over the ones filled in by this crate.
+    #[must_use]
+    pub fn with_field(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.extra.insert(name.to_string(), value.into());
+        self
+    }
+}
+
+impl RoomEnterOptions {
+    /// Protocol version.
+    #[must_use]
+    pub const fn protover(&self) -> u32 {
+        self.protover
+    }
+    /// Client platform.
+    #[must_use]
+    pub fn platform(&self) -> &str {
+        &self.platform
+    }
+    /// The `type` field.
+    #[must_use]
+    pub const fn room_type(&self) -> u32 {
+        self.room_type
+    }
+    /// Additional fields of the payload.
+    #[must_use]
+    pub const fn extra(&self) -> &Map<String, Value> {
+        &self.extra
+    }
+}
+
 /// The configuration for bilibili live stream connection.
 #[derive(Debug, Clone)]
 pub struct StreamConfig(Box<StreamConfigInner>);
 
 impl StreamConfig {
     #[must_use]
-    pub fn new(room_id: u64, uid: u64, token: String, buvid: String, servers: Vec<String>) -> Self {
+    pub fn new(
+        room_id: u64,
+        uid: u64,
+        token: String,
+        buvid: String,
+        servers: Vec<ServerEndpoint>,
+    ) -> Self {
+        let mut headers = vec![
+            ("User-Agent".to_string(), USER_AGENT.to_string()),
+            ("Origin".to_string(), ORIGIN.to_string()),
+        ];
+        if !buvid.is_empty() {
+            headers.push(("Cookie".to_string(), format!("buvid3={}", buvid)));
+        }
         Self(Box::new(StreamConfigInner {
             room_id,
             uid,
             token,
             buvid,
             servers,
+            transports: Transport::PREFERENCE.to_vec(),
+            headers,
+            proxy: None,
+            room_enter: RoomEnterOptions::default(),
+            app_session: None,
         }))
     }
+
+    /// Set the preference order of transports.
+    ///
+    /// Connectors try servers in the first transport, and fall back to the next one if none of
+    /// them can be connected. Transports not listed are never used.
+    /// Defaults to [`Transport::PREFERENCE`](Transport::PREFERENCE).
+    #[must_use]
+    pub fn with_transports(mut self, transports: &[Transport]) -> Self {
+        self.0.transports = transports.to_vec();
+        self
+    }
+
+    /// Set a header sent along with websocket handshakes, replacing the existing one of the same
+    /// name.
+    ///
+    /// Defaults to a browser-like `User-Agent`, an `Origin` of the live web page, and a `Cookie`
+    /// carrying the buvid if it's given.
+    #[must_use]
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        let headers = &mut self.0.headers;
+        headers.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+        headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Remove a header from websocket handshakes.
+    #[must_use]
+    pub fn without_header(mut self, name: &str) -> Self {
+        self.0
+            .headers
+            .retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+        self
+    }
+
+    /// Connect to danmaku servers through given proxy.
+    #[must_use]
+    pub fn with_proxy(mut self, proxy: Proxy) -> Self {
+        self.0.proxy = Some(proxy);
+        self
+    }
+
+    /// Set the options of the room enter packet.
+    #[must_use]
+    pub fn with_room_enter(mut self, options: RoomEnterOptions) -> Self {
+        self.0.room_enter = options;
+        self
+    }
+
+    /// Connect in an open platform app session.
+    ///
+    /// The room is entered with the [`auth body`](AppSession::auth_body) of the session, and
+    /// [`room enter options`](StreamConfig::room_enter) are ignored.
+    #[must_use]
+    pub fn with_app_session(mut self, session: AppSession) -> Self {
+        self.0.app_session = Some(session);
+        self
+    }
 }
 
 impl StreamConfig {
@@ -33,17 +346,59 @@ impl StreamConfig {
     pub fn token(&self) -> &str {
         &self.0.token
     }
-    /// Danmaku server urls.
+    /// Danmaku server endpoints.
     #[must_use]
-    pub fn servers(&self) -> &[String] {
+    pub fn servers(&self) -> &[ServerEndpoint] {
         &self.0.servers
     }
+    /// Preference order of transports.
+    #[must_use]
+    pub fn transports(&self) -> &[Transport] {
+        &self.0.transports
+    }
+    /// Danmaku server urls to be tried, in preference order.
+    ///
+    /// Urls of all servers in the first preferred transport come first, followed by those in the
+    /// next transport. Servers not supporting a transport are skipped for it.
+    #[must_use]
+    pub fn urls(&self) -> Vec<String> {
+        self.0
+            .transports
+            .iter()
+            .flat_map(|transport| {
+                self.0
+                    .servers
+                    .iter()
+                    .filter_map(move |server| server.url(*transport))
+            })
+            .collect()
+    }
 
     /// BUVID token
     #[must_use]
     pub fn buvid(&self) -> &str {
         &self.0.buvid
     }
+    /// Headers sent along with websocket handshakes.
+    #[must_use]
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.0.headers
+    }
+    /// Proxy to connect to danmaku servers through.
+    #[must_use]
+    pub fn proxy(&self) -> Option<&Proxy> {
+        self.0.proxy.as_ref()
+    }
+    /// Options of the room enter packet.
+    #[must_use]
+    pub fn room_enter(&self) -> &RoomEnterOptions {
+        &self.0.room_enter
+    }
+    /// Open platform app session to connect in.
+    #[must_use]
+    pub fn app_session(&self) -> Option<&AppSession> {
+        self.0.app_session.as_ref()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -56,5 +411,57 @@ struct StreamConfigInner {
     token: String,
     /// Buvid
     buvid: String,
-    servers: Vec<String>,
+    servers: Vec<ServerEndpoint>,
+    transports: Vec<Transport>,
+    headers: Vec<(String, String)>,
+    proxy: Option<Proxy>,
+    room_enter: RoomEnterOptions,
+    app_session: Option<AppSession>,
+}
+
+/// Login credential of a bilibili account.
+///
+/// Both values can be found in the cookies of a logged-in browser session.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct Credential {
+    sessdata: String,
+    bili_jct: String,
+}
+
+impl Credential {
+    /// Construct a credential from the `SESSDATA` and `bili_jct` cookies.
+    #[must_use]
+    pub fn new(sessdata: &str, bili_jct: &str) -> Self {
+        Self {
+            sessdata: sessdata.to_string(),
+            bili_jct: bili_jct.to_string(),
+        }
+    }
+    /// The `SESSDATA` cookie.
+    #[must_use]
+    pub fn sessdata(&self) -> &str {
+        &self.sessdata
+    }
+    /// The `bili_jct` cookie, which is also used as the CSRF token.
+    #[must_use]
+    pub fn bili_jct(&self) -> &str {
+        &self.bili_jct
+    }
+    /// Cookies to be sent along with requests.
+    #[must_use]
+    pub fn cookies(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("SESSDATA".to_string(), self.sessdata.clone()),
+            ("bili_jct".to_string(), self.bili_jct.clone()),
+        ])
+    }
+}
+
+impl Debug for Credential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Credential")
+            .field("sessdata", &"<redacted>")
+            .field("bili_jct", &"<redacted>")
+            .finish()
+    }
 }
@@ -8,7 +8,7 @@ use thiserror::Error;
 ///
 /// * `Ok` indicates a successful parse.
 /// * `Incomplete` means that more data is needed to complete the parsing.
-/// The `Needed` enum can contain how many additional bytes are necessary.
+///   The `Needed` enum can contain how many additional bytes are necessary.
 /// * `Err` indicates an error.
 pub enum IncompleteResult<T> {
     Ok(T),
@@ -29,18 +29,68 @@ pub enum ParseError {
     PacketError(String),
     #[error("error when decompressing packet buffer: {0}")]
     ZlibError(#[from] std::io::Error),
+    #[error("error when decoding event: {0}")]
+    Event(String),
 }
 
+/// A boxed error returned by [`Requester`](crate::builder::Requester) implementations.
 #[cfg(feature = "not-send")]
-pub(crate) type BoxedError = Box<dyn std::error::Error>;
+pub type BoxedError = Box<dyn std::error::Error>;
 
+/// A boxed error returned by [`Requester`](crate::builder::Requester) implementations.
 #[cfg(not(feature = "not-send"))]
-pub(crate) type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+pub type BoxedError = Box<dyn std::error::Error + Send + Sync>;
 
-/// Errors that may occur when making HTTP requests through builder.
+/// Errors that may occur when building a stream config.
 #[derive(Debug, Error)]
-#[error("error when making http request: {0}")]
-pub struct BuildError(#[source] pub(crate) BoxedError);
+pub enum BuildError {
+    #[error("error when making http request: {0}")]
+    Http(#[source] BoxedError),
+    #[error("invalid server {url}: {reason}")]
+    InvalidServer { url: String, reason: String },
+    #[error("invalid proxy {url}: {reason}")]
+    InvalidProxy { url: String, reason: String },
+    #[error("not a valid live room url: {url}")]
+    InvalidUrl { url: String },
+    #[error("api rejected the request: {0}")]
+    Api(#[from] ApiError),
+    #[error("no server available in preferred transports")]
+    NoServer,
+    #[error("unable to start open platform app: {0}")]
+    OpenPlatform(#[source] ApiError),
+}
+
+/// Errors that may occur when calling bilibili web apis.
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("error when making http request: {0}")]
+    Http(#[source] BoxedError),
+    #[error("unexpected api response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("api returned error code {code}: {message}")]
+    Api { code: i64, message: String },
+}
+
+/// Errors that may occur when sending a danmaku.
+#[derive(Debug, Error)]
+pub enum SendError {
+    #[error("error when making http request: {0}")]
+    Http(#[source] BoxedError),
+    #[error("unexpected api response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("not logged in or invalid credential")]
+    Unauthorized,
+    #[error("sending danmaku too frequently")]
+    TooFrequent,
+    #[error("danmaku too long")]
+    TooLong,
+    #[error("muted in this room")]
+    Muted,
+    #[error("danmaku contains blocked words")]
+    BlockedWord,
+    #[error("api returned error code {code}: {message}")]
+    Api { code: i64, message: String },
+}
 
 /// Errors that may occur when consuming a stream.
 ///
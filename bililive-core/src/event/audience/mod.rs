@@ -0,0 +1,266 @@
+use serde_json::Value;
+
+use crate::errors::ParseError;
+use crate::packet::{Operation, Packet};
+
+use super::types::{as_string, as_u64};
+use super::{cmd, notification};
+
+#[cfg(test)]
+mod tests;
+
+const ONLINE_RANK_COUNT: &str = "ONLINE_RANK_COUNT";
+const ONLINE_RANK_V2: &str = "ONLINE_RANK_V2";
+const ONLINE_RANK_TOP3: &str = "ONLINE_RANK_TOP3";
+const WATCHED_CHANGE: &str = "WATCHED_CHANGE";
+const LIKE_INFO_V3_UPDATE: &str = "LIKE_INFO_V3_UPDATE";
+const LIKE_INFO_V3_CLICK: &str = "LIKE_INFO_V3_CLICK";
+const HOT_RANK_CHANGED_V2: &str = "HOT_RANK_CHANGED_V2";
+
+/// A user on the online rank (高能榜).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RankUser {
+    /// Rank, starting from `1`.
+    pub rank: u32,
+    /// User id.
+    pub uid: u64,
+    /// User name.
+    pub uname: String,
+    /// Avatar url.
+    pub face: String,
+    /// Contribution score.
+    pub score: u64,
+    /// Guard level in this room. `0` if the user is not a guard.
+    pub guard_level: u8,
+}
+
+/// A congratulation message for users entering top 3 of the online rank.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RankMessage {
+    /// Rank entered.
+    pub rank: u32,
+    /// Message text, e.g. `恭喜 <%user%> 成为高能榜榜1`.
+    pub msg: String,
+}
+
+/// Position of the room on the hot rank (热门榜) of its area.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct HotRank {
+    /// Rank, starting from `1`.
+    pub rank: u32,
+    /// Trend of the rank. `1` for rising, `2` for falling and `0` for unchanged.
+    pub trend: u8,
+    /// Seconds until the rank is settled.
+    pub countdown: u64,
+    /// Area the rank belongs to.
+    pub area_name: String,
+    /// Rank description, e.g. `虚拟日常top50`.
+    pub rank_desc: String,
+    /// Unix timestamp (in seconds) when the rank is updated.
+    pub timestamp: u64,
+}
+
+/// An audience metrics update.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum AudienceEvent {
+    /// Number of users on the online rank and online (`ONLINE_RANK_COUNT`).
+    OnlineCount {
+        /// Users on the online rank.
+        rank_count: u64,
+        /// Users watching now. `None` if it's not reported.
+        online_count: Option<u64>,
+    },
+    /// Top users of the online rank (`ONLINE_RANK_V2`).
+    OnlineRank(Vec<RankUser>),
+    /// Users entering top 3 of the online rank (`ONLINE_RANK_TOP3`).
+    OnlineRankTop3(Vec<RankMessage>),
+    /// Number of users who have watched the live (`WATCHED_CHANGE`).
+    Watched {
+        num: u64,
+        /// Display text, e.g. `1.2万人看过`.
+        text: String,
+    },
+    /// Total likes of the live (`LIKE_INFO_V3_UPDATE`).
+    LikeCount(u64),
+    /// A user likes the live (`LIKE_INFO_V3_CLICK`).
+    LikeClick {
+        uid: u64,
+        uname: String,
+        /// Prompt text, e.g. `为主播点赞了`.
+        like_text: String,
+    },
+    /// Hot rank of the room is changed (`HOT_RANK_CHANGED_V2`).
+    HotRank(HotRank),
+    /// Popularity (人气) reported in heartbeat responses.
+    Popularity(u32),
+}
+
+impl AudienceEvent {
+    /// Decode an audience metrics notification or a heartbeat response.
+    ///
+    /// Returns `Ok(None)` if the packet is neither.
+    ///
+    /// # Errors
+    /// Returns an error if the packet carries audience metrics but its payload is malformed.
+    pub fn from_packet(packet: &Packet) -> Result<Option<Self>, ParseError> {
+        if packet.op() == Operation::HeartBeatResponse {
+            // newer servers may append junk to the popularity
+            let popularity = packet
+                .bytes()
+                .get(..4)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(u32::from_be_bytes)
+                .ok_or(ParseError::Int32BE)?;
+            return Ok(Some(Self::Popularity(popularity)));
+        }
+
+        notification(
+            packet,
+            &[
+                ONLINE_RANK_COUNT,
+                ONLINE_RANK_V2,
+                ONLINE_RANK_TOP3,
+                WATCHED_CHANGE,
+                LIKE_INFO_V3_UPDATE,
+                LIKE_INFO_V3_CLICK,
+                HOT_RANK_CHANGED_V2,
+            ],
+        )?
+        .map(|value| Self::from_value(&value))
+        .transpose()
+    }
+
+    fn from_value(value: &Value) -> Result<Self, ParseError> {
+        let cmd = cmd(value).unwrap_or_default();
+        let data = value
+            .get("data")
+            .filter(|data| data.is_object())
+            .ok_or_else(|| ParseError::Event(format!("{} without data", cmd)))?;
+        let list = |key: &str| data[key].as_array().cloned().unwrap_or_default();
+
+        Ok(match cmd {
+            ONLINE_RANK_COUNT => Self::OnlineCount {
+                rank_count: as_u64(&data["count"]),
+                online_count: data.get("online_count").map(as_u64),
+            },
+            ONLINE_RANK_V2 => {
+                // newer servers push `online_list` instead
+                let users = if data["online_list"].is_array() {
+                    list("online_list")
+                } else {
+                    list("list")
+                };
+                Self::OnlineRank(
+                    users
+                        .iter()
+                        .map(|user| RankUser {
+                            rank: as_u64(&user["rank"]) as u32,
+                            uid: as_u64(&user["uid"]),
+                            uname: as_string(&user["uname"]),
+                            face: as_string(&user["face"]),
+                            score: as_u64(&user["score"]),
+                            guard_level: as_u64(&user["guard_level"]) as u8,
+                        })
+                        .collect(),
+                )
+            }
+            ONLINE_RANK_TOP3 => Self::OnlineRankTop3(
+                list("list")
+                    .iter()
+                    .map(|msg| RankMessage {
+                        rank: as_u64(&msg["rank"]) as u32,
+                        msg: as_string(&msg["msg"]),
+                    })
+                    .collect(),
+            ),
+            WATCHED_CHANGE => Self::Watched {
+                num: as_u64(&data["num"]),
+                text: as_string(&data["text_large"]),
+            },
+            LIKE_INFO_V3_UPDATE => Self::LikeCount(as_u64(&data["click_count"])),
+            LIKE_INFO_V3_CLICK => Self::LikeClick {
+                uid: as_u64(&data["uid"]),
+                uname: as_string(&data["uname"]),
+                like_text: as_string(&data["like_text"]),
+            },
+            _ => Self::HotRank(HotRank {
+                rank: as_u64(&data["rank"]) as u32,
+                trend: as_u64(&data["trend"]) as u8,
+                countdown: as_u64(&data["countdown"]),
+                area_name: as_string(&data["area_name"]),
+                rank_desc: as_string(&data["rank_desc"]),
+                timestamp: as_u64(&data["timestamp"]),
+            }),
+        })
+    }
+}
+
+/// Latest audience metrics of a live room, updated from the live stream.
+///
+/// Metrics are `None` until they are reported.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct AudienceStats {
+    /// Users watching now.
+    pub online_count: Option<u64>,
+    /// Users on the online rank.
+    pub rank_count: Option<u64>,
+    /// Top users of the online rank, ordered by rank.
+    pub online_rank: Vec<RankUser>,
+    /// Users who have watched the live.
+    pub watched: Option<u64>,
+    /// Total likes of the live.
+    pub likes: Option<u64>,
+    /// Popularity (人气) reported in heartbeat responses.
+    pub popularity: Option<u32>,
+    /// Hot rank of the room.
+    pub hot_rank: Option<HotRank>,
+}
+
+impl AudienceStats {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a packet from the live stream. Returns the event if it carries audience metrics.
+    ///
+    /// # Errors
+    /// Returns an error if the packet carries audience metrics but its payload is malformed.
+    pub fn feed(&mut self, packet: &Packet) -> Result<Option<AudienceEvent>, ParseError> {
+        let event = AudienceEvent::from_packet(packet)?;
+        if let Some(event) = &event {
+            self.apply(event);
+        }
+        Ok(event)
+    }
+
+    /// Apply an audience metrics update.
+    pub fn apply(&mut self, event: &AudienceEvent) {
+        match event {
+            AudienceEvent::OnlineCount {
+                rank_count,
+                online_count,
+            } => {
+                self.rank_count = Some(*rank_count);
+                if online_count.is_some() {
+                    self.online_count = *online_count;
+                }
+            }
+            AudienceEvent::OnlineRank(users) => {
+                self.online_rank.clone_from(users);
+                self.online_rank.sort_by_key(|user| user.rank);
+            }
+            AudienceEvent::Watched { num, .. } => self.watched = Some(*num),
+            AudienceEvent::LikeCount(likes) => self.likes = Some(*likes),
+            AudienceEvent::HotRank(hot_rank) => self.hot_rank = Some(hot_rank.clone()),
+            AudienceEvent::Popularity(popularity) => self.popularity = Some(*popularity),
+            AudienceEvent::OnlineRankTop3(_) | AudienceEvent::LikeClick { .. } => {}
+        }
+    }
+
+    /// Top `n` users of the online rank.
+    #[must_use]
+    pub fn top(&self, n: usize) -> &[RankUser] {
+        &self.online_rank[..n.min(self.online_rank.len())]
+    }
+}
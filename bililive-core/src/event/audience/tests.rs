@@ -0,0 +1,138 @@
+use serde_json::json;
+
+use crate::event::fixtures::{danmu_msg, notification};
+use crate::packet::{Operation, Packet, Protocol};
+
+use super::{AudienceEvent, AudienceStats, HotRank, RankMessage};
+
+#[test]
+fn must_decode_audience_events() {
+    let decode = |value| {
+        AudienceEvent::from_packet(&notification(&value))
+            .expect("unable to decode event")
+            .expect("not an audience event")
+    };
+
+    assert_eq!(
+        decode(json!({
+            "cmd": "ONLINE_RANK_COUNT",
+            "data": {"count": 2360, "count_text": "2360", "online_count": 4000, "online_count_text": "4000"}
+        })),
+        AudienceEvent::OnlineCount {
+            rank_count: 2360,
+            online_count: Some(4000)
+        }
+    );
+    assert_eq!(
+        decode(json!({
+            "cmd": "ONLINE_RANK_TOP3",
+            "data": {"dmscore": 112, "list": [{"msg": "恭喜 <%vioIet%> 成为高能榜", "rank": 1}]}
+        })),
+        AudienceEvent::OnlineRankTop3(vec![RankMessage {
+            rank: 1,
+            msg: "恭喜 <%vioIet%> 成为高能榜".to_string()
+        }])
+    );
+    assert_eq!(
+        decode(json!({
+            "cmd": "WATCHED_CHANGE",
+            "data": {"num": 12345, "text_small": "1.2万", "text_large": "1.2万人看过"}
+        })),
+        AudienceEvent::Watched {
+            num: 12345,
+            text: "1.2万人看过".to_string()
+        }
+    );
+    assert_eq!(
+        decode(json!({
+            "cmd": "LIKE_INFO_V3_CLICK",
+            "data": {"uid": 174102117, "uname": "vioIet・伊芙加登", "like_text": "为主播点赞了"}
+        })),
+        AudienceEvent::LikeClick {
+            uid: 174102117,
+            uname: "vioIet・伊芙加登".to_string(),
+            like_text: "为主播点赞了".to_string()
+        }
+    );
+    assert_eq!(
+        decode(json!({
+            "cmd": "HOT_RANK_CHANGED_V2",
+            "data": {
+                "rank": 3, "trend": 1, "countdown": 1380, "timestamp": 1690027262,
+                "area_name": "虚拟日常", "rank_desc": "虚拟日常top50"
+            }
+        })),
+        AudienceEvent::HotRank(HotRank {
+            rank: 3,
+            trend: 1,
+            countdown: 1380,
+            area_name: "虚拟日常".to_string(),
+            rank_desc: "虚拟日常top50".to_string(),
+            timestamp: 1690027262
+        })
+    );
+
+    // popularity in heartbeat responses, which may be followed by junk
+    let heartbeat = Packet::new(
+        Operation::HeartBeatResponse,
+        Protocol::Heartbeat,
+        b"\x00\x00\x00\x01[object Object]".to_vec(),
+    );
+    assert_eq!(
+        AudienceEvent::from_packet(&heartbeat).unwrap(),
+        Some(AudienceEvent::Popularity(1))
+    );
+    let heartbeat = Packet::new(Operation::HeartBeatResponse, Protocol::Heartbeat, vec![0]);
+    assert!(AudienceEvent::from_packet(&heartbeat).is_err());
+
+    assert_eq!(
+        AudienceEvent::from_packet(&notification(&danmu_msg())).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn must_update_audience_stats() {
+    let mut stats = AudienceStats::new();
+    let mut feed = |value| stats.feed(&notification(&value)).unwrap();
+
+    assert!(feed(danmu_msg()).is_none());
+    feed(json!({
+        "cmd": "ONLINE_RANK_V2",
+        "data": {
+            "list": [
+                {"uid": 2, "uname": "B", "face": "", "score": "1200", "rank": 2, "guard_level": 0},
+                {"uid": 1, "uname": "A", "face": "", "score": "2400", "rank": 1, "guard_level": 3}
+            ],
+            "rank_type": "gold-rank"
+        }
+    }));
+    feed(json!({"cmd": "ONLINE_RANK_COUNT", "data": {"count": 2360, "online_count": 4000}}));
+    // online count is kept if it's not reported
+    feed(json!({"cmd": "ONLINE_RANK_COUNT", "data": {"count": 2400}}));
+    feed(json!({"cmd": "WATCHED_CHANGE", "data": {"num": 12345}}));
+    feed(json!({"cmd": "LIKE_INFO_V3_UPDATE", "data": {"click_count": 1234}}));
+    stats
+        .feed(&Packet::new(
+            Operation::HeartBeatResponse,
+            Protocol::Heartbeat,
+            42u32.to_be_bytes().to_vec(),
+        ))
+        .unwrap();
+
+    assert_eq!(stats.online_count, Some(4000));
+    assert_eq!(stats.rank_count, Some(2400));
+    assert_eq!(stats.watched, Some(12345));
+    assert_eq!(stats.likes, Some(1234));
+    assert_eq!(stats.popularity, Some(42));
+    assert_eq!(stats.hot_rank, None);
+    assert_eq!(
+        stats
+            .top(1)
+            .iter()
+            .map(|user| (user.uid, user.score, user.guard_level))
+            .collect::<Vec<_>>(),
+        [(1, 2400, 3)]
+    );
+    assert_eq!(stats.top(10).len(), 2);
+}
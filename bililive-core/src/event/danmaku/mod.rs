@@ -0,0 +1,289 @@
+#[cfg(feature = "protobuf")]
+use log::warn;
+use serde_json::{json, Value};
+
+use crate::errors::ParseError;
+use crate::packet::{Operation, Packet, Protocol};
+use crate::room::history_danmaku;
+
+#[cfg(feature = "protobuf")]
+use super::proto;
+use super::types::{as_string, as_u64, FansMedal};
+use super::{cmd, notification};
+
+#[cfg(test)]
+mod tests;
+
+/// Command of danmaku history notifications, which are synthesized by
+/// [`BackfillStream`](crate::stream::BackfillStream) instead of being pushed by the server.
+///
+/// The `data` field is an entry of the `gethistory` api response as is.
+pub(crate) const DANMAKU_HISTORY: &str = "BILILIVE_DANMAKU_HISTORY";
+
+/// Content check info of a danmaku.
+///
+/// `ct` is unique per message, so it can be used to identify a danmaku across live messages and
+/// history.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CheckInfo {
+    /// Unix timestamp (in seconds) when the danmaku is sent.
+    pub ts: u64,
+    /// Check token.
+    pub ct: String,
+}
+
+/// An emoticon in a danmaku.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Emoticon {
+    /// Text the emoticon replaces, e.g. `[dog]`. For emoticon (sticker) danmaku, it's the whole
+    /// message text.
+    pub text: String,
+    /// Unique id of the emoticon.
+    pub unique_id: String,
+    /// Image url.
+    pub url: String,
+    /// Image width.
+    pub width: u32,
+    /// Image height.
+    pub height: u32,
+}
+
+impl Emoticon {
+    fn from_object(text: &str, value: &Value) -> Self {
+        Self {
+            text: text.to_string(),
+            unique_id: as_string(&value["emoticon_unique"]),
+            url: as_string(&value["url"]),
+            width: as_u64(&value["width"]) as u32,
+            height: as_u64(&value["height"]) as u32,
+        }
+    }
+}
+
+/// The user a danmaku replies to.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DanmakuReply {
+    /// User id. `0` if it's masked by the server.
+    pub uid: u64,
+    /// User name.
+    pub uname: String,
+}
+
+/// A danmaku (chat message).
+///
+/// Decoded from `DANMU_MSG` notifications, or from danmaku history (see
+/// [`room::get_history`](crate::room::get_history)). History mixed into a packet stream by
+/// [`BackfillStream`](crate::stream::BackfillStream) is carried by
+/// `BILILIVE_DANMAKU_HISTORY` notifications instead.
+///
+/// With the `protobuf` feature, the protobuf `dm_v2` payload of newer `DANMU_MSG` is preferred over
+/// the legacy positional array when it's present. It carries the full uid of the sender even if
+/// it's masked in the legacy array.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Danmaku {
+    /// Message text.
+    pub text: String,
+    /// User id of the sender. `0` if it's masked by the server.
+    pub uid: u64,
+    /// User name of the sender. It may be masked by the server if the connection is anonymous.
+    pub uname: String,
+    /// Avatar url of the sender. Empty if it's not reported.
+    pub face: String,
+    /// CRC32 of the sender's uid. Always available even if the uid is masked.
+    pub user_hash: Option<u32>,
+    /// Unix timestamp (in milliseconds) when the danmaku is sent.
+    pub timestamp: u64,
+    /// Display mode. `1` for scrolling, `4` for bottom and `5` for top.
+    pub mode: u32,
+    /// Font size.
+    pub font_size: u32,
+    /// Font color in `0xRRGGBB`.
+    pub color: u32,
+    /// Whether the sender is a room admin.
+    pub is_admin: bool,
+    /// User level (UL) of the sender.
+    pub user_level: u32,
+    /// Guard level of the sender in this room. `0` if the sender is not a guard.
+    pub guard_level: u8,
+    /// Fans medal worn by the sender.
+    pub medal: Option<FansMedal>,
+    /// Content check info.
+    pub check_info: Option<CheckInfo>,
+    /// The user replied to.
+    pub reply: Option<DanmakuReply>,
+    /// Emoticons in the message, ordered by their text.
+    pub emoticons: Vec<Emoticon>,
+    /// Whether the danmaku is backfilled from history instead of received live.
+    pub is_history: bool,
+}
+
+impl Danmaku {
+    /// Decode a `DANMU_MSG` or `BILILIVE_DANMAKU_HISTORY` notification packet.
+    ///
+    /// Returns `Ok(None)` if the packet is not a danmaku.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is a danmaku but its payload is malformed.
+    pub fn from_packet(packet: &Packet) -> Result<Option<Self>, ParseError> {
+        notification(packet, &["DANMU_MSG", DANMAKU_HISTORY])?
+            .map(|value| Self::from_value(&value))
+            .transpose()
+    }
+
+    fn from_value(value: &Value) -> Result<Self, ParseError> {
+        if cmd(value) == Some(DANMAKU_HISTORY) {
+            let data = value
+                .get("data")
+                .ok_or_else(|| ParseError::Event(format!("{} without data", DANMAKU_HISTORY)))?;
+            return Ok(history_danmaku(data)?);
+        }
+        if !value.get("info").is_some_and(Value::is_array) {
+            return Err(ParseError::Event("DANMU_MSG without info".to_string()));
+        }
+        let at = |pointer: &str| {
+            value
+                .pointer(&format!("/info{}", pointer))
+                .unwrap_or(&Value::Null)
+        };
+
+        let text = at("/1")
+            .as_str()
+            .ok_or_else(|| ParseError::Event("DANMU_MSG without text".to_string()))?
+            .to_string();
+        let check_info = at("/9")
+            .get("ct")
+            .and_then(Value::as_str)
+            .map(|ct| CheckInfo {
+                ts: as_u64(&at("/9")["ts"]),
+                ct: ct.to_string(),
+            });
+
+        // richer info is kept in a json string
+        let extra: Value = at("/0/15/extra")
+            .as_str()
+            .and_then(|extra| serde_json::from_str(extra).ok())
+            .unwrap_or_default();
+        let mut emoticons: Vec<_> = extra["emots"]
+            .as_object()
+            .map(|emots| {
+                emots
+                    .iter()
+                    .map(|(text, emoticon)| Emoticon::from_object(text, emoticon))
+                    .collect()
+            })
+            .unwrap_or_default();
+        // emoticon danmaku carry the sticker separately
+        if at("/0/13").get("emoticon_unique").is_some() {
+            emoticons.push(Emoticon::from_object(&text, at("/0/13")));
+        }
+        let reply = Some(as_string(&extra["reply_uname"]))
+            .filter(|uname| !uname.is_empty())
+            .map(|uname| DanmakuReply {
+                uid: as_u64(&extra["reply_mid"]),
+                uname,
+            });
+
+        let mut danmaku = Self {
+            text,
+            uid: as_u64(at("/2/0")),
+            uname: as_string(at("/2/1")),
+            face: as_string(at("/0/15/user/base/face")),
+            user_hash: at("/0/7")
+                .as_str()
+                .and_then(|hash| u32::from_str_radix(hash, 16).ok()),
+            timestamp: as_u64(at("/0/4")),
+            mode: as_u64(at("/0/1")) as u32,
+            font_size: as_u64(at("/0/2")) as u32,
+            color: as_u64(at("/0/3")) as u32,
+            is_admin: as_u64(at("/2/2")) != 0,
+            user_level: as_u64(at("/4/0")) as u32,
+            guard_level: as_u64(at("/7")) as u8,
+            medal: FansMedal::from_array(at("/3")),
+            check_info,
+            reply,
+            emoticons,
+            is_history: false,
+        };
+
+        #[cfg(feature = "protobuf")]
+        if let Some(dm_v2) = value["dm_v2"].as_str().filter(|dm_v2| !dm_v2.is_empty()) {
+            match proto::decode(dm_v2) {
+                Ok(dm) => danmaku.merge_dm_v2(dm),
+                Err(e) => warn!("{}, falling back to legacy danmaku", e),
+            }
+        }
+
+        danmaku.emoticons.sort_by(|a, b| a.text.cmp(&b.text));
+        danmaku.emoticons.dedup_by(|a, b| a.text == b.text);
+        Ok(danmaku)
+    }
+
+    /// Override fields with those in the protobuf `dm_v2` payload.
+    #[cfg(feature = "protobuf")]
+    fn merge_dm_v2(&mut self, dm: proto::Dm) {
+        if dm.content.is_empty() {
+            return;
+        }
+        self.text = dm.content;
+        self.mode = dm.mode as u32;
+        self.font_size = dm.fontsize as u32;
+        self.color = dm.color;
+        if let Ok(hash) = u32::from_str_radix(&dm.mid_hash, 16) {
+            self.user_hash = Some(hash);
+        }
+        if dm.ctime > 0 {
+            self.timestamp = dm.ctime as u64;
+        }
+        if let Some(user) = dm.user {
+            if user.uid > 0 {
+                self.uid = user.uid as u64;
+            }
+            if let Some(base) = user.base {
+                if !base.name.is_empty() {
+                    self.uname = base.name;
+                }
+                if !base.face.is_empty() {
+                    self.face = base.face;
+                }
+            }
+        }
+        if let Some(reply) = dm.reply.filter(|reply| !reply.reply_uname.is_empty()) {
+            self.reply = Some(DanmakuReply {
+                uid: reply.reply_mid as u64,
+                uname: reply.reply_uname,
+            });
+        }
+        if !dm.emoticons.is_empty() {
+            self.emoticons = dm
+                .emoticons
+                .into_iter()
+                .map(|(text, emoticon)| Emoticon {
+                    text,
+                    unique_id: emoticon.emoticon_unique,
+                    url: emoticon.url,
+                    width: emoticon.width as u32,
+                    height: emoticon.height as u32,
+                })
+                .collect();
+        }
+    }
+
+    /// Encode a danmaku history entry as a [`DANMAKU_HISTORY`](DANMAKU_HISTORY) notification
+    /// packet, so that it can be mixed into a live packet stream.
+    pub(crate) fn history_packet(entry: &Value) -> Packet {
+        let value = json!({ "cmd": DANMAKU_HISTORY, "data": entry });
+        Packet::new(
+            Operation::Notification,
+            Protocol::Json,
+            serde_json::to_vec(&value).unwrap_or_default(),
+        )
+    }
+
+    /// A key identifying this danmaku across live messages and history.
+    pub(crate) fn dedup_key(&self) -> String {
+        self.check_info.as_ref().map_or_else(
+            || format!("{}:{}:{}", self.uid, self.timestamp / 1000, self.text),
+            |info| info.ct.clone(),
+        )
+    }
+}
@@ -0,0 +1,212 @@
+use serde_json::json;
+
+use crate::event::fixtures::{danmu_msg, fixture, notification};
+use crate::event::FansMedal;
+use crate::packet::{Operation, Packet, Protocol};
+
+use super::{CheckInfo, Danmaku, DanmakuReply, Emoticon, DANMAKU_HISTORY};
+
+#[test]
+fn must_decode_danmaku() {
+    let danmaku = Danmaku::from_packet(&notification(&danmu_msg()))
+        .expect("unable to decode danmaku")
+        .expect("not a danmaku");
+    assert_eq!(
+        danmaku,
+        Danmaku {
+            text: "晚上好".to_string(),
+            uid: 174102117,
+            uname: "vioIet・伊芙加登".to_string(),
+            face: String::new(),
+            user_hash: Some(0x630b6aa4),
+            timestamp: 1690027262123,
+            mode: 1,
+            font_size: 25,
+            color: 0xffffff,
+            is_admin: true,
+            user_level: 20,
+            guard_level: 3,
+            medal: Some(FansMedal {
+                level: 21,
+                name: "牌子".to_string(),
+                anchor_uname: "主播".to_string(),
+                anchor_room_id: 5440,
+                anchor_uid: 9617619,
+                guard_level: 3,
+                is_lighted: true
+            }),
+            check_info: Some(CheckInfo {
+                ts: 1690027262,
+                ct: "A1B2C3D4".to_string()
+            }),
+            reply: None,
+            emoticons: Vec::new(),
+            is_history: false
+        }
+    );
+}
+
+#[test]
+fn must_decode_danmaku_with_suffix() {
+    let mut value = danmu_msg();
+    value["cmd"] = json!("DANMU_MSG:4:0:2:2:2:0");
+    assert!(Danmaku::from_packet(&notification(&value))
+        .expect("unable to decode danmaku")
+        .is_some());
+}
+
+#[test]
+fn must_skip_other_packets() {
+    let packet = notification(&json!({"cmd": "INTERACT_WORD", "data": {}}));
+    assert!(Danmaku::from_packet(&packet).unwrap().is_none());
+    let packet = Packet::new(
+        Operation::HeartBeatResponse,
+        Protocol::Json,
+        1i32.to_be_bytes(),
+    );
+    assert!(Danmaku::from_packet(&packet).unwrap().is_none());
+}
+
+#[test]
+fn must_reject_malformed_danmaku() {
+    let packet = notification(&json!({"cmd": "DANMU_MSG"}));
+    assert!(Danmaku::from_packet(&packet).is_err());
+}
+
+#[test]
+fn must_decode_history_packet() {
+    let entry = fixture("tests/getHistory.json")["data"]["room"][0].clone();
+    let packet = Danmaku::history_packet(&entry);
+    let value: serde_json::Value = packet.json().unwrap();
+    assert_eq!(value["cmd"], DANMAKU_HISTORY);
+    assert_eq!(value["data"], entry);
+
+    let danmaku = Danmaku::from_packet(&packet)
+        .expect("unable to decode danmaku")
+        .expect("not a danmaku");
+    assert_eq!(danmaku.text, "晚上好");
+    assert_eq!(danmaku.uid, 174102117);
+    assert_eq!(
+        danmaku.check_info,
+        Some(CheckInfo {
+            ts: 1690027262,
+            ct: "A1B2C3D4".to_string()
+        })
+    );
+    assert!(danmaku.is_history);
+
+    let packet = notification(&json!({ "cmd": DANMAKU_HISTORY }));
+    assert!(Danmaku::from_packet(&packet).is_err());
+}
+
+#[test]
+fn must_decode_danmaku_extra() {
+    let mut value = danmu_msg();
+    value["info"][0][15] = json!({
+        "extra": json!({
+            "emots": {"[dog]": {"emoticon_unique": "emoji_dog", "url": "https://i0.hdslb.com/dog.png", "width": 20, "height": 20}},
+            "reply_mid": 9617619,
+            "reply_uname": "主播"
+        }).to_string(),
+        "user": {"uid": 174102117, "base": {"name": "vioIet・伊芙加登", "face": "https://i0.hdslb.com/face.jpg"}}
+    });
+    let danmaku = Danmaku::from_packet(&notification(&value))
+        .expect("unable to decode danmaku")
+        .expect("not a danmaku");
+    assert_eq!(danmaku.face, "https://i0.hdslb.com/face.jpg");
+    assert_eq!(
+        danmaku.reply,
+        Some(DanmakuReply {
+            uid: 9617619,
+            uname: "主播".to_string()
+        })
+    );
+    assert_eq!(
+        danmaku.emoticons,
+        [Emoticon {
+            text: "[dog]".to_string(),
+            unique_id: "emoji_dog".to_string(),
+            url: "https://i0.hdslb.com/dog.png".to_string(),
+            width: 20,
+            height: 20
+        }]
+    );
+
+    // emoticon danmaku carry the sticker separately
+    let mut value = danmu_msg();
+    value["info"][1] = json!("赞");
+    value["info"][0][13] = json!({"emoticon_unique": "official_147", "url": "https://i0.hdslb.com/147.png", "width": 183, "height": 60});
+    let danmaku = Danmaku::from_packet(&notification(&value))
+        .expect("unable to decode danmaku")
+        .expect("not a danmaku");
+    assert_eq!(
+        danmaku
+            .emoticons
+            .iter()
+            .map(|emoticon| (emoticon.text.as_str(), emoticon.unique_id.as_str()))
+            .collect::<Vec<_>>(),
+        [("赞", "official_147")]
+    );
+}
+
+#[test]
+#[cfg(not(feature = "protobuf"))]
+fn must_ignore_dm_v2_without_protobuf() {
+    let danmaku = Danmaku::from_packet(&notification(&fixture("tests/raw/dm_v2.json")))
+        .expect("unable to decode danmaku")
+        .expect("not a danmaku");
+    assert_eq!((danmaku.uid, danmaku.uname.as_str()), (0, "vio***"));
+    assert!(danmaku.emoticons.is_empty());
+}
+
+#[cfg(feature = "protobuf")]
+mod protobuf {
+    use serde_json::json;
+
+    use crate::event::fixtures::{fixture, notification};
+    use crate::event::{Danmaku, DanmakuReply, Emoticon};
+
+    #[test]
+    fn must_prefer_dm_v2() {
+        let danmaku = Danmaku::from_packet(&notification(&fixture("tests/raw/dm_v2.json")))
+            .expect("unable to decode danmaku")
+            .expect("not a danmaku");
+        assert_eq!(danmaku.text, "晚上好[dog]");
+        assert_eq!(danmaku.uid, 174102117);
+        assert_eq!(danmaku.uname, "vioIet・伊芙加登");
+        assert_eq!(
+            danmaku.face,
+            "https://i0.hdslb.com/bfs/face/member/noface.jpg"
+        );
+        assert_eq!(danmaku.user_hash, Some(0x630b6aa4));
+        assert_eq!(danmaku.color, 0xe33fff);
+        assert_eq!(
+            danmaku.reply,
+            Some(DanmakuReply {
+                uid: 9617619,
+                uname: "主播".to_string()
+            })
+        );
+        assert_eq!(
+            danmaku.emoticons,
+            [Emoticon {
+                text: "[dog]".to_string(),
+                unique_id: "emoji_dog".to_string(),
+                url: "https://i0.hdslb.com/bfs/live/dog.png".to_string(),
+                width: 20,
+                height: 20
+            }]
+        );
+        // fields not in dm_v2 still come from the legacy array
+        assert_eq!(danmaku.medal.map(|medal| medal.level), Some(21));
+        assert_eq!(danmaku.guard_level, 3);
+
+        // malformed dm_v2 falls back to the legacy array
+        let mut value = fixture("tests/raw/dm_v2.json");
+        value["dm_v2"] = json!("not protobuf");
+        let danmaku = Danmaku::from_packet(&notification(&value))
+            .expect("unable to decode danmaku")
+            .expect("not a danmaku");
+        assert_eq!((danmaku.uid, danmaku.uname.as_str()), (0, "vio***"));
+    }
+}
@@ -0,0 +1,48 @@
+//! Fixtures shared by tests of event modules.
+
+use serde_json::json;
+
+use crate::packet::{Operation, Packet, Protocol};
+
+/// Wrap a json payload in a notification packet.
+pub(crate) fn notification(value: &serde_json::Value) -> Packet {
+    Packet::new(
+        Operation::Notification,
+        Protocol::Json,
+        serde_json::to_vec(value).unwrap(),
+    )
+}
+
+/// A `DANMU_MSG` payload captured from a live room.
+pub(crate) fn danmu_msg() -> serde_json::Value {
+    json!({
+        "cmd": "DANMU_MSG",
+        "info": [
+            [0, 1, 25, 16777215, 1690027262123i64, 1690026000, 0, "630b6aa4", 0, 0, 0, "", 0, "{}", "{}",
+                {"mode": 0, "show_player_type": 0, "extra": "{\"user_hash\":\"1661692580\"}"},
+                {"activity_identity": "", "activity_source": 0, "not_show": 0}, 0],
+            "晚上好",
+            [174102117, "vioIet・伊芙加登", 1, 0, 0, 10000, 1, ""],
+            [21, "牌子", "主播", 5440, 1725515, "", 0, 1725515, 1725515, 5414290, 3, 1, 9617619],
+            [20, 0, 6406234, ">50000", 0],
+            ["", ""],
+            0,
+            3,
+            null,
+            {"ts": 1690027262, "ct": "A1B2C3D4"},
+            0,
+            0,
+            null,
+            null,
+            0,
+            105,
+            [0]
+        ],
+        "dm_v2": ""
+    })
+}
+
+/// Read a json fixture. `path` is relative to the crate root.
+pub(crate) fn fixture(path: &str) -> serde_json::Value {
+    serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+}
@@ -0,0 +1,452 @@
+use serde_json::{json, Value};
+
+use crate::errors::ParseError;
+use crate::packet::{Operation, Packet, Protocol};
+
+use super::types::{as_string, as_u64, FansMedal};
+use super::{cmd, notification};
+
+#[cfg(test)]
+mod tests;
+
+const SEND_GIFT: &str = "SEND_GIFT";
+const COMBO_SEND: &str = "COMBO_SEND";
+const GIFT_STAR_PROCESS: &str = "GIFT_STAR_PROCESS";
+/// Command of combo finished notifications, which are synthesized by
+/// [`GiftComboStream`](crate::stream::GiftComboStream) instead of being pushed by the server.
+pub(crate) const GIFT_COMBO_FINISHED: &str = "BILILIVE_GIFT_COMBO_FINISHED";
+
+/// Currency a gift is paid with.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum CoinType {
+    /// Gold seeds (金瓜子), where `1000` is 1 CNY.
+    Gold,
+    /// Silver seeds (银瓜子), which are free.
+    Silver,
+}
+
+impl CoinType {
+    fn from_value(value: &Value) -> Self {
+        if value.as_str() == Some("gold") {
+            Self::Gold
+        } else {
+            Self::Silver
+        }
+    }
+
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Gold => "gold",
+            Self::Silver => "silver",
+        }
+    }
+}
+
+/// The blind box a gift is opened from.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct BlindGift {
+    /// Gift id of the blind box.
+    pub original_gift_id: u64,
+    /// Gift name of the blind box.
+    pub original_gift_name: String,
+    /// Unit price of the blind box.
+    pub original_gift_price: u64,
+    /// Action text, e.g. `爆出`.
+    pub action: String,
+}
+
+impl BlindGift {
+    /// Returns `None` if the gift is not opened from a blind box.
+    fn from_value(value: &Value) -> Option<Self> {
+        value.is_object().then(|| Self {
+            original_gift_id: as_u64(&value["original_gift_id"]),
+            original_gift_name: as_string(&value["original_gift_name"]),
+            original_gift_price: as_u64(&value["original_gift_price"]),
+            action: as_string(&value["gift_action"]),
+        })
+    }
+}
+
+/// A gift (`SEND_GIFT`).
+///
+/// Each click of a combo is pushed as a separate gift sharing the same
+/// [`batch_combo_id`](Self::batch_combo_id). See [`GiftCombo`](GiftCombo) for merged combos.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Gift {
+    /// Gift id.
+    pub gift_id: u64,
+    /// Gift name.
+    pub gift_name: String,
+    /// Action text, e.g. `投喂`.
+    pub action: String,
+    /// Number of gifts.
+    pub num: u32,
+    /// Currency the gift is paid with.
+    pub coin_type: CoinType,
+    /// Unit price in [`coin_type`](Self::coin_type).
+    pub price: u64,
+    /// Total value in [`coin_type`](Self::coin_type). For blind gifts, it's what's paid for the
+    /// blind boxes instead of what's opened.
+    pub total_coin: u64,
+    /// User id of the sender.
+    pub uid: u64,
+    /// User name of the sender.
+    pub uname: String,
+    /// Avatar url of the sender.
+    pub face: String,
+    /// Guard level of the sender in this room. `0` if the sender is not a guard.
+    pub guard_level: u8,
+    /// Fans medal worn by the sender.
+    pub medal: Option<FansMedal>,
+    /// Id shared by gifts of the same combo.
+    pub batch_combo_id: Option<String>,
+    /// The blind box the gift is opened from.
+    pub blind_gift: Option<BlindGift>,
+    /// Unix timestamp (in seconds) when the gift is sent.
+    pub timestamp: u64,
+}
+
+impl Gift {
+    fn from_value(data: &Value) -> Self {
+        let num = as_u64(&data["num"]) as u32;
+        let price = as_u64(&data["price"]);
+        Self {
+            gift_id: as_u64(&data["giftId"]),
+            gift_name: as_string(&data["giftName"]),
+            action: as_string(&data["action"]),
+            num,
+            coin_type: CoinType::from_value(&data["coin_type"]),
+            price,
+            total_coin: data
+                .get("total_coin")
+                .map_or(price * u64::from(num), as_u64),
+            uid: as_u64(&data["uid"]),
+            uname: as_string(&data["uname"]),
+            face: as_string(&data["face"]),
+            guard_level: as_u64(&data["guard_level"]) as u8,
+            medal: FansMedal::from_object(&data["medal_info"]),
+            batch_combo_id: data["batch_combo_id"]
+                .as_str()
+                .filter(|id| !id.is_empty())
+                .map(ToString::to_string),
+            blind_gift: BlindGift::from_value(&data["blind_gift"]),
+            timestamp: as_u64(&data["timestamp"]),
+        }
+    }
+}
+
+/// Progress of a gift combo (`COMBO_SEND`).
+///
+/// It's pushed periodically during a combo with running totals.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ComboSend {
+    /// Gift id.
+    pub gift_id: u64,
+    /// Gift name.
+    pub gift_name: String,
+    /// Action text, e.g. `投喂`.
+    pub action: String,
+    /// User id of the sender.
+    pub uid: u64,
+    /// User name of the sender.
+    pub uname: String,
+    /// Fans medal worn by the sender.
+    pub medal: Option<FansMedal>,
+    /// Id shared by gifts of the same combo.
+    pub batch_combo_id: String,
+    /// Number of gifts sent in the combo so far.
+    pub batch_combo_num: u32,
+    /// Total value of the combo so far.
+    pub combo_total_coin: u64,
+}
+
+impl ComboSend {
+    fn from_value(data: &Value) -> Self {
+        Self {
+            gift_id: as_u64(&data["gift_id"]),
+            gift_name: as_string(&data["gift_name"]),
+            action: as_string(&data["action"]),
+            uid: as_u64(&data["uid"]),
+            uname: as_string(&data["uname"]),
+            medal: FansMedal::from_object(&data["medal_info"]),
+            batch_combo_id: as_string(&data["batch_combo_id"]),
+            batch_combo_num: as_u64(&data["batch_combo_num"]) as u32,
+            combo_total_coin: as_u64(&data["combo_total_coin"]),
+        }
+    }
+}
+
+/// Gift star (礼物星球) progress (`GIFT_STAR_PROCESS`).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct GiftStarProcess {
+    /// Progress status.
+    pub status: u32,
+    /// Prompt text, e.g. `小花花已点亮`.
+    pub tip: String,
+}
+
+/// A finished gift combo, merged from its gifts and progress notifications by
+/// [`GiftComboStream`](crate::stream::GiftComboStream).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct GiftCombo {
+    /// Id shared by gifts of the combo. Gifts without one are merged per sender and gift.
+    pub batch_combo_id: String,
+    /// Gift id.
+    pub gift_id: u64,
+    /// Gift name.
+    pub gift_name: String,
+    /// Action text, e.g. `投喂`.
+    pub action: String,
+    /// Currency the gifts are paid with.
+    pub coin_type: CoinType,
+    /// Unit price in [`coin_type`](Self::coin_type).
+    pub price: u64,
+    /// Total number of gifts.
+    pub num: u32,
+    /// Total value in [`coin_type`](Self::coin_type).
+    pub total_coin: u64,
+    /// User id of the sender.
+    pub uid: u64,
+    /// User name of the sender.
+    pub uname: String,
+    /// Avatar url of the sender.
+    pub face: String,
+    /// Guard level of the sender in this room. `0` if the sender is not a guard.
+    pub guard_level: u8,
+    /// Fans medal worn by the sender.
+    pub medal: Option<FansMedal>,
+    /// Unix timestamp (in seconds) of the first gift.
+    pub start_time: u64,
+    /// Unix timestamp (in seconds) of the last gift.
+    pub end_time: u64,
+}
+
+impl GiftCombo {
+    fn from_gift(gift: &Gift) -> Self {
+        Self {
+            batch_combo_id: gift
+                .batch_combo_id
+                .clone()
+                .unwrap_or_else(|| format!("{}:{}", gift.uid, gift.gift_id)),
+            gift_id: gift.gift_id,
+            gift_name: gift.gift_name.clone(),
+            action: gift.action.clone(),
+            coin_type: gift.coin_type,
+            price: gift.price,
+            num: gift.num,
+            total_coin: gift.total_coin,
+            uid: gift.uid,
+            uname: gift.uname.clone(),
+            face: gift.face.clone(),
+            guard_level: gift.guard_level,
+            medal: gift.medal.clone(),
+            start_time: gift.timestamp,
+            end_time: gift.timestamp,
+        }
+    }
+
+    /// Start a combo from its progress, in case its gifts are missed or arrive late.
+    ///
+    /// Totals are left zero, as they count received gifts only.
+    fn from_combo_send(combo: &ComboSend) -> Self {
+        Self {
+            batch_combo_id: combo.batch_combo_id.clone(),
+            gift_id: combo.gift_id,
+            gift_name: combo.gift_name.clone(),
+            action: combo.action.clone(),
+            coin_type: CoinType::Gold,
+            price: combo
+                .combo_total_coin
+                .checked_div(u64::from(combo.batch_combo_num))
+                .unwrap_or_default(),
+            num: 0,
+            total_coin: 0,
+            uid: combo.uid,
+            uname: combo.uname.clone(),
+            face: String::new(),
+            guard_level: 0,
+            medal: combo.medal.clone(),
+            start_time: 0,
+            end_time: 0,
+        }
+    }
+
+    fn add_gift(&mut self, gift: &Gift) {
+        self.num += gift.num;
+        self.total_coin += gift.total_coin;
+        if self.start_time == 0 {
+            self.start_time = gift.timestamp;
+        }
+        self.end_time = self.end_time.max(gift.timestamp);
+        if self.face.is_empty() {
+            self.face.clone_from(&gift.face);
+            self.guard_level = gift.guard_level;
+        }
+    }
+
+    /// Encode the combo as a notification packet, so that it can be mixed into a live packet
+    /// stream.
+    pub(crate) fn to_packet(&self) -> Packet {
+        let value = json!({
+            "cmd": GIFT_COMBO_FINISHED,
+            "data": {
+                "batch_combo_id": self.batch_combo_id,
+                "gift_id": self.gift_id,
+                "gift_name": self.gift_name,
+                "action": self.action,
+                "coin_type": self.coin_type.as_str(),
+                "price": self.price,
+                "num": self.num,
+                "total_coin": self.total_coin,
+                "uid": self.uid,
+                "uname": self.uname,
+                "face": self.face,
+                "guard_level": self.guard_level,
+                "medal_info": self.medal.as_ref().map_or(Value::Null, FansMedal::to_object),
+                "start_time": self.start_time,
+                "end_time": self.end_time
+            }
+        });
+        Packet::new(
+            Operation::Notification,
+            Protocol::Json,
+            serde_json::to_vec(&value).unwrap_or_default(),
+        )
+    }
+
+    fn from_value(data: &Value) -> Self {
+        Self {
+            batch_combo_id: as_string(&data["batch_combo_id"]),
+            gift_id: as_u64(&data["gift_id"]),
+            gift_name: as_string(&data["gift_name"]),
+            action: as_string(&data["action"]),
+            coin_type: CoinType::from_value(&data["coin_type"]),
+            price: as_u64(&data["price"]),
+            num: as_u64(&data["num"]) as u32,
+            total_coin: as_u64(&data["total_coin"]),
+            uid: as_u64(&data["uid"]),
+            uname: as_string(&data["uname"]),
+            face: as_string(&data["face"]),
+            guard_level: as_u64(&data["guard_level"]) as u8,
+            medal: FansMedal::from_object(&data["medal_info"]),
+            start_time: as_u64(&data["start_time"]),
+            end_time: as_u64(&data["end_time"]),
+        }
+    }
+}
+
+/// A gift combo being merged.
+///
+/// Received gifts are summed apart from the running totals reported by `COMBO_SEND`, as they
+/// arrive in any order and either of them may miss some gifts. The larger one is reported when
+/// the combo finishes.
+#[derive(Debug, Clone)]
+pub(crate) struct MergingCombo {
+    /// the combo with totals of received gifts
+    combo: GiftCombo,
+    reported_num: u32,
+    reported_total_coin: u64,
+}
+
+impl MergingCombo {
+    /// Start a combo from a gift or a combo progress. Returns `None` for other events.
+    pub(crate) fn start(event: &GiftEvent) -> Option<Self> {
+        let combo = match event {
+            GiftEvent::Gift(gift) => GiftCombo::from_gift(gift),
+            GiftEvent::ComboSend(combo) => GiftCombo::from_combo_send(combo),
+            _ => return None,
+        };
+        let mut merging = Self {
+            combo,
+            reported_num: 0,
+            reported_total_coin: 0,
+        };
+        if let GiftEvent::ComboSend(combo) = event {
+            merging.add_combo_send(combo);
+        }
+        Some(merging)
+    }
+
+    /// Merge a following gift or combo progress of the combo.
+    pub(crate) fn merge(&mut self, event: &GiftEvent) {
+        match event {
+            GiftEvent::Gift(gift) => self.combo.add_gift(gift),
+            GiftEvent::ComboSend(combo) => self.add_combo_send(combo),
+            _ => {}
+        }
+    }
+
+    fn add_combo_send(&mut self, combo: &ComboSend) {
+        self.reported_num = self.reported_num.max(combo.batch_combo_num);
+        self.reported_total_coin = self.reported_total_coin.max(combo.combo_total_coin);
+    }
+
+    pub(crate) fn batch_combo_id(&self) -> &str {
+        &self.combo.batch_combo_id
+    }
+
+    /// Finish the combo with the larger one of received and reported totals.
+    pub(crate) fn finish(self) -> GiftCombo {
+        GiftCombo {
+            num: self.combo.num.max(self.reported_num),
+            total_coin: self.combo.total_coin.max(self.reported_total_coin),
+            ..self.combo
+        }
+    }
+}
+
+/// A gift related notification.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum GiftEvent {
+    Gift(Gift),
+    ComboSend(ComboSend),
+    StarProcess(GiftStarProcess),
+    /// Only yielded by [`GiftComboStream`](crate::stream::GiftComboStream).
+    ComboFinished(GiftCombo),
+}
+
+impl GiftEvent {
+    /// Decode a gift related notification packet.
+    ///
+    /// Returns `Ok(None)` if the packet is not gift related.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is gift related but its payload is malformed.
+    pub fn from_packet(packet: &Packet) -> Result<Option<Self>, ParseError> {
+        notification(
+            packet,
+            &[
+                SEND_GIFT,
+                COMBO_SEND,
+                GIFT_STAR_PROCESS,
+                GIFT_COMBO_FINISHED,
+            ],
+        )?
+        .map(|value| Self::from_value(&value))
+        .transpose()
+    }
+
+    fn from_value(value: &Value) -> Result<Self, ParseError> {
+        let cmd = cmd(value).unwrap_or_default();
+        let data = value
+            .get("data")
+            .filter(|data| data.is_object())
+            .ok_or_else(|| ParseError::Event(format!("{} without data", cmd)))?;
+
+        Ok(match cmd {
+            SEND_GIFT => Self::Gift(Gift::from_value(data)),
+            COMBO_SEND => Self::ComboSend(ComboSend::from_value(data)),
+            GIFT_STAR_PROCESS => Self::StarProcess(GiftStarProcess {
+                status: as_u64(&data["status"]) as u32,
+                tip: as_string(&data["tip"]),
+            }),
+            GIFT_COMBO_FINISHED => Self::ComboFinished(GiftCombo::from_value(data)),
+            _ => {
+                return Err(ParseError::Event(format!(
+                    "unexpected gift command {}",
+                    cmd
+                )))
+            }
+        })
+    }
+}
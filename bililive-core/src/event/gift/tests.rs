@@ -0,0 +1,217 @@
+use serde_json::json;
+
+use crate::event::fixtures::{danmu_msg, notification};
+use crate::event::FansMedal;
+
+use super::{BlindGift, CoinType, ComboSend, Gift, GiftEvent, GiftStarProcess, MergingCombo};
+
+fn send_gift() -> serde_json::Value {
+    json!({
+        "cmd": "SEND_GIFT",
+        "data": {
+            "action": "投喂",
+            "batch_combo_id": "batch:gift:combo_id:174102117:5440:31036:1690027262.1234",
+            "blind_gift": null,
+            "coin_type": "gold",
+            "face": "https://i0.hdslb.com/face.jpg",
+            "giftId": 31036,
+            "giftName": "小花花",
+            "guard_level": 3,
+            "medal_info": {
+                "anchor_roomid": 5440,
+                "anchor_uname": "主播",
+                "guard_level": 3,
+                "is_lighted": 1,
+                "medal_level": 21,
+                "medal_name": "牌子",
+                "target_id": 9617619
+            },
+            "num": 1,
+            "price": 100,
+            "timestamp": 1690027262,
+            "total_coin": 100,
+            "uid": 174102117,
+            "uname": "vioIet・伊芙加登"
+        }
+    })
+}
+
+#[test]
+fn must_decode_gift() {
+    let event = GiftEvent::from_packet(&notification(&send_gift()))
+        .expect("unable to decode gift")
+        .expect("not a gift");
+    let GiftEvent::Gift(gift) = event else {
+        panic!("not a gift: {:?}", event)
+    };
+    assert_eq!(
+        gift,
+        Gift {
+            gift_id: 31036,
+            gift_name: "小花花".to_string(),
+            action: "投喂".to_string(),
+            num: 1,
+            coin_type: CoinType::Gold,
+            price: 100,
+            total_coin: 100,
+            uid: 174102117,
+            uname: "vioIet・伊芙加登".to_string(),
+            face: "https://i0.hdslb.com/face.jpg".to_string(),
+            guard_level: 3,
+            medal: Some(FansMedal {
+                level: 21,
+                name: "牌子".to_string(),
+                anchor_uname: "主播".to_string(),
+                anchor_room_id: 5440,
+                anchor_uid: 9617619,
+                guard_level: 3,
+                is_lighted: true
+            }),
+            batch_combo_id: Some(
+                "batch:gift:combo_id:174102117:5440:31036:1690027262.1234".to_string()
+            ),
+            blind_gift: None,
+            timestamp: 1690027262
+        }
+    );
+
+    let event = GiftEvent::from_packet(&notification(&json!({
+        "cmd": "SEND_GIFT",
+        "data": {
+            "action": "爆出",
+            "batch_combo_id": "",
+            "blind_gift": {
+                "blind_gift_config_id": 51,
+                "gift_action": "爆出",
+                "original_gift_id": 32251,
+                "original_gift_name": "心动盲盒",
+                "original_gift_price": 15000
+            },
+            "coin_type": "gold",
+            "giftId": 32124,
+            "giftName": "电影票",
+            "medal_info": {"medal_level": 0, "medal_name": ""},
+            "num": 2,
+            "price": 2000,
+            "total_coin": 30000,
+            "uid": 174102117,
+            "uname": "vioIet・伊芙加登"
+        }
+    })))
+    .unwrap()
+    .unwrap();
+    let GiftEvent::Gift(gift) = event else {
+        panic!("not a gift: {:?}", event)
+    };
+    assert_eq!(gift.batch_combo_id, None);
+    assert_eq!(gift.medal, None);
+    assert_eq!(
+        gift.blind_gift,
+        Some(BlindGift {
+            original_gift_id: 32251,
+            original_gift_name: "心动盲盒".to_string(),
+            original_gift_price: 15000,
+            action: "爆出".to_string()
+        })
+    );
+    assert_eq!(gift.total_coin, 30000);
+}
+
+#[test]
+fn must_decode_gift_events() {
+    let event = GiftEvent::from_packet(&notification(&json!({
+        "cmd": "COMBO_SEND",
+        "data": {
+            "action": "投喂",
+            "batch_combo_id": "batch:gift:combo_id:174102117:5440:31036:1690027262.1234",
+            "batch_combo_num": 10,
+            "combo_num": 10,
+            "combo_total_coin": 1000,
+            "gift_id": 31036,
+            "gift_name": "小花花",
+            "medal_info": {"medal_level": 0, "medal_name": ""},
+            "uid": 174102117,
+            "uname": "vioIet・伊芙加登"
+        }
+    })))
+    .unwrap()
+    .unwrap();
+    let GiftEvent::ComboSend(combo) = event else {
+        panic!("not a combo: {:?}", event)
+    };
+    assert_eq!(
+        (combo.gift_id, combo.batch_combo_num, combo.combo_total_coin),
+        (31036, 10, 1000)
+    );
+
+    assert_eq!(
+        GiftEvent::from_packet(&notification(&json!({
+            "cmd": "GIFT_STAR_PROCESS",
+            "data": {"status": 1, "tip": "小花花已点亮"}
+        })))
+        .unwrap(),
+        Some(GiftEvent::StarProcess(GiftStarProcess {
+            status: 1,
+            tip: "小花花已点亮".to_string()
+        }))
+    );
+
+    assert_eq!(
+        GiftEvent::from_packet(&notification(&danmu_msg())).unwrap(),
+        None
+    );
+    assert!(GiftEvent::from_packet(&notification(&json!({"cmd": "SEND_GIFT"}))).is_err());
+}
+
+#[test]
+fn must_roundtrip_gift_combo_packet() {
+    let GiftEvent::Gift(gift) = GiftEvent::from_packet(&notification(&send_gift()))
+        .unwrap()
+        .unwrap()
+    else {
+        panic!("not a gift")
+    };
+    let mut combo = MergingCombo::start(&GiftEvent::Gift(gift.clone())).unwrap();
+    combo.merge(&GiftEvent::Gift(gift));
+    let combo = combo.finish();
+    assert_eq!((combo.num, combo.total_coin), (2, 200));
+
+    let decoded = GiftEvent::from_packet(&combo.to_packet()).unwrap();
+    assert_eq!(decoded, Some(GiftEvent::ComboFinished(combo)));
+}
+
+#[test]
+fn must_not_double_count_late_gifts() {
+    let GiftEvent::Gift(gift) = GiftEvent::from_packet(&notification(&send_gift()))
+        .unwrap()
+        .unwrap()
+    else {
+        panic!("not a gift")
+    };
+    let combo_send = |batch_combo_num: u32| {
+        GiftEvent::ComboSend(ComboSend {
+            gift_id: gift.gift_id,
+            gift_name: gift.gift_name.clone(),
+            action: gift.action.clone(),
+            uid: gift.uid,
+            uname: gift.uname.clone(),
+            medal: None,
+            batch_combo_id: gift.batch_combo_id.clone().unwrap(),
+            batch_combo_num,
+            combo_total_coin: 100 * u64::from(batch_combo_num),
+        })
+    };
+
+    // the progress arrives before the gifts it counts
+    let mut combo = MergingCombo::start(&combo_send(2)).unwrap();
+    combo.merge(&GiftEvent::Gift(gift.clone()));
+    combo.merge(&GiftEvent::Gift(gift.clone()));
+    let finished = combo.clone().finish();
+    assert_eq!((finished.num, finished.total_coin), (2, 200));
+    assert_eq!(finished.face, "https://i0.hdslb.com/face.jpg");
+
+    // more gifts than reported
+    combo.merge(&GiftEvent::Gift(gift));
+    let finished = combo.finish();
+    assert_eq!((finished.num, finished.total_coin), (3, 300));
+}
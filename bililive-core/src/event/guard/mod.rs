@@ -0,0 +1,226 @@
+use std::collections::{HashSet, VecDeque};
+
+use serde_json::Value;
+
+use crate::errors::ParseError;
+use crate::packet::Packet;
+
+use super::types::{as_string, as_u64};
+use super::{cmd, notification};
+
+#[cfg(test)]
+mod tests;
+
+const GUARD_BUY: &str = "GUARD_BUY";
+const USER_TOAST_MSG: &str = "USER_TOAST_MSG";
+const USER_TOAST_MSG_V2: &str = "USER_TOAST_MSG_V2";
+
+/// How many recent purchases are remembered for deduplication.
+const SEEN_CAPACITY: usize = 64;
+
+/// Guard (大航海) level.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum GuardLevel {
+    /// 总督
+    Governor,
+    /// 提督
+    Admiral,
+    /// 舰长
+    Captain,
+}
+
+impl GuardLevel {
+    /// Convert from the numeric level used by bilibili. Returns `None` if it's not a guard.
+    #[must_use]
+    pub const fn from_level(level: u8) -> Option<Self> {
+        match level {
+            1 => Some(Self::Governor),
+            2 => Some(Self::Admiral),
+            3 => Some(Self::Captain),
+            _ => None,
+        }
+    }
+
+    /// The numeric level used by bilibili.
+    #[must_use]
+    pub const fn level(self) -> u8 {
+        match self {
+            Self::Governor => 1,
+            Self::Admiral => 2,
+            Self::Captain => 3,
+        }
+    }
+
+    /// The role name, e.g. `舰长`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Governor => "总督",
+            Self::Admiral => "提督",
+            Self::Captain => "舰长",
+        }
+    }
+}
+
+/// A guard purchase or renewal.
+///
+/// Decoded from `GUARD_BUY`, `USER_TOAST_MSG` or `USER_TOAST_MSG_V2`. A purchase is usually
+/// announced by all of them, use [`GuardDedup`](GuardDedup) to handle it once.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct GuardPurchase {
+    /// User id of the buyer.
+    pub uid: u64,
+    /// User name of the buyer.
+    pub uname: String,
+    /// Guard level purchased.
+    pub guard_level: GuardLevel,
+    /// Role name, e.g. `舰长`.
+    pub role_name: String,
+    /// Number of units purchased.
+    pub num: u32,
+    /// Unit of purchase, e.g. `月`.
+    pub unit: String,
+    /// Price of each unit in gold seeds, where `1000` is 1 CNY.
+    pub unit_price: u64,
+    /// Price of all units in gold seeds.
+    pub total_price: u64,
+    /// Whether it renews an existing guard. Always `false` for `GUARD_BUY`, which doesn't tell.
+    pub is_renewal: bool,
+    /// Whether it's renewed automatically.
+    pub is_auto_renewal: bool,
+    /// Toast message, e.g. `<%user%> 开通了舰长`. `None` for `GUARD_BUY`.
+    pub toast_msg: Option<String>,
+    /// Unix timestamp (in seconds) when the guard starts.
+    pub start_time: u64,
+    /// Unix timestamp (in seconds) when the guard ends.
+    pub end_time: u64,
+}
+
+impl GuardPurchase {
+    /// Decode a `GUARD_BUY`, `USER_TOAST_MSG` or `USER_TOAST_MSG_V2` notification packet.
+    ///
+    /// Returns `Ok(None)` if the packet is not a guard purchase.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is a guard purchase but its payload is malformed.
+    pub fn from_packet(packet: &Packet) -> Result<Option<Self>, ParseError> {
+        notification(packet, &[GUARD_BUY, USER_TOAST_MSG, USER_TOAST_MSG_V2])?
+            .map(|value| Self::from_value(&value))
+            .transpose()
+    }
+
+    fn from_value(value: &Value) -> Result<Self, ParseError> {
+        let cmd = cmd(value).unwrap_or_default();
+        let data = value
+            .get("data")
+            .filter(|data| data.is_object())
+            .ok_or_else(|| ParseError::Event(format!("{} without data", cmd)))?;
+
+        // toast v2 groups fields by their purpose
+        let (guard_info, pay_info) = if cmd == USER_TOAST_MSG_V2 {
+            (&data["guard_info"], &data["pay_info"])
+        } else {
+            (data, data)
+        };
+        let guard_level = GuardLevel::from_level(as_u64(&guard_info["guard_level"]) as u8)
+            .ok_or_else(|| ParseError::Event(format!("{} without guard level", cmd)))?;
+        let num = as_u64(&pay_info["num"]) as u32;
+        let unit_price = as_u64(&pay_info["price"]);
+        let op_type = as_u64(&guard_info["op_type"]);
+
+        let (uid, uname, role_name) = match cmd {
+            GUARD_BUY => (
+                as_u64(&data["uid"]),
+                as_string(&data["username"]),
+                as_string(&data["gift_name"]),
+            ),
+            USER_TOAST_MSG => (
+                as_u64(&data["uid"]),
+                as_string(&data["username"]),
+                as_string(&data["role_name"]),
+            ),
+            _ => (
+                as_u64(&data["sender_uinfo"]["uid"]),
+                as_string(&data["sender_uinfo"]["base"]["name"]),
+                as_string(&guard_info["role_name"]),
+            ),
+        };
+
+        Ok(Self {
+            uid,
+            uname,
+            guard_level,
+            role_name,
+            num,
+            unit: pay_info["unit"]
+                .as_str()
+                .filter(|unit| !unit.is_empty())
+                .unwrap_or("月")
+                .to_string(),
+            unit_price,
+            total_price: unit_price * u64::from(num),
+            is_renewal: op_type == 2 || op_type == 3,
+            is_auto_renewal: op_type == 3,
+            toast_msg: (cmd != GUARD_BUY).then(|| as_string(&data["toast_msg"])),
+            start_time: as_u64(&guard_info["start_time"]),
+            end_time: as_u64(&guard_info["end_time"]),
+        })
+    }
+
+    /// A key identifying this purchase across its announcements.
+    fn dedup_key(&self) -> (u64, GuardLevel, u32, u64) {
+        (self.uid, self.guard_level, self.num, self.start_time)
+    }
+}
+
+/// Deduplicates guard purchases announced by several notifications.
+///
+/// The first announcement of a purchase is kept. Bilibili sends `GUARD_BUY` first, so call
+/// [`feed_toast`](Self::feed_toast) instead if renewal flags and toast messages are needed.
+#[derive(Debug, Clone, Default)]
+pub struct GuardDedup {
+    seen: HashSet<(u64, GuardLevel, u32, u64)>,
+    seen_order: VecDeque<(u64, GuardLevel, u32, u64)>,
+}
+
+impl GuardDedup {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a packet from the live stream. Returns the purchase if it's announced for the first
+    /// time.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is a guard purchase but its payload is malformed.
+    pub fn feed(&mut self, packet: &Packet) -> Result<Option<GuardPurchase>, ParseError> {
+        Ok(GuardPurchase::from_packet(packet)?.filter(|purchase| self.remember(purchase)))
+    }
+
+    /// Like [`feed`](Self::feed), but only toasts are considered, so that purchases always come
+    /// with renewal flags and toast messages.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is a guard purchase but its payload is malformed.
+    pub fn feed_toast(&mut self, packet: &Packet) -> Result<Option<GuardPurchase>, ParseError> {
+        Ok(GuardPurchase::from_packet(packet)?
+            .filter(|purchase| purchase.toast_msg.is_some() && self.remember(purchase)))
+    }
+
+    /// Remember a purchase. Returns `false` if it has been announced before.
+    pub fn remember(&mut self, purchase: &GuardPurchase) -> bool {
+        let key = purchase.dedup_key();
+        if self.seen.contains(&key) {
+            return false;
+        }
+        if self.seen_order.len() >= SEEN_CAPACITY {
+            if let Some(oldest) = self.seen_order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(key);
+        self.seen_order.push_back(key);
+        true
+    }
+}
@@ -0,0 +1,109 @@
+use serde_json::json;
+
+use crate::event::fixtures::{danmu_msg, notification};
+
+use super::{GuardDedup, GuardLevel, GuardPurchase};
+
+fn guard_announcements() -> [serde_json::Value; 3] {
+    [
+        json!({
+            "cmd": "GUARD_BUY",
+            "data": {
+                "uid": 174102117, "username": "vioIet・伊芙加登", "guard_level": 3, "num": 2,
+                "price": 198000, "gift_id": 10003, "gift_name": "舰长",
+                "start_time": 1690027262, "end_time": 1690027262
+            }
+        }),
+        json!({
+            "cmd": "USER_TOAST_MSG",
+            "data": {
+                "uid": 174102117, "username": "vioIet・伊芙加登", "guard_level": 3, "num": 2,
+                "price": 138000, "role_name": "舰长", "unit": "月", "op_type": 2,
+                "toast_msg": "<%vioIet・伊芙加登%> 续费了舰长",
+                "start_time": 1690027262, "end_time": 1690027262
+            }
+        }),
+        json!({
+            "cmd": "USER_TOAST_MSG_V2",
+            "data": {
+                "sender_uinfo": {"uid": 174102117, "base": {"name": "vioIet・伊芙加登"}},
+                "guard_info": {
+                    "guard_level": 3, "role_name": "舰长", "op_type": 2,
+                    "start_time": 1690027262, "end_time": 1690027262
+                },
+                "pay_info": {"price": 138000, "num": 2, "unit": "月"},
+                "toast_msg": "<%vioIet・伊芙加登%> 续费了舰长"
+            }
+        }),
+    ]
+}
+
+#[test]
+fn must_decode_guard_purchase() {
+    let [guard_buy, toast, toast_v2] = guard_announcements();
+
+    let purchase = GuardPurchase::from_packet(&notification(&guard_buy))
+        .expect("unable to decode guard purchase")
+        .expect("not a guard purchase");
+    assert_eq!(purchase.guard_level, GuardLevel::Captain);
+    assert_eq!(
+        (purchase.num, purchase.unit_price, purchase.total_price),
+        (2, 198000, 396000)
+    );
+    assert_eq!(purchase.role_name, "舰长");
+    assert!(!purchase.is_renewal);
+    assert_eq!(purchase.toast_msg, None);
+
+    let expected = GuardPurchase {
+        uid: 174102117,
+        uname: "vioIet・伊芙加登".to_string(),
+        guard_level: GuardLevel::Captain,
+        role_name: "舰长".to_string(),
+        num: 2,
+        unit: "月".to_string(),
+        unit_price: 138000,
+        total_price: 276000,
+        is_renewal: true,
+        is_auto_renewal: false,
+        toast_msg: Some("<%vioIet・伊芙加登%> 续费了舰长".to_string()),
+        start_time: 1690027262,
+        end_time: 1690027262,
+    };
+    for toast in [toast, toast_v2] {
+        assert_eq!(
+            GuardPurchase::from_packet(&notification(&toast)).unwrap(),
+            Some(expected.clone())
+        );
+    }
+
+    assert_eq!(GuardLevel::from_level(1), Some(GuardLevel::Governor));
+    assert_eq!(GuardLevel::from_level(0), None);
+    assert_eq!(GuardLevel::Admiral.name(), "提督");
+    assert!(GuardPurchase::from_packet(&notification(&json!({
+        "cmd": "GUARD_BUY",
+        "data": {"uid": 174102117, "guard_level": 0}
+    })))
+    .is_err());
+}
+
+#[test]
+fn must_dedup_guard_purchases() {
+    let packets = guard_announcements().map(|value| notification(&value));
+
+    let mut dedup = GuardDedup::new();
+    let kept: Vec<_> = packets
+        .iter()
+        .chain([notification(&danmu_msg())].iter())
+        .filter_map(|packet| dedup.feed(packet).unwrap())
+        .collect();
+    assert_eq!(kept.len(), 1);
+    assert_eq!(kept[0].toast_msg, None);
+
+    let mut dedup = GuardDedup::new();
+    let kept: Vec<_> = packets
+        .iter()
+        .filter_map(|packet| dedup.feed_toast(packet).unwrap())
+        .collect();
+    assert_eq!(kept.len(), 1);
+    assert!(kept[0].is_renewal);
+}
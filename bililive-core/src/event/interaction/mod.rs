@@ -0,0 +1,304 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use crate::errors::ParseError;
+use crate::packet::Packet;
+
+#[cfg(feature = "protobuf")]
+use super::proto;
+use super::types::{as_string, as_u64, FansMedal};
+use super::{cmd, notification};
+
+#[cfg(test)]
+mod tests;
+
+const INTERACT_WORD: &str = "INTERACT_WORD";
+const ENTRY_EFFECT: &str = "ENTRY_EFFECT";
+const ENTRY_EFFECT_MUST_RECEIVE: &str = "ENTRY_EFFECT_MUST_RECEIVE";
+const WELCOME_GUARD: &str = "WELCOME_GUARD";
+#[cfg(feature = "protobuf")]
+const INTERACT_WORD_V2: &str = "INTERACT_WORD_V2";
+
+/// What a user does in an [`Interaction`](Interaction).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum InteractAction {
+    /// Enters the room.
+    Enter,
+    /// Follows the anchor.
+    Follow,
+    /// Shares the room.
+    Share,
+    /// Specially follows (特别关注) the anchor.
+    SpecialFollow,
+    /// Follows the anchor, who follows the user back.
+    MutualFollow,
+    /// An action unknown to this crate, with its `msg_type`.
+    Other(u32),
+}
+
+impl InteractAction {
+    const fn from_msg_type(msg_type: u64) -> Self {
+        match msg_type {
+            1 => Self::Enter,
+            2 => Self::Follow,
+            3 => Self::Share,
+            4 => Self::SpecialFollow,
+            5 => Self::MutualFollow,
+            _ => Self::Other(msg_type as u32),
+        }
+    }
+}
+
+/// A user entering, following or sharing the room.
+///
+/// Decoded from `INTERACT_WORD`, or from `ENTRY_EFFECT`, `ENTRY_EFFECT_MUST_RECEIVE` and
+/// `WELCOME_GUARD` which announce special entrances. A guard entering is usually announced by
+/// both `INTERACT_WORD` and `ENTRY_EFFECT`, use [`InteractionDedup`](InteractionDedup) to handle
+/// it once.
+///
+/// With the `protobuf` feature, the protobuf-only `INTERACT_WORD_V2` is decoded as well.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Interaction {
+    /// What the user does.
+    pub action: InteractAction,
+    /// User id.
+    pub uid: u64,
+    /// User name.
+    pub uname: String,
+    /// Avatar url. Only available in entry effects.
+    pub face: String,
+    /// Guard level in this room. `0` if the user is not a guard.
+    pub guard_level: u8,
+    /// Fans medal worn by the user.
+    pub medal: Option<FansMedal>,
+    /// Identities of the user in this room.
+    pub identities: Vec<u32>,
+    /// Text of the entry effect, e.g. `欢迎舰长 <%user%> 进入直播间`. `None` if it's not a special
+    /// entrance.
+    pub entry_effect: Option<String>,
+    /// When the interaction happens. `None` if it's not reported.
+    pub trigger_time: Option<SystemTime>,
+}
+
+impl Interaction {
+    /// Decode an `INTERACT_WORD`, `ENTRY_EFFECT`, `ENTRY_EFFECT_MUST_RECEIVE` or `WELCOME_GUARD`
+    /// notification packet.
+    ///
+    /// Returns `Ok(None)` if the packet is not an interaction.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is an interaction but its payload is malformed.
+    pub fn from_packet(packet: &Packet) -> Result<Option<Self>, ParseError> {
+        #[cfg(feature = "protobuf")]
+        if let Some(value) = notification(packet, &[INTERACT_WORD_V2])? {
+            return Self::from_pb(&value).map(Some);
+        }
+
+        notification(
+            packet,
+            &[
+                INTERACT_WORD,
+                ENTRY_EFFECT,
+                ENTRY_EFFECT_MUST_RECEIVE,
+                WELCOME_GUARD,
+            ],
+        )?
+        .map(|value| Self::from_value(&value))
+        .transpose()
+    }
+
+    fn from_value(value: &Value) -> Result<Self, ParseError> {
+        let cmd = cmd(value).unwrap_or_default();
+        let data = value
+            .get("data")
+            .filter(|data| data.is_object())
+            .ok_or_else(|| ParseError::Event(format!("{} without data", cmd)))?;
+        // trigger times are in nanoseconds
+        let trigger_time = Some(as_u64(&data["trigger_time"]))
+            .filter(|nanos| *nanos != 0)
+            .map(|nanos| UNIX_EPOCH + Duration::from_nanos(nanos));
+
+        Ok(match cmd {
+            INTERACT_WORD => {
+                let medal = &data["fans_medal"];
+                Self {
+                    action: InteractAction::from_msg_type(as_u64(&data["msg_type"])),
+                    uid: as_u64(&data["uid"]),
+                    uname: as_string(&data["uname"]),
+                    face: String::new(),
+                    guard_level: as_u64(&medal["guard_level"]) as u8,
+                    medal: FansMedal::from_object(medal),
+                    identities: data["identities"]
+                        .as_array()
+                        .map(|ids| ids.iter().map(|id| as_u64(id) as u32).collect())
+                        .unwrap_or_default(),
+                    entry_effect: None,
+                    trigger_time: trigger_time.or_else(|| {
+                        Some(as_u64(&data["timestamp"]))
+                            .filter(|secs| *secs != 0)
+                            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+                    }),
+                }
+            }
+            WELCOME_GUARD => Self {
+                action: InteractAction::Enter,
+                uid: as_u64(&data["uid"]),
+                uname: as_string(&data["username"]),
+                face: String::new(),
+                guard_level: as_u64(&data["guard_level"]) as u8,
+                medal: None,
+                identities: Vec::new(),
+                entry_effect: None,
+                trigger_time: None,
+            },
+            _ => {
+                let copy_writing = as_string(&data["copy_writing_v2"]);
+                let copy_writing = if copy_writing.is_empty() {
+                    as_string(&data["copy_writing"])
+                } else {
+                    copy_writing
+                };
+                let base = &data["uinfo"]["base"];
+                let uname = base["name"].as_str().map_or_else(
+                    || {
+                        // the user name is only quoted in the text by older servers
+                        copy_writing
+                            .split_once("<%")
+                            .and_then(|(_, rest)| rest.split_once("%>"))
+                            .map(|(uname, _)| uname.to_string())
+                            .unwrap_or_default()
+                    },
+                    ToString::to_string,
+                );
+                Self {
+                    action: InteractAction::Enter,
+                    uid: as_u64(&data["uid"]),
+                    uname,
+                    face: as_string(&data["face"]),
+                    guard_level: as_u64(&data["privilege_type"]) as u8,
+                    medal: None,
+                    identities: data
+                        .get("identities")
+                        .map(|id| vec![as_u64(id) as u32])
+                        .unwrap_or_default(),
+                    entry_effect: Some(copy_writing),
+                    trigger_time,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(feature = "protobuf")]
+impl Interaction {
+    fn from_pb(value: &Value) -> Result<Self, ParseError> {
+        let pb = value["data"]["pb"]
+            .as_str()
+            .ok_or_else(|| ParseError::Event(format!("{} without pb", INTERACT_WORD_V2)))?;
+        let word: proto::InteractWord = proto::decode(pb)?;
+
+        let (uinfo_uid, base) = word
+            .uinfo
+            .map(|uinfo| (uinfo.uid, uinfo.base.unwrap_or_default()))
+            .unwrap_or_default();
+        let medal = word.fans_medal.unwrap_or_default();
+        let guard_level = if medal.guard_level > 0 {
+            medal.guard_level
+        } else {
+            word.privilege_type
+        };
+        let trigger_time = if word.trigger_time > 0 {
+            Some(UNIX_EPOCH + Duration::from_nanos(word.trigger_time as u64))
+        } else {
+            (word.timestamp > 0).then(|| UNIX_EPOCH + Duration::from_secs(word.timestamp as u64))
+        };
+
+        Ok(Self {
+            action: InteractAction::from_msg_type(word.msg_type as u64),
+            uid: if word.uid > 0 { word.uid } else { uinfo_uid } as u64,
+            uname: if word.uname.is_empty() {
+                base.name
+            } else {
+                word.uname
+            },
+            face: base.face,
+            guard_level: guard_level as u8,
+            medal: (medal.medal_level > 0 && !medal.medal_name.is_empty()).then(|| FansMedal {
+                level: medal.medal_level as u32,
+                name: medal.medal_name,
+                anchor_uname: String::new(),
+                anchor_room_id: medal.anchor_roomid as u64,
+                anchor_uid: medal.target_id as u64,
+                guard_level: medal.guard_level as u8,
+                is_lighted: medal.is_lighted != 0,
+            }),
+            identities: word.identities.iter().map(|id| *id as u32).collect(),
+            entry_effect: None,
+            trigger_time,
+        })
+    }
+}
+
+/// Deduplicates entrances announced by several notifications.
+///
+/// Entrances of the same user within the window are considered the same one, and the first
+/// announcement is kept. Other interactions are never dropped.
+#[derive(Debug, Clone)]
+pub struct InteractionDedup {
+    window: Duration,
+    /// recent entrances and when they happen
+    recent: VecDeque<(u64, SystemTime)>,
+}
+
+impl InteractionDedup {
+    /// Deduplicate entrances within the given window.
+    #[must_use]
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Feed a packet from the live stream. Returns the interaction unless it's a duplicated
+    /// entrance.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is an interaction but its payload is malformed.
+    pub fn feed(&mut self, packet: &Packet) -> Result<Option<Interaction>, ParseError> {
+        Ok(Interaction::from_packet(packet)?.filter(|interaction| self.remember(interaction)))
+    }
+
+    /// Remember an interaction. Returns `false` if it's an entrance announced before.
+    pub fn remember(&mut self, interaction: &Interaction) -> bool {
+        if interaction.action != InteractAction::Enter {
+            return true;
+        }
+        let time = interaction.trigger_time.unwrap_or_else(SystemTime::now);
+        let window = self.window;
+        let within = |other: SystemTime| {
+            time.duration_since(other)
+                .or_else(|_| other.duration_since(time))
+                .is_ok_and(|diff| diff <= window)
+        };
+
+        let is_new = !self
+            .recent
+            .iter()
+            .any(|(uid, other)| *uid == interaction.uid && within(*other));
+        if is_new {
+            self.recent.push_back((interaction.uid, time));
+        }
+        // forget entrances out of the window
+        while self
+            .recent
+            .front()
+            .is_some_and(|(_, other)| !within(*other) && *other < time)
+        {
+            self.recent.pop_front();
+        }
+        is_new
+    }
+}
@@ -0,0 +1,185 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde_json::json;
+
+use crate::errors::IncompleteResult;
+use crate::event::fixtures::{danmu_msg, notification};
+use crate::packet::Packet;
+
+use super::{InteractAction, Interaction, InteractionDedup};
+
+#[test]
+fn must_decode_interaction() {
+    let content = std::fs::read("tests/raw/buffer.packet").unwrap();
+    let IncompleteResult::Ok((_, packet)) = Packet::parse(&content) else {
+        panic!("error while parsing")
+    };
+    let interaction = Interaction::from_packet(&packet)
+        .expect("unable to decode interaction")
+        .expect("not an interaction");
+    assert_eq!(
+        interaction,
+        Interaction {
+            action: InteractAction::Enter,
+            uid: 174102117,
+            uname: "vioIet・伊芙加登".to_string(),
+            face: String::new(),
+            guard_level: 0,
+            medal: None,
+            identities: vec![1],
+            entry_effect: None,
+            trigger_time: Some(UNIX_EPOCH + Duration::from_nanos(1626324623404263200))
+        }
+    );
+
+    let decode = |value| {
+        Interaction::from_packet(&notification(&value))
+            .expect("unable to decode interaction")
+            .expect("not an interaction")
+    };
+    let follow = decode(json!({
+        "cmd": "INTERACT_WORD",
+        "data": {
+            "fans_medal": {"guard_level": 3, "is_lighted": 1, "medal_level": 21, "medal_name": "牌子", "target_id": 9617619},
+            "identities": [3, 1], "msg_type": 2, "timestamp": 1626324624, "uid": 174102117, "uname": "vioIet・伊芙加登"
+        }
+    }));
+    assert_eq!(follow.action, InteractAction::Follow);
+    assert_eq!(follow.guard_level, 3);
+    assert_eq!(follow.medal.map(|medal| medal.level), Some(21));
+    // falls back to the timestamp in seconds
+    assert_eq!(
+        follow.trigger_time,
+        Some(UNIX_EPOCH + Duration::from_secs(1626324624))
+    );
+    assert_eq!(
+        decode(json!({"cmd": "INTERACT_WORD", "data": {"msg_type": 7, "uid": 1}})).action,
+        InteractAction::Other(7)
+    );
+
+    let effect = decode(json!({
+        "cmd": "ENTRY_EFFECT",
+        "data": {
+            "uid": 174102117, "face": "https://i0.hdslb.com/face.jpg", "privilege_type": 3,
+            "copy_writing": "欢迎舰长 <%vioIet・伊芙加登%> 进入直播间", "identities": 6,
+            "trigger_time": 1626324623404263200i64
+        }
+    }));
+    assert_eq!(effect.action, InteractAction::Enter);
+    assert_eq!(effect.uname, "vioIet・伊芙加登");
+    assert_eq!(effect.guard_level, 3);
+    assert_eq!(effect.identities, [6]);
+    assert_eq!(
+        effect.entry_effect.as_deref(),
+        Some("欢迎舰长 <%vioIet・伊芙加登%> 进入直播间")
+    );
+
+    let welcome = decode(json!({
+        "cmd": "WELCOME_GUARD",
+        "data": {"uid": 174102117, "username": "vioIet・伊芙加登", "guard_level": 3}
+    }));
+    assert_eq!(
+        (welcome.action, welcome.uname.as_str(), welcome.trigger_time),
+        (InteractAction::Enter, "vioIet・伊芙加登", None)
+    );
+
+    assert_eq!(
+        Interaction::from_packet(&notification(&danmu_msg())).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn must_dedup_entrances() {
+    let enter = |uid: u64, msg_type: u64, nanos: u64| {
+        notification(&json!({
+            "cmd": "INTERACT_WORD",
+            "data": {"msg_type": msg_type, "uid": uid, "trigger_time": nanos}
+        }))
+    };
+    let effect = |uid: u64, nanos: u64| {
+        notification(&json!({
+            "cmd": "ENTRY_EFFECT",
+            "data": {"uid": uid, "privilege_type": 3, "trigger_time": nanos}
+        }))
+    };
+    let secs = |secs: u64| secs * 1_000_000_000;
+
+    let mut dedup = InteractionDedup::new(Duration::from_secs(5));
+    let kept: Vec<_> = [
+        enter(1, 1, secs(100)),
+        effect(1, secs(101)),
+        // follows are never dropped
+        enter(1, 2, secs(101)),
+        enter(1, 2, secs(101)),
+        enter(2, 1, secs(102)),
+        // enters again after the window
+        effect(1, secs(110)),
+        notification(&danmu_msg()),
+    ]
+    .iter()
+    .filter_map(|packet| dedup.feed(packet).unwrap())
+    .map(|interaction| {
+        (
+            interaction.uid,
+            interaction.action,
+            interaction.entry_effect.is_some(),
+        )
+    })
+    .collect();
+    assert_eq!(
+        kept,
+        [
+            (1, InteractAction::Enter, false),
+            (1, InteractAction::Follow, false),
+            (1, InteractAction::Follow, false),
+            (2, InteractAction::Enter, false),
+            (1, InteractAction::Enter, true)
+        ]
+    );
+}
+
+#[cfg(feature = "protobuf")]
+mod protobuf {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use serde_json::json;
+
+    use crate::event::fixtures::{fixture, notification};
+    use crate::event::{FansMedal, InteractAction, Interaction};
+
+    #[test]
+    fn must_decode_interact_word_v2() {
+        let interaction =
+            Interaction::from_packet(&notification(&fixture("tests/raw/interact_word_v2.json")))
+                .expect("unable to decode interaction")
+                .expect("not an interaction");
+        assert_eq!(
+            interaction,
+            Interaction {
+                action: InteractAction::Enter,
+                uid: 174102117,
+                uname: "vioIet・伊芙加登".to_string(),
+                face: "https://i0.hdslb.com/bfs/face/member/noface.jpg".to_string(),
+                guard_level: 3,
+                medal: Some(FansMedal {
+                    level: 21,
+                    name: "牌子".to_string(),
+                    anchor_uname: String::new(),
+                    anchor_room_id: 5440,
+                    anchor_uid: 9617619,
+                    guard_level: 3,
+                    is_lighted: true
+                }),
+                identities: vec![3, 1],
+                entry_effect: None,
+                trigger_time: Some(UNIX_EPOCH + Duration::from_nanos(1626324623404263200))
+            }
+        );
+
+        assert!(Interaction::from_packet(&notification(
+            &json!({"cmd": "INTERACT_WORD_V2", "data": {"pb": "not protobuf"}})
+        ))
+        .is_err());
+    }
+}
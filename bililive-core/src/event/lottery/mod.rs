@@ -0,0 +1,385 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use crate::errors::ParseError;
+use crate::packet::Packet;
+
+use super::types::{as_string, as_u64};
+use super::{cmd, notification};
+
+#[cfg(test)]
+mod tests;
+
+const ANCHOR_LOT_START: &str = "ANCHOR_LOT_START";
+const ANCHOR_LOT_CHECKSTATUS: &str = "ANCHOR_LOT_CHECKSTATUS";
+const ANCHOR_LOT_END: &str = "ANCHOR_LOT_END";
+const ANCHOR_LOT_AWARD: &str = "ANCHOR_LOT_AWARD";
+const POPULARITY_RED_POCKET_START: &str = "POPULARITY_RED_POCKET_START";
+const POPULARITY_RED_POCKET_WINNER_LIST: &str = "POPULARITY_RED_POCKET_WINNER_LIST";
+
+/// Who may enter an anchor lottery.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum LotteryRequirement {
+    /// Everyone.
+    None,
+    /// Followers of the anchor.
+    Follow,
+    /// Users with the room's fans medal at least the given level.
+    Medal(u32),
+    /// Guards at least the given guard level, where `1` is the highest.
+    Guard(u8),
+    /// A requirement unknown to this crate, with its `require_type` and `require_value`.
+    Other(u32, u64),
+}
+
+impl LotteryRequirement {
+    fn from_type(require_type: u64, require_value: u64) -> Self {
+        match require_type {
+            0 => Self::None,
+            1 => Self::Follow,
+            2 => Self::Medal(require_value as u32),
+            3 => Self::Guard(require_value as u8),
+            _ => Self::Other(require_type as u32, require_value),
+        }
+    }
+}
+
+/// A gift to send to enter a lottery.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct LotteryGift {
+    /// Gift id.
+    pub gift_id: u64,
+    /// Gift name.
+    pub gift_name: String,
+    /// Number of gifts to send.
+    pub num: u32,
+    /// Price of each gift in gold seeds, where `1000` is 1 CNY.
+    pub price: u64,
+}
+
+/// An award of a lottery.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct LotteryAward {
+    /// Award name.
+    pub name: String,
+    /// Award image url.
+    pub image: String,
+    /// Number of winners, or of gifts in a red pocket.
+    pub num: u32,
+}
+
+/// A winner of a lottery.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct LotteryWinner {
+    /// User id.
+    pub uid: u64,
+    /// User name.
+    pub uname: String,
+    /// Name of the award won.
+    pub award_name: String,
+    /// Number of awards won.
+    pub num: u32,
+}
+
+/// An anchor lottery (天选时刻).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AnchorLottery {
+    /// Lottery id.
+    pub id: u64,
+    /// Room id.
+    pub room_id: u64,
+    /// The award.
+    pub award: LotteryAward,
+    /// Danmaku to send to enter. Empty if entering doesn't require danmaku.
+    pub danmu: String,
+    /// Gift to send to enter. `None` if entering doesn't require gifts.
+    pub gift: Option<LotteryGift>,
+    /// Who may enter.
+    pub requirement: LotteryRequirement,
+    /// Requirement description, e.g. `当前主播粉丝勋章至少1级`.
+    pub require_text: String,
+    /// Duration of the lottery in seconds.
+    pub max_time: u64,
+    /// Unix timestamp (in seconds) when the lottery is drawn.
+    pub end_time: u64,
+}
+
+/// A popularity red pocket (人气红包).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RedPocket {
+    /// Lottery id.
+    pub lot_id: u64,
+    /// User id of the sender.
+    pub sender_uid: u64,
+    /// User name of the sender.
+    pub sender_name: String,
+    /// Avatar url of the sender.
+    pub sender_face: String,
+    /// Danmaku sent when entering.
+    pub danmu: String,
+    /// Gifts in the red pocket.
+    pub awards: Vec<LotteryAward>,
+    /// Price of all gifts in gold seeds.
+    pub total_price: u64,
+    /// Unix timestamp (in seconds) when the red pocket starts.
+    pub start_time: u64,
+    /// Unix timestamp (in seconds) when the red pocket is drawn.
+    pub end_time: u64,
+}
+
+/// A lottery or red pocket notification.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum LotteryEvent {
+    /// An anchor lottery starts (`ANCHOR_LOT_START`).
+    AnchorStart(AnchorLottery),
+    /// Review status of an anchor lottery is changed (`ANCHOR_LOT_CHECKSTATUS`).
+    AnchorCheckStatus {
+        id: u64,
+        status: u32,
+        /// Why the lottery is rejected. Empty if it's not.
+        reject_reason: String,
+    },
+    /// An anchor lottery stops accepting entries (`ANCHOR_LOT_END`).
+    AnchorEnd { id: u64 },
+    /// An anchor lottery is drawn (`ANCHOR_LOT_AWARD`).
+    AnchorAward {
+        id: u64,
+        award: LotteryAward,
+        winners: Vec<LotteryWinner>,
+    },
+    /// A red pocket starts (`POPULARITY_RED_POCKET_START`).
+    RedPocketStart(RedPocket),
+    /// A red pocket is drawn (`POPULARITY_RED_POCKET_WINNER_LIST`).
+    RedPocketWinners {
+        lot_id: u64,
+        /// Number of users entered.
+        total_num: u64,
+        winners: Vec<LotteryWinner>,
+    },
+}
+
+impl LotteryEvent {
+    /// Decode a lottery or red pocket notification packet.
+    ///
+    /// Returns `Ok(None)` if the packet is not one.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is a lottery notification but its payload is malformed.
+    pub fn from_packet(packet: &Packet) -> Result<Option<Self>, ParseError> {
+        notification(
+            packet,
+            &[
+                ANCHOR_LOT_START,
+                ANCHOR_LOT_CHECKSTATUS,
+                ANCHOR_LOT_END,
+                ANCHOR_LOT_AWARD,
+                POPULARITY_RED_POCKET_START,
+                POPULARITY_RED_POCKET_WINNER_LIST,
+            ],
+        )?
+        .map(|value| Self::from_value(&value))
+        .transpose()
+    }
+
+    fn from_value(value: &Value) -> Result<Self, ParseError> {
+        let cmd = cmd(value).unwrap_or_default();
+        let data = value
+            .get("data")
+            .filter(|data| data.is_object())
+            .ok_or_else(|| ParseError::Event(format!("{} without data", cmd)))?;
+        let list = |key: &str| data[key].as_array().cloned().unwrap_or_default();
+
+        Ok(match cmd {
+            ANCHOR_LOT_START => {
+                let gift_id = as_u64(&data["gift_id"]);
+                Self::AnchorStart(AnchorLottery {
+                    id: as_u64(&data["id"]),
+                    room_id: as_u64(&data["room_id"]),
+                    award: LotteryAward {
+                        name: as_string(&data["award_name"]),
+                        image: as_string(&data["award_image"]),
+                        num: as_u64(&data["award_num"]) as u32,
+                    },
+                    danmu: as_string(&data["danmu"]),
+                    gift: (gift_id != 0).then(|| LotteryGift {
+                        gift_id,
+                        gift_name: as_string(&data["gift_name"]),
+                        num: as_u64(&data["gift_num"]) as u32,
+                        price: as_u64(&data["gift_price"]),
+                    }),
+                    requirement: LotteryRequirement::from_type(
+                        as_u64(&data["require_type"]),
+                        as_u64(&data["require_value"]),
+                    ),
+                    require_text: as_string(&data["require_text"]),
+                    max_time: as_u64(&data["max_time"]),
+                    // `time` is the countdown when the notification is sent
+                    end_time: as_u64(&data["current_time"]) + as_u64(&data["time"]),
+                })
+            }
+            ANCHOR_LOT_CHECKSTATUS => Self::AnchorCheckStatus {
+                id: as_u64(&data["id"]),
+                status: as_u64(&data["status"]) as u32,
+                reject_reason: as_string(&data["reject_reason"]),
+            },
+            ANCHOR_LOT_END => Self::AnchorEnd {
+                id: as_u64(&data["id"]),
+            },
+            ANCHOR_LOT_AWARD => {
+                let award = LotteryAward {
+                    name: as_string(&data["award_name"]),
+                    image: as_string(&data["award_image"]),
+                    num: as_u64(&data["award_num"]) as u32,
+                };
+                Self::AnchorAward {
+                    id: as_u64(&data["id"]),
+                    winners: list("award_users")
+                        .iter()
+                        .map(|user| LotteryWinner {
+                            uid: as_u64(&user["uid"]),
+                            uname: as_string(&user["uname"]),
+                            award_name: award.name.clone(),
+                            num: user.get("num").map_or(1, |num| as_u64(num) as u32),
+                        })
+                        .collect(),
+                    award,
+                }
+            }
+            POPULARITY_RED_POCKET_START => Self::RedPocketStart(RedPocket {
+                lot_id: as_u64(&data["lot_id"]),
+                sender_uid: as_u64(&data["sender_uid"]),
+                sender_name: as_string(&data["sender_name"]),
+                sender_face: as_string(&data["sender_face"]),
+                danmu: as_string(&data["danmu"]),
+                awards: list("awards")
+                    .iter()
+                    .map(|award| LotteryAward {
+                        name: as_string(&award["gift_name"]),
+                        image: as_string(&award["gift_pic"]),
+                        num: as_u64(&award["num"]) as u32,
+                    })
+                    .collect(),
+                total_price: as_u64(&data["total_price"]),
+                start_time: as_u64(&data["start_time"]),
+                end_time: as_u64(&data["end_time"]),
+            }),
+            _ => {
+                // winners are `[uid, uname, bag_id, gift_id, ...]`, and awards are keyed by
+                // gift id
+                let awards = &data["awards"];
+                Self::RedPocketWinners {
+                    lot_id: as_u64(&data["lot_id"]),
+                    total_num: as_u64(&data["total_num"]),
+                    winners: list("winner_info")
+                        .iter()
+                        .map(|winner| {
+                            let gift_id = as_u64(&winner[3]).to_string();
+                            LotteryWinner {
+                                uid: as_u64(&winner[0]),
+                                uname: as_string(&winner[1]),
+                                award_name: as_string(&awards[gift_id.as_str()]["award_name"]),
+                                num: 1,
+                            }
+                        })
+                        .collect(),
+                }
+            }
+        })
+    }
+}
+
+/// Tracks lotteries and red pockets running in a room.
+///
+/// Entering them sends danmaku with a fixed text, which usually should be excluded from chat
+/// analytics. Feed it with packets from the live stream, and check danmaku with
+/// [`is_entry`](Self::is_entry). Lotteries are removed when they are drawn, or by
+/// [`expire`](Self::expire) if the result is missed.
+#[derive(Debug, Clone, Default)]
+pub struct LotteryTracker {
+    anchor: Vec<AnchorLottery>,
+    red_pockets: Vec<RedPocket>,
+}
+
+impl LotteryTracker {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a packet from the live stream. Returns the event if it's a lottery notification.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is a lottery notification but its payload is malformed.
+    pub fn feed(&mut self, packet: &Packet) -> Result<Option<LotteryEvent>, ParseError> {
+        let event = LotteryEvent::from_packet(packet)?;
+        if let Some(event) = &event {
+            self.apply(event);
+        }
+        Ok(event)
+    }
+
+    /// Apply a lottery notification.
+    pub fn apply(&mut self, event: &LotteryEvent) {
+        match event {
+            LotteryEvent::AnchorStart(lottery) => {
+                self.anchor.retain(|existing| existing.id != lottery.id);
+                self.anchor.push(lottery.clone());
+            }
+            LotteryEvent::AnchorAward { id, .. } => {
+                self.anchor.retain(|existing| existing.id != *id);
+            }
+            LotteryEvent::RedPocketStart(red_pocket) => {
+                self.red_pockets
+                    .retain(|existing| existing.lot_id != red_pocket.lot_id);
+                self.red_pockets.push(red_pocket.clone());
+            }
+            LotteryEvent::RedPocketWinners { lot_id, .. } => {
+                self.red_pockets
+                    .retain(|existing| existing.lot_id != *lot_id);
+            }
+            // entries sent right before the end may still arrive
+            LotteryEvent::AnchorCheckStatus { .. } | LotteryEvent::AnchorEnd { .. } => {}
+        }
+    }
+
+    /// Whether the danmaku text is sent to enter a running lottery or red pocket.
+    #[must_use]
+    pub fn is_entry(&self, text: &str) -> bool {
+        let text = text.trim();
+        !text.is_empty()
+            && self
+                .anchor
+                .iter()
+                .map(|lottery| &lottery.danmu)
+                .chain(self.red_pockets.iter().map(|red_pocket| &red_pocket.danmu))
+                .any(|danmu| danmu.trim() == text)
+    }
+
+    /// Remove lotteries and red pockets drawn by now.
+    pub fn expire(&mut self) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        self.expire_at(now);
+    }
+
+    /// Remove lotteries and red pockets drawn by the given unix timestamp (in seconds).
+    pub fn expire_at(&mut self, now: u64) {
+        self.anchor.retain(|lottery| lottery.end_time > now);
+        self.red_pockets
+            .retain(|red_pocket| red_pocket.end_time > now);
+    }
+
+    /// Anchor lotteries running, in the order they start.
+    #[must_use]
+    pub fn anchor_lotteries(&self) -> &[AnchorLottery] {
+        &self.anchor
+    }
+
+    /// Red pockets running, in the order they start.
+    #[must_use]
+    pub fn red_pockets(&self) -> &[RedPocket] {
+        &self.red_pockets
+    }
+}
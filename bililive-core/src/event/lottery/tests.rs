@@ -0,0 +1,166 @@
+use serde_json::json;
+
+use crate::event::fixtures::{danmu_msg, notification};
+
+use super::{
+    AnchorLottery, LotteryAward, LotteryEvent, LotteryGift, LotteryRequirement, LotteryTracker,
+    LotteryWinner,
+};
+
+#[test]
+fn must_decode_lottery_events() {
+    let decode = |value| {
+        LotteryEvent::from_packet(&notification(&value))
+            .expect("unable to decode lottery event")
+            .expect("not a lottery event")
+    };
+
+    let start = decode(json!({
+        "cmd": "ANCHOR_LOT_START",
+        "data": {
+            "id": 2468, "room_id": 23090051, "award_name": "手办", "award_num": 2, "award_image": "",
+            "danmu": "冲冲冲", "gift_id": 31036, "gift_name": "小花花", "gift_num": 1, "gift_price": 100,
+            "require_type": 2, "require_value": 1, "require_text": "当前主播粉丝勋章至少1级",
+            "max_time": 600, "current_time": 1626324624, "time": 599
+        }
+    }));
+    assert_eq!(
+        start,
+        LotteryEvent::AnchorStart(AnchorLottery {
+            id: 2468,
+            room_id: 23090051,
+            award: LotteryAward {
+                name: "手办".to_string(),
+                image: String::new(),
+                num: 2
+            },
+            danmu: "冲冲冲".to_string(),
+            gift: Some(LotteryGift {
+                gift_id: 31036,
+                gift_name: "小花花".to_string(),
+                num: 1,
+                price: 100
+            }),
+            requirement: LotteryRequirement::Medal(1),
+            require_text: "当前主播粉丝勋章至少1级".to_string(),
+            max_time: 600,
+            end_time: 1626325223
+        })
+    );
+    let LotteryEvent::AnchorStart(free) = decode(json!({
+        "cmd": "ANCHOR_LOT_START",
+        "data": {"id": 2469, "danmu": "", "gift_id": 0, "require_type": 9, "require_value": 3}
+    })) else {
+        panic!("not a lottery start")
+    };
+    assert_eq!(
+        (free.gift, free.requirement),
+        (None, LotteryRequirement::Other(9, 3))
+    );
+
+    assert_eq!(
+        decode(json!({"cmd": "ANCHOR_LOT_END", "data": {"id": 2468}})),
+        LotteryEvent::AnchorEnd { id: 2468 }
+    );
+    let LotteryEvent::AnchorAward { id, award, winners } = decode(json!({
+        "cmd": "ANCHOR_LOT_AWARD",
+        "data": {
+            "id": 2468, "award_name": "手办", "award_num": 2, "award_image": "",
+            "award_users": [
+                {"uid": 174102117, "uname": "vioIet・伊芙加登", "num": 1},
+                {"uid": 9617619, "uname": "无"}
+            ]
+        }
+    })) else {
+        panic!("not a lottery award")
+    };
+    assert_eq!((id, award.num), (2468, 2));
+    assert_eq!(
+        winners
+            .iter()
+            .map(|winner| (winner.uid, winner.award_name.as_str(), winner.num))
+            .collect::<Vec<_>>(),
+        [(174102117, "手办", 1), (9617619, "手办", 1)]
+    );
+
+    let LotteryEvent::RedPocketStart(red_pocket) = decode(json!({
+        "cmd": "POPULARITY_RED_POCKET_START",
+        "data": {
+            "lot_id": 1357, "sender_uid": 174102117, "sender_name": "vioIet・伊芙加登", "sender_face": "",
+            "danmu": "老板大气！点点红包抽礼物", "total_price": 16000,
+            "awards": [{"gift_id": 31212, "gift_name": "打call", "gift_pic": "", "num": 2}],
+            "start_time": 1626324624, "end_time": 1626324804
+        }
+    })) else {
+        panic!("not a red pocket")
+    };
+    assert_eq!(red_pocket.awards[0].name, "打call");
+    assert_eq!(red_pocket.end_time, 1626324804);
+    assert_eq!(
+        decode(json!({
+            "cmd": "POPULARITY_RED_POCKET_WINNER_LIST",
+            "data": {
+                "lot_id": 1357, "total_num": 42,
+                "winner_info": [[174102117, "vioIet・伊芙加登", 5190, 31212]],
+                "awards": {"31212": {"award_name": "打call", "award_price": 500}}
+            }
+        })),
+        LotteryEvent::RedPocketWinners {
+            lot_id: 1357,
+            total_num: 42,
+            winners: vec![LotteryWinner {
+                uid: 174102117,
+                uname: "vioIet・伊芙加登".to_string(),
+                award_name: "打call".to_string(),
+                num: 1
+            }]
+        }
+    );
+
+    assert_eq!(
+        LotteryEvent::from_packet(&notification(&danmu_msg())).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn must_track_lottery_entries() {
+    let mut tracker = LotteryTracker::new();
+    assert!(!tracker.is_entry("冲冲冲"));
+
+    tracker
+        .feed(&notification(&json!({
+            "cmd": "ANCHOR_LOT_START",
+            "data": {"id": 2468, "danmu": "冲冲冲", "current_time": 100, "time": 60}
+        })))
+        .unwrap();
+    tracker
+        .feed(&notification(&json!({
+            "cmd": "POPULARITY_RED_POCKET_START",
+            "data": {"lot_id": 1357, "danmu": "老板大气！点点红包抽礼物", "end_time": 300}
+        })))
+        .unwrap();
+    assert!(tracker.is_entry("冲冲冲"));
+    assert!(tracker.is_entry(" 老板大气！点点红包抽礼物"));
+    assert!(!tracker.is_entry("冲冲冲冲"));
+    assert!(!tracker.is_entry(""));
+
+    // entries may arrive after the lottery stops accepting them
+    tracker
+        .feed(&notification(
+            &json!({"cmd": "ANCHOR_LOT_END", "data": {"id": 2468}}),
+        ))
+        .unwrap();
+    assert!(tracker.is_entry("冲冲冲"));
+    tracker
+        .feed(&notification(
+            &json!({"cmd": "ANCHOR_LOT_AWARD", "data": {"id": 2468, "award_users": []}}),
+        ))
+        .unwrap();
+    assert!(!tracker.is_entry("冲冲冲"));
+    assert_eq!(tracker.red_pockets().len(), 1);
+
+    tracker.expire_at(300);
+    assert!(!tracker.is_entry("老板大气！点点红包抽礼物"));
+    assert!(tracker.red_pockets().is_empty());
+}
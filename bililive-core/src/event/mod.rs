@@ -0,0 +1,105 @@
+//! Typed events decoded from notification packets.
+//!
+//! Bilibili pushes most live events as json [`Notification`](crate::packet::Operation::Notification)
+//! packets, each with a `cmd` field indicating its type. Types in this module decode those payloads.
+//!
+//! Each event type provides a `from_packet` function, which returns `Ok(None)` if the packet is not
+//! of its type, so that they can be tried one by one.
+//!
+//! Combos of gifts are merged into [`GiftCombo`](GiftCombo) by
+//! [`GiftComboStream`](crate::stream::GiftComboStream).
+//!
+//! Super chats currently pinned in a room are tracked by [`SuperChatTracker`](SuperChatTracker).
+//!
+//! Guard purchases announced by several notifications are deduplicated by
+//! [`GuardDedup`](GuardDedup).
+//!
+//! Uids masked in anonymous connections are recovered from
+//! [`Danmaku::user_hash`](Danmaku::user_hash) by [`UidCracker`](UidCracker) and
+//! [`UidResolver`](UidResolver).
+//!
+//! Entrances announced by several notifications are deduplicated by
+//! [`InteractionDedup`](InteractionDedup).
+//!
+//! Danmaku sent to enter lotteries and red pockets are told apart by
+//! [`LotteryTracker`](LotteryTracker).
+//!
+//! The current state of a room is kept up to date with room notifications by
+//! [`RoomState`](RoomState).
+//!
+//! Audience metrics, including the popularity in heartbeat responses, are collected by
+//! [`AudienceStats`](AudienceStats).
+//!
+//! PK battles of a room are followed through their phases by [`PkTracker`](PkTracker).
+//!
+//! Messages pushed in open platform app sessions (see [`open`](crate::open)) are decoded by
+//! [`OpenEvent`](OpenEvent).
+
+use serde_json::Value;
+
+pub use audience::{AudienceEvent, AudienceStats, HotRank, RankMessage, RankUser};
+pub use danmaku::{CheckInfo, Danmaku, DanmakuReply, Emoticon};
+pub(crate) use gift::MergingCombo;
+pub use gift::{BlindGift, CoinType, ComboSend, Gift, GiftCombo, GiftEvent, GiftStarProcess};
+pub use guard::{GuardDedup, GuardLevel, GuardPurchase};
+pub use interaction::{InteractAction, Interaction, InteractionDedup};
+pub use lottery::{
+    AnchorLottery, LotteryAward, LotteryEvent, LotteryGift, LotteryRequirement, LotteryTracker,
+    LotteryWinner, RedPocket,
+};
+pub use open::{
+    OpenDanmaku, OpenEvent, OpenGift, OpenGuard, OpenLike, OpenMedal, OpenSuperChat,
+    OpenSuperChatDelete, OpenUser,
+};
+pub use pk::{PkBattle, PkEvent, PkEventKind, PkPhase, PkResult, PkScore, PkSide, PkTracker};
+pub use room::{RoomEvent, RoomSilent, RoomState, SilentMode};
+pub use super_chat::{
+    SuperChat, SuperChatColors, SuperChatDelete, SuperChatEvent, SuperChatTracker,
+};
+pub use types::FansMedal;
+pub use uid::{CrackedUid, UidCracker, UidResolver};
+
+use crate::errors::ParseError;
+use crate::packet::{Operation, Packet};
+
+mod audience;
+mod danmaku;
+#[cfg(test)]
+mod fixtures;
+mod gift;
+mod guard;
+mod interaction;
+mod lottery;
+mod open;
+mod pk;
+#[cfg(feature = "protobuf")]
+mod proto;
+mod room;
+mod super_chat;
+#[cfg(test)]
+mod tests;
+mod types;
+mod uid;
+
+/// Get the command of a notification payload.
+///
+/// Suffixes like `:4:0:2:2:2:0` in `DANMU_MSG:4:0:2:2:2:0` are stripped.
+#[must_use]
+pub fn cmd(value: &Value) -> Option<&str> {
+    value
+        .get("cmd")
+        .and_then(Value::as_str)
+        .map(|cmd| cmd.split(':').next().unwrap_or(cmd))
+}
+
+/// Parse the json payload of a notification packet if its command is one of `cmds`.
+pub(crate) fn notification(packet: &Packet, cmds: &[&str]) -> Result<Option<Value>, ParseError> {
+    if packet.op() != Operation::Notification {
+        return Ok(None);
+    }
+    let value: Value = packet.json()?;
+    Ok(cmd(&value)
+        .filter(|cmd| cmds.contains(cmd))
+        .is_some()
+        .then_some(value))
+}
@@ -0,0 +1,309 @@
+use serde_json::Value;
+
+use crate::errors::ParseError;
+use crate::packet::Packet;
+
+use super::types::{as_string, as_u64};
+use super::{cmd, notification};
+
+#[cfg(test)]
+mod tests;
+
+const DM: &str = "LIVE_OPEN_PLATFORM_DM";
+const SEND_GIFT: &str = "LIVE_OPEN_PLATFORM_SEND_GIFT";
+const SUPER_CHAT: &str = "LIVE_OPEN_PLATFORM_SUPER_CHAT";
+const SUPER_CHAT_DEL: &str = "LIVE_OPEN_PLATFORM_SUPER_CHAT_DEL";
+const GUARD: &str = "LIVE_OPEN_PLATFORM_GUARD";
+const LIKE: &str = "LIVE_OPEN_PLATFORM_LIKE";
+
+/// A user in open platform messages.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OpenUser {
+    /// User id. `0` if it's not disclosed to the app.
+    pub uid: u64,
+    /// Open id of the user, which is unique per app.
+    pub open_id: String,
+    /// User name.
+    pub uname: String,
+    /// Avatar url.
+    pub uface: String,
+}
+
+impl OpenUser {
+    fn from_value(value: &Value) -> Self {
+        Self {
+            uid: as_u64(&value["uid"]),
+            open_id: as_string(&value["open_id"]),
+            uname: as_string(&value["uname"]),
+            uface: as_string(&value["uface"]),
+        }
+    }
+}
+
+/// The fans medal of the anchor's room, in open platform messages.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OpenMedal {
+    /// Medal name.
+    pub name: String,
+    /// Medal level.
+    pub level: u32,
+    /// Whether the user is wearing it.
+    pub is_wearing: bool,
+}
+
+impl OpenMedal {
+    /// Returns `None` if the user has no medal of the anchor's room.
+    fn from_value(value: &Value) -> Option<Self> {
+        let name = value["fans_medal_name"]
+            .as_str()
+            .filter(|name| !name.is_empty())?;
+        Some(Self {
+            name: name.to_string(),
+            level: as_u64(&value["fans_medal_level"]) as u32,
+            is_wearing: value["fans_medal_wearing_status"].as_bool() == Some(true),
+        })
+    }
+}
+
+/// A danmaku (`LIVE_OPEN_PLATFORM_DM`).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OpenDanmaku {
+    /// Live room id.
+    pub room_id: u64,
+    /// Sender.
+    pub user: OpenUser,
+    /// Message text.
+    pub msg: String,
+    /// Message id, unique per message.
+    pub msg_id: String,
+    /// `0` for text and `1` for emoticon.
+    pub dm_type: u32,
+    /// Image url of the emoticon. Empty if it's a text danmaku.
+    pub emoji_img_url: String,
+    /// Guard level of the sender. `0` if the sender is not a guard.
+    pub guard_level: u8,
+    /// Fans medal of the sender.
+    pub medal: Option<OpenMedal>,
+    /// Unix timestamp (in seconds) when the danmaku is sent.
+    pub timestamp: u64,
+}
+
+/// A gift (`LIVE_OPEN_PLATFORM_SEND_GIFT`).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OpenGift {
+    /// Live room id.
+    pub room_id: u64,
+    /// Sender.
+    pub user: OpenUser,
+    /// Gift id.
+    pub gift_id: u64,
+    /// Gift name.
+    pub gift_name: String,
+    /// Number of gifts.
+    pub gift_num: u32,
+    /// Unit price, where `1000` is 1 CNY.
+    pub price: u64,
+    /// Whether it's a paid gift.
+    pub paid: bool,
+    /// Icon url of the gift.
+    pub gift_icon: String,
+    /// Message id, unique per message.
+    pub msg_id: String,
+    /// Guard level of the sender. `0` if the sender is not a guard.
+    pub guard_level: u8,
+    /// Fans medal of the sender.
+    pub medal: Option<OpenMedal>,
+    /// Unix timestamp (in seconds) when the gift is sent.
+    pub timestamp: u64,
+}
+
+/// A super chat (`LIVE_OPEN_PLATFORM_SUPER_CHAT`).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OpenSuperChat {
+    /// Live room id.
+    pub room_id: u64,
+    /// Sender.
+    pub user: OpenUser,
+    /// Super chat id, referred by [`OpenSuperChatDelete`](OpenSuperChatDelete).
+    pub message_id: u64,
+    /// Message text.
+    pub message: String,
+    /// Price in CNY.
+    pub rmb: u64,
+    /// Message id, unique per message.
+    pub msg_id: String,
+    /// Guard level of the sender. `0` if the sender is not a guard.
+    pub guard_level: u8,
+    /// Fans medal of the sender.
+    pub medal: Option<OpenMedal>,
+    /// Unix timestamp (in seconds) when the super chat is sent.
+    pub timestamp: u64,
+    /// Unix timestamp (in seconds) when the super chat starts to be pinned.
+    pub start_time: u64,
+    /// Unix timestamp (in seconds) when the super chat stops being pinned.
+    pub end_time: u64,
+}
+
+/// Removal of super chats (`LIVE_OPEN_PLATFORM_SUPER_CHAT_DEL`).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OpenSuperChatDelete {
+    /// Live room id.
+    pub room_id: u64,
+    /// Ids of the removed super chats.
+    pub message_ids: Vec<u64>,
+    /// Message id, unique per message.
+    pub msg_id: String,
+}
+
+/// A guard purchase (`LIVE_OPEN_PLATFORM_GUARD`).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OpenGuard {
+    /// Live room id.
+    pub room_id: u64,
+    /// Buyer.
+    pub user: OpenUser,
+    /// Guard level. `1` for 总督, `2` for 提督 and `3` for 舰长.
+    pub guard_level: u8,
+    /// Number of units purchased.
+    pub guard_num: u32,
+    /// Unit of purchase, e.g. `月`.
+    pub guard_unit: String,
+    /// Price, where `1000` is 1 CNY.
+    pub price: u64,
+    /// Message id, unique per message.
+    pub msg_id: String,
+    /// Fans medal of the buyer.
+    pub medal: Option<OpenMedal>,
+    /// Unix timestamp (in seconds) when the guard is purchased.
+    pub timestamp: u64,
+}
+
+/// Likes (`LIVE_OPEN_PLATFORM_LIKE`).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OpenLike {
+    /// Live room id.
+    pub room_id: u64,
+    /// User who likes.
+    pub user: OpenUser,
+    /// Prompt text, e.g. `为主播点赞了`.
+    pub like_text: String,
+    /// Number of likes.
+    pub like_count: u32,
+    /// Message id, unique per message.
+    pub msg_id: String,
+    /// Fans medal of the user.
+    pub medal: Option<OpenMedal>,
+    /// Unix timestamp (in seconds) when the likes are sent.
+    pub timestamp: u64,
+}
+
+/// A message pushed in an open platform app session.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum OpenEvent {
+    Danmaku(OpenDanmaku),
+    Gift(OpenGift),
+    SuperChat(OpenSuperChat),
+    SuperChatDelete(OpenSuperChatDelete),
+    Guard(OpenGuard),
+    Like(OpenLike),
+}
+
+impl OpenEvent {
+    /// Decode an open platform notification packet.
+    ///
+    /// Returns `Ok(None)` if the packet is not an open platform message.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is an open platform message but its payload is malformed.
+    pub fn from_packet(packet: &Packet) -> Result<Option<Self>, ParseError> {
+        notification(
+            packet,
+            &[DM, SEND_GIFT, SUPER_CHAT, SUPER_CHAT_DEL, GUARD, LIKE],
+        )?
+        .map(|value| Self::from_value(&value))
+        .transpose()
+    }
+
+    fn from_value(value: &Value) -> Result<Self, ParseError> {
+        let cmd = cmd(value).unwrap_or_default();
+        let data = value
+            .get("data")
+            .filter(|data| data.is_object())
+            .ok_or_else(|| ParseError::Event(format!("{} without data", cmd)))?;
+        let room_id = as_u64(&data["room_id"]);
+        let user = OpenUser::from_value(data);
+        let msg_id = as_string(&data["msg_id"]);
+        let guard_level = as_u64(&data["guard_level"]) as u8;
+        let medal = OpenMedal::from_value(data);
+        let timestamp = as_u64(&data["timestamp"]);
+
+        Ok(match cmd {
+            DM => Self::Danmaku(OpenDanmaku {
+                room_id,
+                user,
+                msg: as_string(&data["msg"]),
+                msg_id,
+                dm_type: as_u64(&data["dm_type"]) as u32,
+                emoji_img_url: as_string(&data["emoji_img_url"]),
+                guard_level,
+                medal,
+                timestamp,
+            }),
+            SEND_GIFT => Self::Gift(OpenGift {
+                room_id,
+                user,
+                gift_id: as_u64(&data["gift_id"]),
+                gift_name: as_string(&data["gift_name"]),
+                gift_num: as_u64(&data["gift_num"]) as u32,
+                price: as_u64(&data["price"]),
+                paid: data["paid"].as_bool() == Some(true),
+                gift_icon: as_string(&data["gift_icon"]),
+                msg_id,
+                guard_level,
+                medal,
+                timestamp,
+            }),
+            SUPER_CHAT => Self::SuperChat(OpenSuperChat {
+                room_id,
+                user,
+                message_id: as_u64(&data["message_id"]),
+                message: as_string(&data["message"]),
+                rmb: as_u64(&data["rmb"]),
+                msg_id,
+                guard_level,
+                medal,
+                timestamp,
+                start_time: as_u64(&data["start_time"]),
+                end_time: as_u64(&data["end_time"]),
+            }),
+            SUPER_CHAT_DEL => Self::SuperChatDelete(OpenSuperChatDelete {
+                room_id,
+                message_ids: data["message_ids"]
+                    .as_array()
+                    .map(|ids| ids.iter().map(as_u64).collect())
+                    .unwrap_or_default(),
+                msg_id,
+            }),
+            GUARD => Self::Guard(OpenGuard {
+                room_id,
+                user: OpenUser::from_value(&data["user_info"]),
+                guard_level,
+                guard_num: as_u64(&data["guard_num"]) as u32,
+                guard_unit: as_string(&data["guard_unit"]),
+                price: as_u64(&data["price"]),
+                msg_id,
+                medal,
+                timestamp,
+            }),
+            _ => Self::Like(OpenLike {
+                room_id,
+                user,
+                like_text: as_string(&data["like_text"]),
+                like_count: as_u64(&data["like_count"]) as u32,
+                msg_id,
+                medal,
+                timestamp,
+            }),
+        })
+    }
+}
@@ -0,0 +1,179 @@
+use serde_json::json;
+
+use crate::event::fixtures::{danmu_msg, notification};
+
+use super::{OpenDanmaku, OpenEvent, OpenMedal, OpenSuperChatDelete, OpenUser};
+
+fn open_user() -> OpenUser {
+    OpenUser {
+        uid: 0,
+        open_id: "39b8fedb-60a5-4e29-ac75-b16955f7e632".to_string(),
+        uname: "vioIet・伊芙加登".to_string(),
+        uface: "https://i0.hdslb.com/face.jpg".to_string(),
+    }
+}
+
+#[test]
+fn must_decode_open_danmaku() {
+    let event = OpenEvent::from_packet(&notification(&json!({
+        "cmd": "LIVE_OPEN_PLATFORM_DM",
+        "data": {
+            "room_id": 5440,
+            "uid": 0,
+            "open_id": "39b8fedb-60a5-4e29-ac75-b16955f7e632",
+            "uname": "vioIet・伊芙加登",
+            "uface": "https://i0.hdslb.com/face.jpg",
+            "msg": "晚上好",
+            "msg_id": "a1b2c3",
+            "fans_medal_level": 21,
+            "fans_medal_name": "牌子",
+            "fans_medal_wearing_status": true,
+            "guard_level": 3,
+            "timestamp": 1690027262,
+            "emoji_img_url": "",
+            "dm_type": 0
+        }
+    })))
+    .expect("unable to decode event")
+    .expect("not an open platform event");
+    assert_eq!(
+        event,
+        OpenEvent::Danmaku(OpenDanmaku {
+            room_id: 5440,
+            user: open_user(),
+            msg: "晚上好".to_string(),
+            msg_id: "a1b2c3".to_string(),
+            dm_type: 0,
+            emoji_img_url: String::new(),
+            guard_level: 3,
+            medal: Some(OpenMedal {
+                name: "牌子".to_string(),
+                level: 21,
+                is_wearing: true
+            }),
+            timestamp: 1690027262
+        })
+    );
+}
+
+#[test]
+fn must_decode_open_events() {
+    let decode = |value| {
+        OpenEvent::from_packet(&notification(&value))
+            .expect("unable to decode event")
+            .expect("not an open platform event")
+    };
+
+    let event = decode(json!({
+        "cmd": "LIVE_OPEN_PLATFORM_SEND_GIFT",
+        "data": {
+            "room_id": 5440, "uid": 0, "open_id": "39b8fedb-60a5-4e29-ac75-b16955f7e632",
+            "uname": "vioIet・伊芙加登", "uface": "https://i0.hdslb.com/face.jpg",
+            "gift_id": 31036, "gift_name": "小花花", "gift_num": 10, "price": 100, "paid": true,
+            "fans_medal_level": 0, "fans_medal_name": "", "fans_medal_wearing_status": false,
+            "guard_level": 0, "timestamp": 1690027262, "msg_id": "b2c3d4",
+            "gift_icon": "https://i0.hdslb.com/gift.png", "combo_gift": false
+        }
+    }));
+    let OpenEvent::Gift(gift) = event else {
+        panic!("not a gift: {:?}", event)
+    };
+    assert_eq!(gift.user, open_user());
+    assert_eq!(
+        (
+            gift.gift_id,
+            gift.gift_name.as_str(),
+            gift.gift_num,
+            gift.price
+        ),
+        (31036, "小花花", 10, 100)
+    );
+    assert!(gift.paid);
+    assert_eq!(gift.medal, None);
+
+    let event = decode(json!({
+        "cmd": "LIVE_OPEN_PLATFORM_SUPER_CHAT",
+        "data": {
+            "room_id": 5440, "uid": 0, "open_id": "39b8fedb-60a5-4e29-ac75-b16955f7e632",
+            "uname": "vioIet・伊芙加登", "uface": "https://i0.hdslb.com/face.jpg",
+            "message_id": 8265, "message": "加油", "rmb": 30, "timestamp": 1690027262,
+            "start_time": 1690027262, "end_time": 1690027322, "guard_level": 0,
+            "fans_medal_level": 0, "fans_medal_name": "", "fans_medal_wearing_status": false,
+            "msg_id": "c3d4e5"
+        }
+    }));
+    let OpenEvent::SuperChat(super_chat) = event else {
+        panic!("not a super chat: {:?}", event)
+    };
+    assert_eq!(
+        (
+            super_chat.message_id,
+            super_chat.message.as_str(),
+            super_chat.rmb
+        ),
+        (8265, "加油", 30)
+    );
+    assert_eq!(super_chat.end_time - super_chat.start_time, 60);
+
+    assert_eq!(
+        decode(json!({
+            "cmd": "LIVE_OPEN_PLATFORM_SUPER_CHAT_DEL",
+            "data": {"room_id": 5440, "message_ids": [8265, 8266], "msg_id": "d4e5f6"}
+        })),
+        OpenEvent::SuperChatDelete(OpenSuperChatDelete {
+            room_id: 5440,
+            message_ids: vec![8265, 8266],
+            msg_id: "d4e5f6".to_string()
+        })
+    );
+
+    let event = decode(json!({
+        "cmd": "LIVE_OPEN_PLATFORM_GUARD",
+        "data": {
+            "user_info": {
+                "uid": 0, "open_id": "39b8fedb-60a5-4e29-ac75-b16955f7e632",
+                "uname": "vioIet・伊芙加登", "uface": "https://i0.hdslb.com/face.jpg"
+            },
+            "guard_level": 3, "guard_num": 1, "guard_unit": "月", "price": 198000,
+            "fans_medal_level": 21, "fans_medal_name": "牌子", "fans_medal_wearing_status": false,
+            "room_id": 5440, "msg_id": "e5f6a7", "timestamp": 1690027262
+        }
+    }));
+    let OpenEvent::Guard(guard) = event else {
+        panic!("not a guard: {:?}", event)
+    };
+    assert_eq!(guard.user, open_user());
+    assert_eq!(
+        (
+            guard.guard_level,
+            guard.guard_num,
+            guard.guard_unit.as_str()
+        ),
+        (3, 1, "月")
+    );
+    assert_eq!(guard.medal.map(|medal| medal.is_wearing), Some(false));
+
+    let event = decode(json!({
+        "cmd": "LIVE_OPEN_PLATFORM_LIKE",
+        "data": {
+            "uname": "vioIet・伊芙加登", "uid": 0, "open_id": "39b8fedb-60a5-4e29-ac75-b16955f7e632",
+            "uface": "https://i0.hdslb.com/face.jpg", "timestamp": 1690027262, "room_id": 5440,
+            "like_text": "为主播点赞了", "like_count": 5, "msg_id": "f6a7b8"
+        }
+    }));
+    let OpenEvent::Like(like) = event else {
+        panic!("not a like: {:?}", event)
+    };
+    assert_eq!(
+        (like.like_text.as_str(), like.like_count),
+        ("为主播点赞了", 5)
+    );
+
+    assert_eq!(
+        OpenEvent::from_packet(&notification(&danmu_msg())).unwrap(),
+        None
+    );
+    assert!(
+        OpenEvent::from_packet(&notification(&json!({"cmd": "LIVE_OPEN_PLATFORM_DM"}))).is_err()
+    );
+}
@@ -0,0 +1,417 @@
+use std::collections::VecDeque;
+
+use serde_json::Value;
+
+use crate::errors::ParseError;
+use crate::packet::Packet;
+
+use super::types::{as_string, as_u64};
+use super::{cmd, notification};
+
+#[cfg(test)]
+mod tests;
+
+const PK_BATTLE_PRE_NEW: &str = "PK_BATTLE_PRE_NEW";
+const PK_BATTLE_START_NEW: &str = "PK_BATTLE_START_NEW";
+const PK_BATTLE_PROCESS_NEW: &str = "PK_BATTLE_PROCESS_NEW";
+const PK_BATTLE_FINAL_PROCESS: &str = "PK_BATTLE_FINAL_PROCESS";
+const PK_BATTLE_END: &str = "PK_BATTLE_END";
+const PK_BATTLE_SETTLE_V2: &str = "PK_BATTLE_SETTLE_V2";
+
+/// How many finished battles are remembered to drop their late events.
+const FINISHED_CAPACITY: usize = 8;
+
+/// Result of a battle for one side.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PkResult {
+    Win,
+    Lose,
+    Draw,
+}
+
+impl PkResult {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value.as_i64()? {
+            2 => Some(Self::Win),
+            -1 => Some(Self::Lose),
+            _ => Some(Self::Draw),
+        }
+    }
+}
+
+/// One side of a battle.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PkSide {
+    /// Live room id.
+    pub room_id: u64,
+    /// Votes (score) so far.
+    pub votes: u64,
+    /// User name of the top contributor.
+    pub best_uname: String,
+    /// Result of the side. Only available when the battle ends.
+    pub result: Option<PkResult>,
+}
+
+impl PkSide {
+    fn from_value(value: &Value) -> Self {
+        Self {
+            room_id: as_u64(&value["room_id"]),
+            votes: as_u64(&value["votes"]),
+            best_uname: as_string(&value["best_uname"]),
+            result: PkResult::from_value(&value["winner_type"]),
+        }
+    }
+}
+
+/// What happens in a battle.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum PkEventKind {
+    /// A battle is matched and about to start (`PK_BATTLE_PRE_NEW`).
+    Pre {
+        /// Live room id of the opponent.
+        room_id: u64,
+        /// User id of the opponent.
+        uid: u64,
+        /// User name of the opponent.
+        uname: String,
+        /// Seconds until the battle starts.
+        pre_timer: u64,
+    },
+    /// A battle starts (`PK_BATTLE_START_NEW`).
+    Start {
+        /// Live room id of the room initiating the battle.
+        init_room_id: u64,
+        /// Live room id of the matched room.
+        match_room_id: u64,
+        /// Unix timestamp (in seconds) when the battle starts.
+        start_time: u64,
+        /// Unix timestamp (in seconds) when votes stop being counted.
+        frozen_time: u64,
+        /// Unix timestamp (in seconds) when the battle ends.
+        end_time: u64,
+    },
+    /// Votes of both sides are updated (`PK_BATTLE_PROCESS_NEW`).
+    Process { init: PkSide, matched: PkSide },
+    /// The battle enters its final moments (`PK_BATTLE_FINAL_PROCESS`).
+    FinalProcess {
+        /// Unix timestamp (in seconds) when votes stop being counted.
+        frozen_time: u64,
+    },
+    /// The battle ends with final votes of both sides (`PK_BATTLE_END`).
+    End { init: PkSide, matched: PkSide },
+    /// The battle is settled (`PK_BATTLE_SETTLE_V2`).
+    Settle {
+        /// Result for the room receiving the notification.
+        result: Option<PkResult>,
+        /// Live room id of the winner. `None` if it's a draw.
+        winner_room_id: Option<u64>,
+        /// Unix timestamp (in seconds) when the punishment of the loser ends.
+        punish_end_time: u64,
+    },
+}
+
+/// A PK battle notification.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PkEvent {
+    /// Battle id.
+    pub pk_id: u64,
+    /// Unix timestamp (in seconds) when the notification is sent.
+    pub timestamp: u64,
+    pub kind: PkEventKind,
+}
+
+impl PkEvent {
+    /// Decode a PK battle notification packet.
+    ///
+    /// Returns `Ok(None)` if the packet is not a PK battle notification.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is a PK battle notification but its payload is malformed.
+    pub fn from_packet(packet: &Packet) -> Result<Option<Self>, ParseError> {
+        notification(
+            packet,
+            &[
+                PK_BATTLE_PRE_NEW,
+                PK_BATTLE_START_NEW,
+                PK_BATTLE_PROCESS_NEW,
+                PK_BATTLE_FINAL_PROCESS,
+                PK_BATTLE_END,
+                PK_BATTLE_SETTLE_V2,
+            ],
+        )?
+        .map(|value| Self::from_value(&value))
+        .transpose()
+    }
+
+    fn from_value(value: &Value) -> Result<Self, ParseError> {
+        let cmd = cmd(value).unwrap_or_default();
+        let data = value
+            .get("data")
+            .filter(|data| data.is_object())
+            .ok_or_else(|| ParseError::Event(format!("{} without data", cmd)))?;
+        let pk_id = as_u64(&value["pk_id"]);
+        if pk_id == 0 {
+            return Err(ParseError::Event(format!("{} without pk id", cmd)));
+        }
+
+        let kind = match cmd {
+            PK_BATTLE_PRE_NEW => PkEventKind::Pre {
+                room_id: as_u64(&data["room_id"]),
+                uid: as_u64(&data["uid"]),
+                uname: as_string(&data["uname"]),
+                pre_timer: as_u64(&data["pre_timer"]),
+            },
+            PK_BATTLE_START_NEW => PkEventKind::Start {
+                init_room_id: as_u64(&data["init_info"]["room_id"]),
+                match_room_id: as_u64(&data["match_info"]["room_id"]),
+                start_time: as_u64(&data["pk_start_time"]),
+                frozen_time: as_u64(&data["pk_frozen_time"]),
+                end_time: as_u64(&data["pk_end_time"]),
+            },
+            PK_BATTLE_PROCESS_NEW => PkEventKind::Process {
+                init: PkSide::from_value(&data["init_info"]),
+                matched: PkSide::from_value(&data["match_info"]),
+            },
+            PK_BATTLE_FINAL_PROCESS => PkEventKind::FinalProcess {
+                frozen_time: as_u64(&data["pk_frozen_time"]),
+            },
+            PK_BATTLE_END => PkEventKind::End {
+                init: PkSide::from_value(&data["init_info"]),
+                matched: PkSide::from_value(&data["match_info"]),
+            },
+            _ => PkEventKind::Settle {
+                result: PkResult::from_value(&data["result_type"]),
+                winner_room_id: Some(as_u64(&data["winner"]["room_id"]))
+                    .filter(|room_id| *room_id != 0),
+                punish_end_time: as_u64(&data["punish_end_time"]),
+            },
+        };
+
+        Ok(Self {
+            pk_id,
+            timestamp: as_u64(&value["timestamp"]),
+            kind,
+        })
+    }
+}
+
+/// Phase of a battle.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum PkPhase {
+    /// Matched and about to start.
+    Pre,
+    /// Votes are being counted.
+    Battle,
+    /// Final moments (绝杀时刻) before votes stop being counted.
+    Final,
+    /// Final votes are known.
+    Ended,
+    /// The result is settled.
+    Settled,
+}
+
+/// Votes of both sides at a moment.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PkScore {
+    /// Unix timestamp (in seconds) of the notification carrying the votes.
+    pub timestamp: u64,
+    /// Votes of the tracked room.
+    pub votes: u64,
+    /// Votes of the opponent.
+    pub opponent_votes: u64,
+}
+
+/// A battle seen from the tracked room.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PkBattle {
+    /// Battle id.
+    pub pk_id: u64,
+    /// Current phase.
+    pub phase: PkPhase,
+    /// Live room id of the opponent, if it's known.
+    pub opponent_room_id: Option<u64>,
+    /// User name of the opponent, if it's known.
+    pub opponent_uname: Option<String>,
+    /// Unix timestamp (in seconds) when the battle starts, if it's known.
+    pub start_time: Option<u64>,
+    /// Unix timestamp (in seconds) when the battle ends, if it's known.
+    pub end_time: Option<u64>,
+    /// Votes of both sides over time. The last one is the latest.
+    pub scores: Vec<PkScore>,
+    /// Result for the tracked room, once the battle ends.
+    pub result: Option<PkResult>,
+}
+
+impl PkBattle {
+    fn new(pk_id: u64) -> Self {
+        Self {
+            pk_id,
+            phase: PkPhase::Pre,
+            opponent_room_id: None,
+            opponent_uname: None,
+            start_time: None,
+            end_time: None,
+            scores: Vec::new(),
+            result: None,
+        }
+    }
+
+    /// Latest votes of both sides.
+    #[must_use]
+    pub fn score(&self) -> Option<&PkScore> {
+        self.scores.last()
+    }
+}
+
+/// Tracks PK battles of a live room.
+///
+/// Feed it with packets from the live stream. Notifications may arrive out of order or be missed:
+/// phases only move forward, a battle is started by whichever of its notifications comes first,
+/// and late notifications of finished battles are dropped.
+#[derive(Debug, Clone)]
+pub struct PkTracker {
+    room_id: u64,
+    current: Option<PkBattle>,
+    finished: VecDeque<u64>,
+}
+
+impl PkTracker {
+    /// Track battles of the given room.
+    #[must_use]
+    pub fn new(room_id: u64) -> Self {
+        Self {
+            room_id,
+            current: None,
+            finished: VecDeque::new(),
+        }
+    }
+
+    /// The current battle, or the last one if it's settled.
+    #[must_use]
+    pub fn current(&self) -> Option<&PkBattle> {
+        self.current.as_ref()
+    }
+
+    /// Feed a packet from the live stream. Returns the event if it's a PK battle notification.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is a PK battle notification but its payload is malformed.
+    pub fn feed(&mut self, packet: &Packet) -> Result<Option<PkEvent>, ParseError> {
+        let event = PkEvent::from_packet(packet)?;
+        if let Some(event) = &event {
+            self.apply(event);
+        }
+        Ok(event)
+    }
+
+    /// Apply a PK battle notification. Returns `false` if it's dropped as a late notification of
+    /// a finished battle.
+    pub fn apply(&mut self, event: &PkEvent) -> bool {
+        if self.finished.contains(&event.pk_id) {
+            return false;
+        }
+        if self
+            .current
+            .as_ref()
+            .is_none_or(|battle| battle.pk_id != event.pk_id)
+        {
+            if let Some(battle) = self.current.take() {
+                if self.finished.len() >= FINISHED_CAPACITY {
+                    self.finished.pop_front();
+                }
+                self.finished.push_back(battle.pk_id);
+            }
+        }
+
+        let room_id = self.room_id;
+        let battle = self
+            .current
+            .get_or_insert_with(|| PkBattle::new(event.pk_id));
+        let phase = match &event.kind {
+            PkEventKind::Pre { room_id, uname, .. } => {
+                battle.opponent_room_id = Some(*room_id);
+                battle.opponent_uname = Some(uname.clone());
+                PkPhase::Pre
+            }
+            PkEventKind::Start {
+                init_room_id,
+                match_room_id,
+                start_time,
+                end_time,
+                ..
+            } => {
+                let opponent = if *init_room_id == room_id {
+                    *match_room_id
+                } else {
+                    *init_room_id
+                };
+                battle.opponent_room_id = Some(opponent);
+                battle.start_time = Some(*start_time);
+                battle.end_time = Some(*end_time);
+                PkPhase::Battle
+            }
+            PkEventKind::Process { init, matched } => {
+                // votes after the battle ends are stale
+                if battle.phase < PkPhase::Ended {
+                    Self::record(battle, room_id, event.timestamp, init, matched);
+                }
+                PkPhase::Battle
+            }
+            PkEventKind::FinalProcess { .. } => PkPhase::Final,
+            PkEventKind::End { init, matched } => {
+                let ours = Self::record(battle, room_id, event.timestamp, init, matched);
+                if battle.result.is_none() {
+                    battle.result = ours.result;
+                }
+                PkPhase::Ended
+            }
+            PkEventKind::Settle {
+                result,
+                winner_room_id,
+                ..
+            } => {
+                battle.result = result.or(battle.result).or_else(|| {
+                    winner_room_id.map(|winner| {
+                        if winner == room_id {
+                            PkResult::Win
+                        } else {
+                            PkResult::Lose
+                        }
+                    })
+                });
+                PkPhase::Settled
+            }
+        };
+        battle.phase = battle.phase.max(phase);
+        true
+    }
+
+    /// Record votes of both sides. Returns the side of the tracked room.
+    fn record<'a>(
+        battle: &mut PkBattle,
+        room_id: u64,
+        timestamp: u64,
+        init: &'a PkSide,
+        matched: &'a PkSide,
+    ) -> &'a PkSide {
+        let (ours, theirs) = if matched.room_id == room_id {
+            (matched, init)
+        } else {
+            (init, matched)
+        };
+        if theirs.room_id != 0 {
+            battle.opponent_room_id = Some(theirs.room_id);
+        }
+        let score = PkScore {
+            timestamp,
+            votes: ours.votes,
+            opponent_votes: theirs.votes,
+        };
+        if battle.scores.last().is_none_or(|last| {
+            (last.votes, last.opponent_votes) != (score.votes, score.opponent_votes)
+        }) {
+            battle.scores.push(score);
+        }
+        ours
+    }
+}
@@ -0,0 +1,181 @@
+use serde_json::json;
+
+use crate::event::fixtures::{danmu_msg, notification};
+use crate::packet::Packet;
+
+use super::{PkEvent, PkEventKind, PkPhase, PkResult, PkSide, PkTracker};
+
+fn pk_notification(cmd: &str, pk_id: u64, timestamp: u64, data: serde_json::Value) -> Packet {
+    notification(&json!({
+        "cmd": cmd,
+        "pk_id": pk_id.to_string(),
+        "pk_status": 201,
+        "timestamp": timestamp,
+        "data": data,
+        "roomid": 5440
+    }))
+}
+
+fn pk_votes(cmd: &str, pk_id: u64, timestamp: u64, ours: u64, theirs: u64) -> Packet {
+    pk_notification(
+        cmd,
+        pk_id,
+        timestamp,
+        json!({
+            "battle_type": 1,
+            "init_info": {"room_id": 21452505, "votes": theirs, "best_uname": "B", "winner_type": if theirs > ours { 2 } else { -1 }},
+            "match_info": {"room_id": 5440, "votes": ours, "best_uname": "A", "winner_type": if ours > theirs { 2 } else { -1 }}
+        }),
+    )
+}
+
+#[test]
+fn must_decode_pk_events() {
+    let event = PkEvent::from_packet(&pk_notification(
+        "PK_BATTLE_PRE_NEW",
+        123,
+        1690027200,
+        json!({"battle_type": 1, "uname": "对手", "uid": 1, "room_id": 21452505, "pre_timer": 10}),
+    ))
+    .expect("unable to decode pk event")
+    .expect("not a pk event");
+    assert_eq!(
+        event,
+        PkEvent {
+            pk_id: 123,
+            timestamp: 1690027200,
+            kind: PkEventKind::Pre {
+                room_id: 21452505,
+                uid: 1,
+                uname: "对手".to_string(),
+                pre_timer: 10
+            }
+        }
+    );
+
+    let event = PkEvent::from_packet(&pk_votes("PK_BATTLE_END", 123, 1690027600, 100, 50))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        event.kind,
+        PkEventKind::End {
+            init: PkSide {
+                room_id: 21452505,
+                votes: 50,
+                best_uname: "B".to_string(),
+                result: Some(PkResult::Lose)
+            },
+            matched: PkSide {
+                room_id: 5440,
+                votes: 100,
+                best_uname: "A".to_string(),
+                result: Some(PkResult::Win)
+            }
+        }
+    );
+
+    let event = PkEvent::from_packet(&pk_notification(
+        "PK_BATTLE_SETTLE_V2",
+        123,
+        1690027610,
+        json!({"result_type": 2, "winner": {"room_id": 5440}, "punish_end_time": 1690027900}),
+    ))
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        event.kind,
+        PkEventKind::Settle {
+            result: Some(PkResult::Win),
+            winner_room_id: Some(5440),
+            punish_end_time: 1690027900
+        }
+    );
+
+    assert_eq!(
+        PkEvent::from_packet(&notification(&danmu_msg())).unwrap(),
+        None
+    );
+    assert!(PkEvent::from_packet(&notification(
+        &json!({"cmd": "PK_BATTLE_END", "data": {"battle_type": 1}})
+    ))
+    .is_err());
+}
+
+#[test]
+fn must_track_pk_battle() {
+    let mut tracker = PkTracker::new(5440);
+    let mut feed = |packet: Packet| tracker.feed(&packet).unwrap();
+
+    feed(pk_notification(
+        "PK_BATTLE_START_NEW",
+        123,
+        1690027200,
+        json!({
+            "pk_start_time": 1690027200, "pk_frozen_time": 1690027500, "pk_end_time": 1690027510,
+            "init_info": {"room_id": 21452505}, "match_info": {"room_id": 5440}
+        }),
+    ));
+    feed(pk_votes("PK_BATTLE_PROCESS_NEW", 123, 1690027300, 10, 0));
+    // unchanged votes are not recorded again
+    feed(pk_votes("PK_BATTLE_PROCESS_NEW", 123, 1690027310, 10, 0));
+    // a late pre notification doesn't move the phase back
+    feed(pk_notification(
+        "PK_BATTLE_PRE_NEW",
+        123,
+        1690027190,
+        json!({"uname": "对手", "room_id": 21452505}),
+    ));
+    feed(pk_votes("PK_BATTLE_PROCESS_NEW", 123, 1690027400, 10, 30));
+    // the final process notification is missed
+    feed(pk_votes("PK_BATTLE_END", 123, 1690027510, 100, 50));
+    // stale votes after the battle ends are ignored
+    feed(pk_votes("PK_BATTLE_PROCESS_NEW", 123, 1690027450, 20, 30));
+
+    let battle = tracker.current().expect("no battle");
+    assert_eq!(battle.pk_id, 123);
+    assert_eq!(battle.phase, PkPhase::Ended);
+    assert_eq!(battle.opponent_room_id, Some(21452505));
+    assert_eq!(battle.opponent_uname.as_deref(), Some("对手"));
+    assert_eq!(
+        (battle.start_time, battle.end_time),
+        (Some(1690027200), Some(1690027510))
+    );
+    assert_eq!(
+        battle
+            .scores
+            .iter()
+            .map(|score| (score.timestamp, score.votes, score.opponent_votes))
+            .collect::<Vec<_>>(),
+        [
+            (1690027300, 10, 0),
+            (1690027400, 10, 30),
+            (1690027510, 100, 50)
+        ]
+    );
+    assert_eq!(battle.result, Some(PkResult::Win));
+
+    let mut feed = |packet: Packet| tracker.apply(&PkEvent::from_packet(&packet).unwrap().unwrap());
+    assert!(feed(pk_notification(
+        "PK_BATTLE_SETTLE_V2",
+        123,
+        1690027520,
+        json!({"result_type": 2, "winner": {"room_id": 5440}}),
+    )));
+    assert_eq!(tracker.current().unwrap().phase, PkPhase::Settled);
+
+    // a new battle starts with whichever notification comes first
+    let mut feed = |packet: Packet| tracker.apply(&PkEvent::from_packet(&packet).unwrap().unwrap());
+    assert!(feed(pk_votes(
+        "PK_BATTLE_PROCESS_NEW",
+        124,
+        1690028000,
+        5,
+        0
+    )));
+    // and late notifications of the last battle are dropped
+    assert!(!feed(pk_votes("PK_BATTLE_END", 123, 1690027510, 100, 50)));
+    let battle = tracker.current().unwrap();
+    assert_eq!((battle.pk_id, battle.phase), (124, PkPhase::Battle));
+    assert_eq!(battle.score().map(|score| score.votes), Some(5));
+    assert_eq!(battle.result, None);
+}
@@ -0,0 +1,143 @@
+//! Protobuf payloads carried by some notifications.
+//!
+//! Messages mirror the schemas bundled in `proto/`, and only list fields decoded by this crate.
+
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use prost::Message;
+
+use crate::errors::ParseError;
+
+#[cfg(test)]
+mod tests;
+
+/// Decode a base64 encoded protobuf message.
+pub(crate) fn decode<M: Message + Default>(encoded: &str) -> Result<M, ParseError> {
+    let buf = BASE64
+        .decode(encoded)
+        .map_err(|e| ParseError::Event(format!("malformed protobuf payload: {}", e)))?;
+    M::decode(buf.as_slice())
+        .map_err(|e| ParseError::Event(format!("malformed protobuf payload: {}", e)))
+}
+
+/// `bililive.dm.v2.Dm`, carried by `dm_v2` of `DANMU_MSG`.
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct Dm {
+    #[prost(string, tag = "1")]
+    pub id_str: String,
+    #[prost(int32, tag = "2")]
+    pub mode: i32,
+    #[prost(int32, tag = "3")]
+    pub fontsize: i32,
+    #[prost(uint32, tag = "4")]
+    pub color: u32,
+    #[prost(string, tag = "5")]
+    pub mid_hash: String,
+    #[prost(string, tag = "6")]
+    pub content: String,
+    #[prost(int64, tag = "7")]
+    pub ctime: i64,
+    #[prost(int32, tag = "13")]
+    pub dm_type: i32,
+    #[prost(map = "string, message", tag = "14")]
+    pub emoticons: HashMap<String, DmEmoticon>,
+    #[prost(message, optional, tag = "20")]
+    pub user: Option<DmUser>,
+    #[prost(message, optional, tag = "23")]
+    pub reply: Option<DmReply>,
+}
+
+/// `bililive.dm.v2.Emoticon`.
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct DmEmoticon {
+    #[prost(string, tag = "1")]
+    pub emoticon_unique: String,
+    #[prost(string, tag = "2")]
+    pub url: String,
+    #[prost(bool, tag = "3")]
+    pub is_dynamic: bool,
+    #[prost(int64, tag = "6")]
+    pub height: i64,
+    #[prost(int64, tag = "7")]
+    pub width: i64,
+}
+
+/// `bililive.dm.v2.User`.
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct DmUser {
+    #[prost(int64, tag = "1")]
+    pub uid: i64,
+    #[prost(message, optional, tag = "2")]
+    pub base: Option<UserBase>,
+}
+
+/// `bililive.dm.v2.UserBase` and `bililive.interact.v2.UserBase`.
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct UserBase {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub face: String,
+}
+
+/// `bililive.dm.v2.Reply`.
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct DmReply {
+    #[prost(int64, tag = "1")]
+    pub reply_mid: i64,
+    #[prost(string, tag = "2")]
+    pub reply_uname: String,
+}
+
+/// `bililive.interact.v2.InteractWord`, carried by `data.pb` of `INTERACT_WORD_V2`.
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct InteractWord {
+    #[prost(int64, tag = "1")]
+    pub uid: i64,
+    #[prost(string, tag = "2")]
+    pub uname: String,
+    #[prost(int64, repeated, tag = "4")]
+    pub identities: Vec<i64>,
+    #[prost(int64, tag = "5")]
+    pub msg_type: i64,
+    #[prost(int64, tag = "6")]
+    pub roomid: i64,
+    #[prost(int64, tag = "7")]
+    pub timestamp: i64,
+    #[prost(message, optional, tag = "9")]
+    pub fans_medal: Option<InteractFansMedal>,
+    #[prost(int64, tag = "15")]
+    pub trigger_time: i64,
+    #[prost(int64, tag = "16")]
+    pub privilege_type: i64,
+    #[prost(message, optional, tag = "22")]
+    pub uinfo: Option<InteractUserInfo>,
+}
+
+/// `bililive.interact.v2.FansMedal`.
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct InteractFansMedal {
+    #[prost(int64, tag = "1")]
+    pub target_id: i64,
+    #[prost(int64, tag = "2")]
+    pub medal_level: i64,
+    #[prost(string, tag = "3")]
+    pub medal_name: String,
+    #[prost(int64, tag = "8")]
+    pub is_lighted: i64,
+    #[prost(int64, tag = "9")]
+    pub guard_level: i64,
+    #[prost(int64, tag = "12")]
+    pub anchor_roomid: i64,
+}
+
+/// `bililive.interact.v2.UserInfo`.
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct InteractUserInfo {
+    #[prost(int64, tag = "1")]
+    pub uid: i64,
+    #[prost(message, optional, tag = "2")]
+    pub base: Option<UserBase>,
+}
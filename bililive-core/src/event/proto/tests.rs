@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+use crate::event::fixtures::fixture;
+
+use super::{
+    decode, Dm, DmEmoticon, DmReply, DmUser, InteractFansMedal, InteractUserInfo, InteractWord,
+    UserBase,
+};
+
+fn user_base(name: &str, face: &str) -> Option<UserBase> {
+    Some(UserBase {
+        name: name.to_string(),
+        face: face.to_string(),
+    })
+}
+
+#[test]
+fn must_decode_dm_payload() {
+    let value = fixture("tests/raw/dm_v2.json");
+    let dm: Dm = decode(value["dm_v2"].as_str().unwrap()).expect("unable to decode dm");
+
+    assert_eq!(dm.id_str, "6a8f3c2e1b");
+    assert_eq!((dm.mode, dm.fontsize, dm.color), (1, 25, 14893055));
+    assert_eq!(dm.mid_hash, "630b6aa4");
+    assert_eq!(dm.content, "晚上好[dog]");
+    assert_eq!(dm.ctime, 1690027262123);
+    assert_eq!(dm.dm_type, 0);
+    assert_eq!(
+        dm.emoticons["[dog]"],
+        DmEmoticon {
+            emoticon_unique: "emoji_dog".to_string(),
+            url: "https://i0.hdslb.com/bfs/live/dog.png".to_string(),
+            is_dynamic: false,
+            height: 20,
+            width: 20,
+        }
+    );
+    assert_eq!(
+        dm.user,
+        Some(DmUser {
+            uid: 174102117,
+            base: user_base(
+                "vioIet・伊芙加登",
+                "https://i0.hdslb.com/bfs/face/member/noface.jpg"
+            ),
+        })
+    );
+    assert_eq!(
+        dm.reply,
+        Some(DmReply {
+            reply_mid: 9617619,
+            reply_uname: "主播".to_string(),
+        })
+    );
+}
+
+#[test]
+fn must_decode_interact_word_payload() {
+    let value = fixture("tests/raw/interact_word_v2.json");
+    let word: InteractWord =
+        decode(value["data"]["pb"].as_str().unwrap()).expect("unable to decode interact word");
+
+    assert_eq!(word.uid, 174102117);
+    assert_eq!(word.uname, "vioIet・伊芙加登");
+    assert_eq!(word.identities, [3, 1]);
+    assert_eq!(word.msg_type, 1);
+    assert_eq!(word.roomid, 23090051);
+    assert_eq!(word.timestamp, 1626324624);
+    assert_eq!(
+        word.fans_medal,
+        Some(InteractFansMedal {
+            target_id: 9617619,
+            medal_level: 21,
+            medal_name: "牌子".to_string(),
+            is_lighted: 1,
+            guard_level: 3,
+            anchor_roomid: 5440,
+        })
+    );
+    assert_eq!(word.trigger_time, 1626324623404263200);
+    assert_eq!(word.privilege_type, 3);
+    assert_eq!(
+        word.uinfo,
+        Some(InteractUserInfo {
+            uid: 174102117,
+            base: user_base(
+                "vioIet・伊芙加登",
+                "https://i0.hdslb.com/bfs/face/member/noface.jpg"
+            ),
+        })
+    );
+}
+
+#[test]
+fn must_map_dm_tags() {
+    let buf: &[u8] = &[
+        0x0a, 0x01, b'a', // 1: id_str
+        0x10, 0x01, // 2: mode
+        0x18, 0x19, // 3: fontsize
+        0x20, 0x02, // 4: color
+        0x2a, 0x01, b'b', // 5: mid_hash
+        0x32, 0x01, b'c', // 6: content
+        0x38, 0x03, // 7: ctime
+        0x40, 0x09, // 8: unknown varint
+        0x68, 0x01, // 13: dm_type
+        0x72, 0x0e, // 14: emoticons
+        0x0a, 0x01, b'd', // key
+        0x12, 0x09, // value
+        0x0a, 0x01, b'e', // 1: emoticon_unique
+        0x18, 0x01, // 3: is_dynamic
+        0x30, 0x04, // 6: height
+        0x38, 0x05, // 7: width
+        0xa2, 0x01, 0x0a, // 20: user
+        0x08, 0x06, // 1: uid
+        0x12, 0x06, // 2: base
+        0x0a, 0x01, b'f', // 1: name
+        0x12, 0x01, b'g', // 2: face
+        0xba, 0x01, 0x05, // 23: reply
+        0x08, 0x07, // 1: reply_mid
+        0x12, 0x01, b'h', // 2: reply_uname
+        0xf5, 0x01, 0x01, 0x02, 0x03, 0x04, // 30: unknown fixed32
+        0x9a, 0x06, 0x01, b'x', // 99: unknown string
+    ];
+    let dm: Dm = decode(&BASE64.encode(buf)).expect("unable to decode dm");
+
+    let emoticon = DmEmoticon {
+        emoticon_unique: "e".to_string(),
+        url: String::new(),
+        is_dynamic: true,
+        height: 4,
+        width: 5,
+    };
+    assert_eq!(
+        dm,
+        Dm {
+            id_str: "a".to_string(),
+            mode: 1,
+            fontsize: 25,
+            color: 2,
+            mid_hash: "b".to_string(),
+            content: "c".to_string(),
+            ctime: 3,
+            dm_type: 1,
+            emoticons: HashMap::from([("d".to_string(), emoticon)]),
+            user: Some(DmUser {
+                uid: 6,
+                base: user_base("f", "g"),
+            }),
+            reply: Some(DmReply {
+                reply_mid: 7,
+                reply_uname: "h".to_string(),
+            }),
+        }
+    );
+}
+
+#[test]
+fn must_map_interact_word_tags() {
+    let buf: &[u8] = &[
+        0x08, 0x01, // 1: uid
+        0x12, 0x01, b'a', // 2: uname
+        0x1a, 0x01, b'c', // 3: unknown string
+        0x22, 0x02, 0x03, 0x01, // 4: identities, packed
+        0x20, 0x02, // 4: identities, unpacked
+        0x28, 0x01, // 5: msg_type
+        0x30, 0x02, // 6: roomid
+        0x38, 0x03, // 7: timestamp
+        0x4a, 0x0d, // 9: fans_medal
+        0x08, 0x04, // 1: target_id
+        0x10, 0x05, // 2: medal_level
+        0x1a, 0x01, b'b', // 3: medal_name
+        0x40, 0x01, // 8: is_lighted
+        0x48, 0x03, // 9: guard_level
+        0x60, 0x06, // 12: anchor_roomid
+        0x50, 0x01, // 10: unknown varint
+        0x78, 0x07, // 15: trigger_time
+        0x80, 0x01, 0x03, // 16: privilege_type
+        0xb2, 0x01, 0x07, // 22: uinfo
+        0x08, 0x01, // 1: uid
+        0x12, 0x03, // 2: base
+        0x0a, 0x01, b'a', // 1: name
+    ];
+    let word: InteractWord = decode(&BASE64.encode(buf)).expect("unable to decode interact word");
+
+    assert_eq!(
+        word,
+        InteractWord {
+            uid: 1,
+            uname: "a".to_string(),
+            identities: vec![3, 1, 2],
+            msg_type: 1,
+            roomid: 2,
+            timestamp: 3,
+            fans_medal: Some(InteractFansMedal {
+                target_id: 4,
+                medal_level: 5,
+                medal_name: "b".to_string(),
+                is_lighted: 1,
+                guard_level: 3,
+                anchor_roomid: 6,
+            }),
+            trigger_time: 7,
+            privilege_type: 3,
+            uinfo: Some(InteractUserInfo {
+                uid: 1,
+                base: user_base("a", ""),
+            }),
+        }
+    );
+}
+
+#[test]
+fn must_default_missing_fields() {
+    assert_eq!(decode::<Dm>("").unwrap(), Dm::default());
+    assert_eq!(decode::<InteractWord>("").unwrap(), InteractWord::default());
+
+    // 6: content
+    let dm: Dm = decode(&BASE64.encode([0x32, 0x01, b'c'])).unwrap();
+    assert_eq!(dm.content, "c");
+    assert!(dm.id_str.is_empty());
+    assert!(dm.emoticons.is_empty());
+    assert_eq!((dm.user, dm.reply), (None, None));
+
+    // 22: uinfo, without base
+    let word: InteractWord = decode(&BASE64.encode([0xb2, 0x01, 0x02, 0x08, 0x01])).unwrap();
+    assert_eq!(word.uid, 0);
+    assert_eq!(word.fans_medal, None);
+    assert_eq!(word.uinfo, Some(InteractUserInfo { uid: 1, base: None }));
+}
+
+#[test]
+fn must_reject_malformed_payload() {
+    assert!(decode::<Dm>("not base64!").is_err());
+    // truncated string
+    assert!(decode::<Dm>(&BASE64.encode([0x0a, 0x05, b'a'])).is_err());
+    // a string where uid is expected
+    assert!(decode::<InteractWord>(&BASE64.encode([0x0a, 0x01, b'a'])).is_err());
+}
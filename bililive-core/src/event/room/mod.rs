@@ -0,0 +1,317 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+use crate::errors::ParseError;
+use crate::packet::Packet;
+use crate::room::RoomInfo;
+
+use super::types::{as_string, as_u64};
+use super::{cmd, notification};
+
+#[cfg(test)]
+mod tests;
+
+const LIVE: &str = "LIVE";
+const PREPARING: &str = "PREPARING";
+const ROOM_CHANGE: &str = "ROOM_CHANGE";
+const CUT_OFF: &str = "CUT_OFF";
+const WARNING: &str = "WARNING";
+const ROOM_BLOCK_MSG: &str = "ROOM_BLOCK_MSG";
+const ROOM_SILENT_ON: &str = "ROOM_SILENT_ON";
+const ROOM_SILENT_OFF: &str = "ROOM_SILENT_OFF";
+const ROOM_ADMINS: &str = "ROOM_ADMINS";
+const ROOM_ADMIN_ENTRANCE: &str = "room_admin_entrance";
+const ROOM_ADMIN_REVOKE: &str = "ROOM_ADMIN_REVOKE";
+const CHANGE_ROOM_INFO: &str = "CHANGE_ROOM_INFO";
+
+/// Who may send danmaku while the room is silenced (全员禁言).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SilentMode {
+    /// Users with user level (UL) at least the given one.
+    Level(u32),
+    /// Users with the room's fans medal at least the given level.
+    Medal(u32),
+    /// Nobody except admins and guards.
+    Member,
+}
+
+/// Silent mode of a room.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct RoomSilent {
+    /// Who may still send danmaku.
+    pub mode: SilentMode,
+    /// Unix timestamp (in seconds) when silent mode ends. `None` if it lasts until turned off.
+    pub until: Option<u64>,
+}
+
+/// A room state or moderation notification.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum RoomEvent {
+    /// The room starts streaming (`LIVE`).
+    Live {
+        room_id: u64,
+        /// Unix timestamp (in seconds) when the live started. Not every `LIVE` carries it.
+        live_time: Option<u64>,
+    },
+    /// The room stops streaming (`PREPARING`).
+    Preparing { room_id: u64 },
+    /// Title or area of the room is changed (`ROOM_CHANGE`).
+    RoomChange {
+        title: String,
+        area_id: u64,
+        area_name: String,
+        parent_area_id: u64,
+        parent_area_name: String,
+    },
+    /// The live is cut off by moderators (`CUT_OFF`).
+    CutOff { room_id: u64, reason: String },
+    /// The anchor is warned by moderators (`WARNING`).
+    Warning { room_id: u64, msg: String },
+    /// A user is blocked from the room (`ROOM_BLOCK_MSG`).
+    Block {
+        uid: u64,
+        uname: String,
+        /// `1` if blocked by an admin, `2` if blocked by the anchor.
+        operator: u8,
+    },
+    /// The room is silenced (`ROOM_SILENT_ON`).
+    SilentOn(RoomSilent),
+    /// The room is no longer silenced (`ROOM_SILENT_OFF`).
+    SilentOff,
+    /// Full list of room admins (`ROOM_ADMINS`).
+    Admins { uids: Vec<u64> },
+    /// A user is appointed as a room admin (`room_admin_entrance`).
+    AdminAppointed { uid: u64, msg: String },
+    /// A user is no longer a room admin (`ROOM_ADMIN_REVOKE`).
+    AdminRevoked { uid: u64, msg: String },
+    /// Room decoration is changed (`CHANGE_ROOM_INFO`).
+    InfoChange { room_id: u64, background: String },
+}
+
+impl RoomEvent {
+    /// Decode a room state or moderation notification packet.
+    ///
+    /// Returns `Ok(None)` if the packet is not one.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is a room state notification but its payload is malformed.
+    pub fn from_packet(packet: &Packet) -> Result<Option<Self>, ParseError> {
+        notification(
+            packet,
+            &[
+                LIVE,
+                PREPARING,
+                ROOM_CHANGE,
+                CUT_OFF,
+                WARNING,
+                ROOM_BLOCK_MSG,
+                ROOM_SILENT_ON,
+                ROOM_SILENT_OFF,
+                ROOM_ADMINS,
+                ROOM_ADMIN_ENTRANCE,
+                ROOM_ADMIN_REVOKE,
+                CHANGE_ROOM_INFO,
+            ],
+        )?
+        .map(|value| Self::from_value(&value))
+        .transpose()
+    }
+
+    fn from_value(value: &Value) -> Result<Self, ParseError> {
+        let cmd = cmd(value).unwrap_or_default();
+        let data = || {
+            value
+                .get("data")
+                .filter(|data| data.is_object())
+                .ok_or_else(|| ParseError::Event(format!("{} without data", cmd)))
+        };
+        let room_id = as_u64(&value["roomid"]);
+
+        Ok(match cmd {
+            LIVE => Self::Live {
+                room_id,
+                live_time: value
+                    .get("live_time")
+                    .map(as_u64)
+                    .filter(|live_time| *live_time != 0),
+            },
+            PREPARING => Self::Preparing { room_id },
+            ROOM_CHANGE => {
+                let data = data()?;
+                Self::RoomChange {
+                    title: as_string(&data["title"]),
+                    area_id: as_u64(&data["area_id"]),
+                    area_name: as_string(&data["area_name"]),
+                    parent_area_id: as_u64(&data["parent_area_id"]),
+                    parent_area_name: as_string(&data["parent_area_name"]),
+                }
+            }
+            CUT_OFF => Self::CutOff {
+                room_id,
+                reason: as_string(&value["msg"]),
+            },
+            WARNING => Self::Warning {
+                room_id,
+                msg: as_string(&value["msg"]),
+            },
+            ROOM_BLOCK_MSG => {
+                let data = data()?;
+                Self::Block {
+                    uid: as_u64(&data["uid"]),
+                    uname: as_string(&data["uname"]),
+                    operator: as_u64(&data["operator"]) as u8,
+                }
+            }
+            ROOM_SILENT_ON => {
+                let data = data()?;
+                let level = as_u64(&data["level"]) as u32;
+                let mode = match data["type"].as_str() {
+                    Some("level") => SilentMode::Level(level),
+                    Some("medal") => SilentMode::Medal(level),
+                    Some("member") => SilentMode::Member,
+                    mode => {
+                        return Err(ParseError::Event(format!(
+                            "unknown silent mode: {:?}",
+                            mode
+                        )))
+                    }
+                };
+                // `-1` means until turned off
+                let until = data["second"].as_i64().filter(|second| *second > 0);
+                Self::SilentOn(RoomSilent {
+                    mode,
+                    until: until.map(|second| second as u64),
+                })
+            }
+            ROOM_SILENT_OFF => Self::SilentOff,
+            ROOM_ADMINS => Self::Admins {
+                uids: value["uids"]
+                    .as_array()
+                    .map(|uids| uids.iter().map(as_u64).collect())
+                    .unwrap_or_default(),
+            },
+            ROOM_ADMIN_ENTRANCE => Self::AdminAppointed {
+                uid: as_u64(&value["uid"]),
+                msg: as_string(&value["msg"]),
+            },
+            ROOM_ADMIN_REVOKE => Self::AdminRevoked {
+                uid: as_u64(&value["uid"]),
+                msg: as_string(&value["msg"]),
+            },
+            _ => Self::InfoChange {
+                room_id,
+                background: as_string(&value["background"]),
+            },
+        })
+    }
+}
+
+/// State of a live room, which can be kept up to date with notifications.
+///
+/// Seed it with [`from_info`](Self::from_info) if the room info is fetched via
+/// [`room::get_info`](crate::room::get_info), then feed it with packets from the live stream.
+/// Silent mode and admins are only known after they change in the stream, as the room info
+/// doesn't carry them.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RoomState {
+    /// Room title.
+    pub title: String,
+    /// Area (sub-category) id.
+    pub area_id: u64,
+    /// Area (sub-category) name.
+    pub area_name: String,
+    /// Parent area (category) id.
+    pub parent_area_id: u64,
+    /// Parent area (category) name.
+    pub parent_area_name: String,
+    /// Whether the room is streaming.
+    pub is_live: bool,
+    /// Unix timestamp (in seconds) when the current live started, if it's known.
+    pub live_time: Option<u64>,
+    /// Reason of the last cut off, cleared when the room goes live again.
+    pub cut_off: Option<String>,
+    /// Silent mode. `None` if the room is not silenced.
+    pub silent: Option<RoomSilent>,
+    /// User ids of room admins.
+    pub admins: BTreeSet<u64>,
+}
+
+impl RoomState {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seed the state with room info.
+    #[must_use]
+    pub fn from_info(info: &RoomInfo) -> Self {
+        Self {
+            title: info.title.clone(),
+            area_id: info.area_id,
+            area_name: info.area_name.clone(),
+            parent_area_id: info.parent_area_id,
+            parent_area_name: info.parent_area_name.clone(),
+            is_live: info.is_live(),
+            live_time: info.live_start_time,
+            ..Self::default()
+        }
+    }
+
+    /// Feed a packet from the live stream. Returns the event if it's a room state notification.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is a room state notification but its payload is malformed.
+    pub fn feed(&mut self, packet: &Packet) -> Result<Option<RoomEvent>, ParseError> {
+        let event = RoomEvent::from_packet(packet)?;
+        if let Some(event) = &event {
+            self.apply(event);
+        }
+        Ok(event)
+    }
+
+    /// Apply a room state notification.
+    pub fn apply(&mut self, event: &RoomEvent) {
+        match event {
+            RoomEvent::Live { live_time, .. } => {
+                self.is_live = true;
+                self.cut_off = None;
+                if live_time.is_some() {
+                    self.live_time = *live_time;
+                }
+            }
+            RoomEvent::Preparing { .. } => {
+                self.is_live = false;
+                self.live_time = None;
+            }
+            RoomEvent::RoomChange {
+                title,
+                area_id,
+                area_name,
+                parent_area_id,
+                parent_area_name,
+            } => {
+                self.title.clone_from(title);
+                self.area_id = *area_id;
+                self.area_name.clone_from(area_name);
+                self.parent_area_id = *parent_area_id;
+                self.parent_area_name.clone_from(parent_area_name);
+            }
+            RoomEvent::CutOff { reason, .. } => {
+                self.is_live = false;
+                self.live_time = None;
+                self.cut_off = Some(reason.clone());
+            }
+            RoomEvent::SilentOn(silent) => self.silent = Some(*silent),
+            RoomEvent::SilentOff => self.silent = None,
+            RoomEvent::Admins { uids } => self.admins = uids.iter().copied().collect(),
+            RoomEvent::AdminAppointed { uid, .. } => {
+                self.admins.insert(*uid);
+            }
+            RoomEvent::AdminRevoked { uid, .. } => {
+                self.admins.remove(uid);
+            }
+            RoomEvent::Warning { .. } | RoomEvent::Block { .. } | RoomEvent::InfoChange { .. } => {}
+        }
+    }
+}
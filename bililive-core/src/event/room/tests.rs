@@ -0,0 +1,140 @@
+use serde_json::json;
+
+use crate::event::fixtures::{danmu_msg, notification};
+
+use super::{RoomEvent, RoomSilent, RoomState, SilentMode};
+
+#[test]
+fn must_decode_room_events() {
+    let decode = |value| {
+        RoomEvent::from_packet(&notification(&value))
+            .expect("unable to decode event")
+            .expect("not a room event")
+    };
+
+    assert_eq!(
+        decode(json!({"cmd": "LIVE", "live_time": 1690034400, "roomid": 5440})),
+        RoomEvent::Live {
+            room_id: 5440,
+            live_time: Some(1690034400)
+        }
+    );
+    assert_eq!(
+        decode(json!({"cmd": "LIVE", "roomid": 5440})),
+        RoomEvent::Live {
+            room_id: 5440,
+            live_time: None
+        }
+    );
+    assert_eq!(
+        decode(json!({"cmd": "PREPARING", "roomid": "5440"})),
+        RoomEvent::Preparing { room_id: 5440 }
+    );
+    assert_eq!(
+        decode(json!({"cmd": "CUT_OFF", "msg": "禁播游戏", "roomid": 5440})),
+        RoomEvent::CutOff {
+            room_id: 5440,
+            reason: "禁播游戏".to_string()
+        }
+    );
+    assert_eq!(
+        decode(json!({
+            "cmd": "ROOM_BLOCK_MSG",
+            "data": {"dmscore": 30, "operator": 1, "uid": 174102117, "uname": "vioIet・伊芙加登"},
+            "uid": "174102117",
+            "uname": "vioIet・伊芙加登"
+        })),
+        RoomEvent::Block {
+            uid: 174102117,
+            uname: "vioIet・伊芙加登".to_string(),
+            operator: 1
+        }
+    );
+    assert_eq!(
+        decode(
+            json!({"cmd": "ROOM_SILENT_ON", "data": {"type": "medal", "level": 5, "second": -1}})
+        ),
+        RoomEvent::SilentOn(RoomSilent {
+            mode: SilentMode::Medal(5),
+            until: None
+        })
+    );
+    assert_eq!(
+        decode(
+            json!({"cmd": "ROOM_SILENT_ON", "data": {"type": "member", "level": 0, "second": 1690038000}})
+        ),
+        RoomEvent::SilentOn(RoomSilent {
+            mode: SilentMode::Member,
+            until: Some(1690038000)
+        })
+    );
+    assert_eq!(
+        decode(
+            json!({"cmd": "CHANGE_ROOM_INFO", "background": "https://i0.hdslb.com/bg.png", "roomid": 5440})
+        ),
+        RoomEvent::InfoChange {
+            room_id: 5440,
+            background: "https://i0.hdslb.com/bg.png".to_string()
+        }
+    );
+
+    assert_eq!(
+        RoomEvent::from_packet(&notification(&danmu_msg())).unwrap(),
+        None
+    );
+    assert!(RoomEvent::from_packet(&notification(&json!({"cmd": "ROOM_CHANGE"}))).is_err());
+    assert!(RoomEvent::from_packet(&notification(
+        &json!({"cmd": "ROOM_SILENT_ON", "data": {"type": "", "level": 0}})
+    ))
+    .is_err());
+}
+
+#[test]
+fn must_update_room_state() {
+    let mut state = RoomState::new();
+    let mut feed = |value| state.feed(&notification(&value)).unwrap();
+
+    assert!(feed(danmu_msg()).is_none());
+    feed(json!({"cmd": "LIVE", "live_time": 1690034400, "roomid": 5440}));
+    feed(json!({
+        "cmd": "ROOM_CHANGE",
+        "data": {
+            "title": "晚上好", "area_id": 371, "area_name": "虚拟日常",
+            "parent_area_id": 9, "parent_area_name": "虚拟主播"
+        }
+    }));
+    feed(json!({"cmd": "ROOM_SILENT_ON", "data": {"type": "level", "level": 20, "second": -1}}));
+    feed(json!({"cmd": "ROOM_ADMINS", "uids": [1, 2, 3]}));
+    feed(json!({"cmd": "room_admin_entrance", "msg": "系统提示：你已被主播设为房管", "uid": 4}));
+    feed(json!({"cmd": "ROOM_ADMIN_REVOKE", "msg": "撤销房管", "uid": 2}));
+    // a `LIVE` without live time keeps the known one
+    feed(json!({"cmd": "LIVE", "roomid": 5440}));
+
+    assert_eq!(
+        state,
+        RoomState {
+            title: "晚上好".to_string(),
+            area_id: 371,
+            area_name: "虚拟日常".to_string(),
+            parent_area_id: 9,
+            parent_area_name: "虚拟主播".to_string(),
+            is_live: true,
+            live_time: Some(1690034400),
+            cut_off: None,
+            silent: Some(RoomSilent {
+                mode: SilentMode::Level(20),
+                until: None
+            }),
+            admins: [1, 3, 4].into_iter().collect()
+        }
+    );
+
+    let mut feed = |value| state.feed(&notification(&value)).unwrap();
+    feed(json!({"cmd": "ROOM_SILENT_OFF", "data": {"type": "", "level": 0, "second": 0}}));
+    let event = feed(json!({"cmd": "CUT_OFF", "msg": "禁播游戏", "roomid": 5440}));
+    assert!(matches!(event, Some(RoomEvent::CutOff { .. })));
+    assert!(!state.is_live);
+    assert_eq!(state.live_time, None);
+    assert_eq!(state.cut_off.as_deref(), Some("禁播游戏"));
+    assert_eq!(state.silent, None);
+}
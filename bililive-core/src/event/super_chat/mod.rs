@@ -0,0 +1,247 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use crate::errors::ParseError;
+use crate::packet::Packet;
+
+use super::types::{as_string, as_u64, FansMedal};
+use super::{cmd, notification};
+
+#[cfg(test)]
+mod tests;
+
+const SUPER_CHAT_MESSAGE: &str = "SUPER_CHAT_MESSAGE";
+const SUPER_CHAT_MESSAGE_JPN: &str = "SUPER_CHAT_MESSAGE_JPN";
+const SUPER_CHAT_MESSAGE_DELETE: &str = "SUPER_CHAT_MESSAGE_DELETE";
+
+/// Colours of a super chat card, in `#RRGGBB`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SuperChatColors {
+    /// Background of the message.
+    pub background: String,
+    /// Background of the header.
+    pub background_bottom: String,
+    /// Background of the price tag.
+    pub background_price: String,
+    /// Colour of the message text.
+    pub message_font: String,
+}
+
+/// A super chat (醒目留言).
+///
+/// Decoded from `SUPER_CHAT_MESSAGE`, or `SUPER_CHAT_MESSAGE_JPN` which carries the translated
+/// message.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SuperChat {
+    /// Super chat id, referred by [`SuperChatDelete`](SuperChatDelete).
+    pub id: u64,
+    /// User id of the sender.
+    pub uid: u64,
+    /// User name of the sender.
+    pub uname: String,
+    /// Avatar url of the sender.
+    pub face: String,
+    /// Guard level of the sender in this room. `0` if the sender is not a guard.
+    pub guard_level: u8,
+    /// Fans medal worn by the sender.
+    pub medal: Option<FansMedal>,
+    /// Message text.
+    pub message: String,
+    /// Translated message text. Empty if it's not translated.
+    pub message_trans: String,
+    /// Price in CNY.
+    pub price: u64,
+    /// How long (in seconds) the super chat is pinned.
+    pub time: u64,
+    /// Unix timestamp (in seconds) when the super chat starts to be pinned.
+    pub start_time: u64,
+    /// Unix timestamp (in seconds) when the super chat stops being pinned.
+    pub end_time: u64,
+    /// Colours of the card.
+    pub colors: SuperChatColors,
+}
+
+impl SuperChat {
+    fn from_value(data: &Value, message_trans: &str) -> Self {
+        let user_info = &data["user_info"];
+        Self {
+            id: as_u64(&data["id"]),
+            uid: as_u64(&data["uid"]),
+            uname: as_string(&user_info["uname"]),
+            face: as_string(&user_info["face"]),
+            guard_level: as_u64(&user_info["guard_level"]) as u8,
+            medal: FansMedal::from_object(&data["medal_info"]),
+            message: as_string(&data["message"]),
+            message_trans: as_string(&data[message_trans]),
+            price: as_u64(&data["price"]),
+            time: as_u64(&data["time"]),
+            start_time: as_u64(&data["start_time"]),
+            end_time: as_u64(&data["end_time"]),
+            colors: SuperChatColors {
+                background: as_string(&data["background_color"]),
+                background_bottom: as_string(&data["background_bottom_color"]),
+                background_price: as_string(&data["background_price_color"]),
+                message_font: as_string(&data["message_font_color"]),
+            },
+        }
+    }
+}
+
+/// Removal of super chats by moderation (`SUPER_CHAT_MESSAGE_DELETE`).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SuperChatDelete {
+    /// Ids of the removed super chats.
+    pub ids: Vec<u64>,
+}
+
+/// A super chat related notification.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum SuperChatEvent {
+    /// A new super chat (`SUPER_CHAT_MESSAGE`).
+    Message(SuperChat),
+    /// Translation of a super chat (`SUPER_CHAT_MESSAGE_JPN`), which may arrive before or after
+    /// the super chat itself.
+    Translated(SuperChat),
+    Delete(SuperChatDelete),
+}
+
+impl SuperChatEvent {
+    /// Decode a super chat related notification packet.
+    ///
+    /// Returns `Ok(None)` if the packet is not super chat related.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is super chat related but its payload is malformed.
+    pub fn from_packet(packet: &Packet) -> Result<Option<Self>, ParseError> {
+        notification(
+            packet,
+            &[
+                SUPER_CHAT_MESSAGE,
+                SUPER_CHAT_MESSAGE_JPN,
+                SUPER_CHAT_MESSAGE_DELETE,
+            ],
+        )?
+        .map(|value| Self::from_value(&value))
+        .transpose()
+    }
+
+    fn from_value(value: &Value) -> Result<Self, ParseError> {
+        let cmd = cmd(value).unwrap_or_default();
+        let data = value
+            .get("data")
+            .filter(|data| data.is_object())
+            .ok_or_else(|| ParseError::Event(format!("{} without data", cmd)))?;
+
+        Ok(match cmd {
+            SUPER_CHAT_MESSAGE => Self::Message(SuperChat::from_value(data, "message_trans")),
+            SUPER_CHAT_MESSAGE_JPN => Self::Translated(SuperChat::from_value(data, "message_jpn")),
+            _ => Self::Delete(SuperChatDelete {
+                ids: data["ids"]
+                    .as_array()
+                    .map(|ids| ids.iter().map(as_u64).collect())
+                    .unwrap_or_default(),
+            }),
+        })
+    }
+}
+
+/// Tracks super chats currently pinned in a room.
+///
+/// Feed it with packets from the live stream, and it keeps the pinned super chats in the order
+/// they start, merging translations and applying deletions. Super chats past their
+/// [`end_time`](SuperChat::end_time) are removed by [`expire`](Self::expire).
+#[derive(Debug, Clone, Default)]
+pub struct SuperChatTracker {
+    active: Vec<SuperChat>,
+}
+
+impl SuperChatTracker {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a packet from the live stream. Returns whether pinned super chats are changed.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is super chat related but its payload is malformed.
+    pub fn feed(&mut self, packet: &Packet) -> Result<bool, ParseError> {
+        Ok(SuperChatEvent::from_packet(packet)?.is_some_and(|event| self.apply(event)))
+    }
+
+    /// Apply a super chat event. Returns whether pinned super chats are changed.
+    pub fn apply(&mut self, event: SuperChatEvent) -> bool {
+        match event {
+            SuperChatEvent::Message(super_chat) => self.upsert(super_chat, false),
+            SuperChatEvent::Translated(super_chat) => self.upsert(super_chat, true),
+            SuperChatEvent::Delete(delete) => {
+                let before = self.active.len();
+                self.active
+                    .retain(|super_chat| !delete.ids.contains(&super_chat.id));
+                self.active.len() != before
+            }
+        }
+    }
+
+    fn upsert(&mut self, super_chat: SuperChat, translated: bool) -> bool {
+        if let Some(existing) = self
+            .active
+            .iter_mut()
+            .find(|existing| existing.id == super_chat.id)
+        {
+            // a translation only updates the translated text, which is kept if the original
+            // message arrives later
+            let mut merged = if translated {
+                SuperChat {
+                    message_trans: super_chat.message_trans,
+                    ..existing.clone()
+                }
+            } else {
+                super_chat
+            };
+            if merged.message_trans.is_empty() {
+                merged.message_trans.clone_from(&existing.message_trans);
+            }
+            let changed = *existing != merged;
+            *existing = merged;
+            return changed;
+        }
+
+        let idx = self
+            .active
+            .partition_point(|existing| existing.start_time <= super_chat.start_time);
+        self.active.insert(idx, super_chat);
+        true
+    }
+
+    /// Remove super chats that have ended by now. Returns the removed ones.
+    pub fn expire(&mut self) -> Vec<SuperChat> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        self.expire_at(now)
+    }
+
+    /// Remove super chats that have ended by the given unix timestamp (in seconds). Returns the
+    /// removed ones.
+    pub fn expire_at(&mut self, now: u64) -> Vec<SuperChat> {
+        let (expired, active) = std::mem::take(&mut self.active)
+            .into_iter()
+            .partition(|super_chat| super_chat.end_time <= now);
+        self.active = active;
+        expired
+    }
+
+    /// Super chats currently pinned, in the order they start.
+    #[must_use]
+    pub fn active(&self) -> &[SuperChat] {
+        &self.active
+    }
+
+    /// Get a pinned super chat by id.
+    #[must_use]
+    pub fn get(&self, id: u64) -> Option<&SuperChat> {
+        self.active.iter().find(|super_chat| super_chat.id == id)
+    }
+}
@@ -0,0 +1,144 @@
+use serde_json::json;
+
+use crate::event::fixtures::{danmu_msg, notification};
+
+use super::{SuperChat, SuperChatColors, SuperChatDelete, SuperChatEvent, SuperChatTracker};
+
+fn super_chat_message(id: u64, start_time: u64) -> serde_json::Value {
+    json!({
+        "cmd": "SUPER_CHAT_MESSAGE",
+        "data": {
+            "id": id,
+            "uid": 174102117,
+            "price": 30,
+            "rate": 1000,
+            "message": "加油",
+            "message_trans": "",
+            "background_color": "#EDF5FF",
+            "background_bottom_color": "#2A60B2",
+            "background_price_color": "#7497CD",
+            "message_font_color": "#A3F6FF",
+            "time": 60,
+            "start_time": start_time,
+            "end_time": start_time + 60,
+            "medal_info": {"medal_level": 0, "medal_name": ""},
+            "user_info": {
+                "uname": "vioIet・伊芙加登",
+                "face": "https://i0.hdslb.com/face.jpg",
+                "guard_level": 3
+            },
+            "ts": start_time
+        },
+        "roomid": 5440
+    })
+}
+
+#[test]
+fn must_decode_super_chat() {
+    let event = SuperChatEvent::from_packet(&notification(&super_chat_message(8265, 1690027262)))
+        .expect("unable to decode super chat")
+        .expect("not a super chat");
+    assert_eq!(
+        event,
+        SuperChatEvent::Message(SuperChat {
+            id: 8265,
+            uid: 174102117,
+            uname: "vioIet・伊芙加登".to_string(),
+            face: "https://i0.hdslb.com/face.jpg".to_string(),
+            guard_level: 3,
+            medal: None,
+            message: "加油".to_string(),
+            message_trans: String::new(),
+            price: 30,
+            time: 60,
+            start_time: 1690027262,
+            end_time: 1690027322,
+            colors: SuperChatColors {
+                background: "#EDF5FF".to_string(),
+                background_bottom: "#2A60B2".to_string(),
+                background_price: "#7497CD".to_string(),
+                message_font: "#A3F6FF".to_string()
+            }
+        })
+    );
+
+    // ids are strings in translations
+    let event = SuperChatEvent::from_packet(&notification(&json!({
+        "cmd": "SUPER_CHAT_MESSAGE_JPN",
+        "data": {
+            "id": "8265", "uid": "174102117", "price": 30, "message": "加油",
+            "message_jpn": "頑張って", "time": 60, "start_time": 1690027262, "end_time": 1690027322,
+            "user_info": {"uname": "vioIet・伊芙加登"}
+        }
+    })))
+    .unwrap()
+    .unwrap();
+    let SuperChatEvent::Translated(super_chat) = event else {
+        panic!("not a translation: {:?}", event)
+    };
+    assert_eq!(
+        (
+            super_chat.id,
+            super_chat.uid,
+            super_chat.message_trans.as_str()
+        ),
+        (8265, 174102117, "頑張って")
+    );
+
+    assert_eq!(
+        SuperChatEvent::from_packet(&notification(&json!({
+            "cmd": "SUPER_CHAT_MESSAGE_DELETE",
+            "data": {"ids": [8265]},
+            "roomid": 5440
+        })))
+        .unwrap(),
+        Some(SuperChatEvent::Delete(SuperChatDelete { ids: vec![8265] }))
+    );
+    assert_eq!(
+        SuperChatEvent::from_packet(&notification(&danmu_msg())).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn must_track_super_chats() {
+    let mut tracker = SuperChatTracker::new();
+    assert!(tracker
+        .feed(&notification(&super_chat_message(8266, 1690027300)))
+        .unwrap());
+    assert!(tracker
+        .feed(&notification(&super_chat_message(8265, 1690027262)))
+        .unwrap());
+    assert!(!tracker.feed(&notification(&danmu_msg())).unwrap());
+    // pinned in the order they start
+    assert_eq!(
+        tracker.active().iter().map(|sc| sc.id).collect::<Vec<_>>(),
+        [8265, 8266]
+    );
+
+    // translations are merged
+    let translated = json!({
+        "cmd": "SUPER_CHAT_MESSAGE_JPN",
+        "data": {"id": "8265", "message": "加油", "message_jpn": "頑張って", "start_time": 1690027262}
+    });
+    assert!(tracker.feed(&notification(&translated)).unwrap());
+    assert!(!tracker.feed(&notification(&translated)).unwrap());
+    // and kept when the message is received again
+    assert!(!tracker
+        .feed(&notification(&super_chat_message(8265, 1690027262)))
+        .unwrap());
+    let super_chat = tracker.get(8265).unwrap();
+    assert_eq!(super_chat.message_trans, "頑張って");
+    assert_eq!(super_chat.end_time, 1690027322);
+
+    // expired by end time
+    let expired = tracker.expire_at(1690027322);
+    assert_eq!(expired.iter().map(|sc| sc.id).collect::<Vec<_>>(), [8265]);
+    assert!(tracker.expire_at(1690027322).is_empty());
+
+    // deleted by moderation
+    let delete = json!({"cmd": "SUPER_CHAT_MESSAGE_DELETE", "data": {"ids": [8266, 1]}});
+    assert!(tracker.feed(&notification(&delete)).unwrap());
+    assert!(!tracker.feed(&notification(&delete)).unwrap());
+    assert!(tracker.active().is_empty());
+}
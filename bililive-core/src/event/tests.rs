@@ -0,0 +1,13 @@
+use serde_json::json;
+
+use super::cmd;
+
+#[test]
+fn must_strip_cmd_suffix() {
+    assert_eq!(
+        cmd(&json!({"cmd": "DANMU_MSG:4:0:2:2:2:0"})),
+        Some("DANMU_MSG")
+    );
+    assert_eq!(cmd(&json!({"cmd": "LIVE"})), Some("LIVE"));
+    assert_eq!(cmd(&json!({"data": {}})), None);
+}
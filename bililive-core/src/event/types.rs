@@ -0,0 +1,91 @@
+use serde_json::{json, Value};
+
+/// A fans medal (粉丝勋章) worn by a user.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct FansMedal {
+    /// Medal level.
+    pub level: u32,
+    /// Medal name.
+    pub name: String,
+    /// User name of the anchor the medal belongs to.
+    pub anchor_uname: String,
+    /// Live room id of the anchor the medal belongs to.
+    pub anchor_room_id: u64,
+    /// User id of the anchor the medal belongs to.
+    pub anchor_uid: u64,
+    /// Guard level of the user in the anchor's room. `0` if the user is not a guard.
+    pub guard_level: u8,
+    /// Whether the medal is lighted.
+    pub is_lighted: bool,
+}
+
+impl FansMedal {
+    /// Decode the positional medal array used in `DANMU_MSG` and danmaku history.
+    ///
+    /// Returns `None` if the user wears no medal.
+    pub(crate) fn from_array(value: &Value) -> Option<Self> {
+        let medal = value.as_array()?;
+        if medal.is_empty() {
+            return None;
+        }
+        Some(Self {
+            level: as_u64(&medal[0]) as u32,
+            name: as_string(medal.get(1)?),
+            anchor_uname: medal.get(2).map(as_string).unwrap_or_default(),
+            anchor_room_id: medal.get(3).map_or(0, as_u64),
+            anchor_uid: medal.get(12).map_or(0, as_u64),
+            guard_level: medal.get(10).map_or(0, as_u64) as u8,
+            is_lighted: medal.get(11).map_or(0, as_u64) != 0,
+        })
+    }
+
+    /// Decode the `medal_info` object used in gift and guard notifications.
+    ///
+    /// Returns `None` if the user wears no medal.
+    pub(crate) fn from_object(value: &Value) -> Option<Self> {
+        let level = as_u64(&value["medal_level"]) as u32;
+        let name = value["medal_name"]
+            .as_str()
+            .filter(|name| !name.is_empty())?;
+        if level == 0 {
+            return None;
+        }
+        Some(Self {
+            level,
+            name: name.to_string(),
+            anchor_uname: as_string(&value["anchor_uname"]),
+            anchor_room_id: as_u64(&value["anchor_roomid"]),
+            anchor_uid: as_u64(&value["target_id"]),
+            guard_level: as_u64(&value["guard_level"]) as u8,
+            is_lighted: as_u64(&value["is_lighted"]) != 0,
+        })
+    }
+
+    /// Encode the medal as a `medal_info` object.
+    pub(crate) fn to_object(&self) -> Value {
+        json!({
+            "medal_level": self.level,
+            "medal_name": self.name,
+            "anchor_uname": self.anchor_uname,
+            "anchor_roomid": self.anchor_room_id,
+            "target_id": self.anchor_uid,
+            "guard_level": self.guard_level,
+            "is_lighted": u8::from(self.is_lighted)
+        })
+    }
+}
+
+/// Read an unsigned integer, which may be encoded as a number or a string.
+pub(crate) fn as_u64(value: &Value) -> u64 {
+    match value {
+        Value::Number(n) => n.as_u64().unwrap_or(0),
+        Value::String(s) => s.parse().unwrap_or(0),
+        Value::Bool(b) => u64::from(*b),
+        _ => 0,
+    }
+}
+
+/// Read a string, or an empty string if it's not one.
+pub(crate) fn as_string(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
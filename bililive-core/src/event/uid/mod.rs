@@ -0,0 +1,323 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Debug, Formatter};
+
+use crate::errors::ParseError;
+use crate::packet::Packet;
+
+use super::Danmaku;
+
+#[cfg(test)]
+mod tests;
+
+/// CRC32 (IEEE) polynomial in reversed form.
+const POLYNOMIAL: u32 = 0xedb8_8320;
+const TABLE: [u32; 256] = crc_table();
+
+/// Digits of the suffixes tabulated for meet-in-the-middle.
+const SUFFIX_DIGITS: u32 = 5;
+const SUFFIX_SPAN: u64 = 10u64.pow(SUFFIX_DIGITS);
+
+/// Digits of uids covered by default.
+const DEFAULT_MAX_DIGITS: u32 = 10;
+/// Digits of uids covered at most. Cracking 12-digit uids takes about a second.
+const MAX_DIGITS: u32 = 12;
+
+/// How many hashes are cached by default.
+const DEFAULT_CACHE_CAPACITY: usize = 4096;
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+fn update(crc: u32, byte: u8) -> u32 {
+    (crc >> 8) ^ TABLE[((crc ^ u32::from(byte)) & 0xff) as usize]
+}
+
+/// Look up all values whose key equals `key` in a sorted table.
+fn lookup(table: &[(u32, u32)], key: u32) -> impl Iterator<Item = u32> + '_ {
+    let start = table.partition_point(|(k, _)| *k < key);
+    table[start..]
+        .iter()
+        .take_while(move |(k, _)| *k == key)
+        .map(|(_, v)| *v)
+}
+
+/// Uids recovered from a CRC32 hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrackedUid {
+    /// Exactly one uid matches the hash.
+    Unique(u64),
+    /// Several uids match the hash, in ascending order.
+    Ambiguous(Vec<u64>),
+    /// No uid of up to `max_digits` digits matches the hash. The uid may be longer.
+    NotFound { max_digits: u32 },
+}
+
+impl CrackedUid {
+    /// The uid, if exactly one uid matches the hash.
+    #[must_use]
+    pub const fn uid(&self) -> Option<u64> {
+        match self {
+            Self::Unique(uid) => Some(*uid),
+            _ => None,
+        }
+    }
+
+    /// All uids matching the hash, in ascending order.
+    #[must_use]
+    pub fn candidates(&self) -> &[u64] {
+        match self {
+            Self::Unique(uid) => std::slice::from_ref(uid),
+            Self::Ambiguous(uids) => uids,
+            Self::NotFound { .. } => &[],
+        }
+    }
+}
+
+/// Recovers uids from their CRC32 hashes.
+///
+/// In anonymous connections, bilibili masks the uid of danmaku senders and only reports
+/// [`user_hash`](Danmaku::user_hash), which is the CRC32 of the uid in decimal. As uids are short
+/// numbers, the hash can be inverted offline.
+///
+/// Uids are split into a prefix and a 5-digit suffix. CRC32 is linear, so the hash of a uid is
+/// the hash of its prefix followed by zeros, xor the hash of its suffix starting from zero. Hashes
+/// of all suffixes are tabulated when the cracker is built, then each prefix is looked up in the
+/// table (meet-in-the-middle).
+///
+/// Building a cracker takes a few milliseconds, so it should be reused. Cracking covers uids of
+/// up to [`max_digits`](Self::with_max_digits) digits, 10 by default, which takes about 10ms in
+/// release builds. Each extra digit makes it ten times slower and yields ten times more
+/// candidates, so at most 12 digits are supported.
+///
+/// Recent accounts have 16-digit uids, which can't be recovered: about two million 16-digit
+/// numbers share each hash. Their hashes are reported as
+/// [`NotFound`](CrackedUid::NotFound), or rarely match a shorter uid by chance.
+#[derive(Clone)]
+pub struct UidCracker {
+    max_digits: u32,
+    /// crc32 (from zero state, without final xor) of each zero-padded suffix, sorted
+    suffixes: Vec<(u32, u32)>,
+    /// crc32 of each uid shorter than the suffix span, sorted
+    short: Vec<(u32, u32)>,
+}
+
+impl Debug for UidCracker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UidCracker")
+            .field("max_digits", &self.max_digits)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for UidCracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UidCracker {
+    /// Build a cracker covering uids of up to 10 digits.
+    #[must_use]
+    pub fn new() -> Self {
+        let mut suffixes = Vec::with_capacity(SUFFIX_SPAN as usize);
+        let mut short = Vec::with_capacity(SUFFIX_SPAN as usize);
+        for n in 0..SUFFIX_SPAN as u32 {
+            let padded = format!("{:0width$}", n, width = SUFFIX_DIGITS as usize);
+            suffixes.push((padded.bytes().fold(0, update), n));
+            short.push((!n.to_string().bytes().fold(0xffff_ffff, update), n));
+        }
+        suffixes.sort_unstable();
+        short.sort_unstable();
+
+        Self {
+            max_digits: DEFAULT_MAX_DIGITS,
+            suffixes,
+            short,
+        }
+    }
+
+    /// Set the maximum digits of uids to recover. It's capped at 12.
+    ///
+    /// Each extra digit makes cracking ten times slower: about 10ms for 10 digits, 100ms for 11
+    /// digits and a second for 12 digits in release builds.
+    #[must_use]
+    pub fn with_max_digits(mut self, max_digits: u32) -> Self {
+        self.max_digits = max_digits.min(MAX_DIGITS);
+        self
+    }
+
+    /// Maximum digits of uids to recover.
+    #[must_use]
+    pub const fn max_digits(&self) -> u32 {
+        self.max_digits
+    }
+
+    /// Find all uids of up to [`max_digits`](Self::max_digits) digits with the given CRC32.
+    ///
+    /// A hash may have several candidates, especially if longer uids are covered.
+    #[must_use]
+    pub fn crack(&self, hash: u32) -> CrackedUid {
+        let mut uids: Vec<u64> = lookup(&self.short, hash)
+            .map(u64::from)
+            .filter(|uid| *uid != 0 && uid.to_string().len() as u32 <= self.max_digits)
+            .collect();
+
+        if self.max_digits > SUFFIX_DIGITS {
+            // state before the final xor
+            let target = !hash;
+            let max_prefix_digits = self.max_digits - SUFFIX_DIGITS;
+            for digit in 1..=9 {
+                self.walk(
+                    update(0xffff_ffff, b'0' + digit),
+                    u64::from(digit),
+                    1,
+                    max_prefix_digits,
+                    target,
+                    &mut uids,
+                );
+            }
+        }
+
+        uids.sort_unstable();
+        match uids.as_slice() {
+            [] => CrackedUid::NotFound {
+                max_digits: self.max_digits,
+            },
+            [uid] => CrackedUid::Unique(*uid),
+            _ => CrackedUid::Ambiguous(uids),
+        }
+    }
+
+    /// Find uids starting with `prefix`, whose crc state is `state`.
+    fn walk(
+        &self,
+        state: u32,
+        prefix: u64,
+        digits: u32,
+        max_digits: u32,
+        target: u32,
+        uids: &mut Vec<u64>,
+    ) {
+        let shifted = (0..SUFFIX_DIGITS).fold(state, |state, _| update(state, 0));
+        uids.extend(
+            lookup(&self.suffixes, shifted ^ target)
+                .map(|suffix| prefix * SUFFIX_SPAN + u64::from(suffix)),
+        );
+
+        if digits < max_digits {
+            for digit in 0..=9 {
+                self.walk(
+                    update(state, b'0' + digit),
+                    prefix * 10 + u64::from(digit),
+                    digits + 1,
+                    max_digits,
+                    target,
+                    uids,
+                );
+            }
+        }
+    }
+}
+
+/// Fills in masked uids of danmaku senders, caching cracked hashes.
+///
+/// Hashes are cracked by [`UidCracker`](UidCracker). Only hashes matching exactly one uid are
+/// filled in, as there's no telling which candidate sent an ambiguous hash.
+/// [`resolve`](Self::resolve) reports all candidates.
+#[derive(Debug, Clone)]
+pub struct UidResolver {
+    cracker: UidCracker,
+    capacity: usize,
+    cache: HashMap<u32, CrackedUid>,
+    cache_order: VecDeque<u32>,
+}
+
+impl Default for UidResolver {
+    fn default() -> Self {
+        Self::new(UidCracker::new())
+    }
+}
+
+impl UidResolver {
+    /// Build a resolver recovering uids with the given cracker, caching up to 4096 hashes.
+    #[must_use]
+    pub fn new(cracker: UidCracker) -> Self {
+        Self {
+            cracker,
+            capacity: DEFAULT_CACHE_CAPACITY,
+            cache: HashMap::new(),
+            cache_order: VecDeque::new(),
+        }
+    }
+
+    /// Set how many hashes are cached.
+    #[must_use]
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Recover the uids with the given CRC32.
+    pub fn resolve(&mut self, hash: u32) -> CrackedUid {
+        if let Some(uid) = self.cache.get(&hash) {
+            return uid.clone();
+        }
+
+        let uid = self.cracker.crack(hash);
+        if self.capacity > 0 {
+            if self.cache_order.len() >= self.capacity {
+                if let Some(oldest) = self.cache_order.pop_front() {
+                    self.cache.remove(&oldest);
+                }
+            }
+            self.cache.insert(hash, uid.clone());
+            self.cache_order.push_back(hash);
+        }
+        uid
+    }
+
+    /// Fill in the uid of a danmaku if it's masked and its hash matches exactly one uid. Returns
+    /// whether it's filled.
+    pub fn fill(&mut self, danmaku: &mut Danmaku) -> bool {
+        if danmaku.uid != 0 {
+            return false;
+        }
+        match danmaku.user_hash.and_then(|hash| self.resolve(hash).uid()) {
+            Some(uid) => {
+                danmaku.uid = uid;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Decode a `DANMU_MSG` notification packet, filling in the uid if it's masked.
+    ///
+    /// Returns `Ok(None)` if the packet is not a danmaku.
+    ///
+    /// # Errors
+    /// Returns an error if the packet is a danmaku but its payload is malformed.
+    pub fn feed(&mut self, packet: &Packet) -> Result<Option<Danmaku>, ParseError> {
+        Ok(Danmaku::from_packet(packet)?.map(|mut danmaku| {
+            self.fill(&mut danmaku);
+            danmaku
+        }))
+    }
+}
@@ -0,0 +1,93 @@
+use serde_json::json;
+
+use crate::event::fixtures::{danmu_msg, notification};
+
+use super::{CrackedUid, UidCracker, UidResolver};
+
+fn crc32(uid: u64) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(uid.to_string().as_bytes());
+    crc.sum()
+}
+
+#[test]
+fn must_crack_uid() {
+    let cracker = UidCracker::new();
+    for uid in [1, 42, 90873, 9617619, 174102117, 3493118494] {
+        let cracked = cracker.crack(crc32(uid));
+        let candidates = cracked.candidates();
+        assert!(candidates.contains(&uid), "{} not in {:?}", uid, candidates);
+        assert!(candidates
+            .iter()
+            .all(|candidate| crc32(*candidate) == crc32(uid)));
+        assert!(candidates.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+    assert_eq!(cracker.crack(0x630b6aa4).candidates()[0], 174102117);
+    assert!(matches!(
+        cracker.crack(0x630b6aa4),
+        CrackedUid::Ambiguous(_)
+    ));
+
+    // uids longer than max digits are not covered
+    let cracker = cracker.with_max_digits(8);
+    assert!(!cracker
+        .crack(crc32(174102117))
+        .candidates()
+        .contains(&174102117));
+    assert!(cracker
+        .crack(crc32(9617619))
+        .candidates()
+        .contains(&9617619));
+    let cracker = cracker.with_max_digits(3);
+    assert_eq!(cracker.crack(crc32(42)), CrackedUid::Unique(42));
+    assert_eq!(
+        cracker.crack(crc32(90873)),
+        CrackedUid::NotFound { max_digits: 3 }
+    );
+    assert_eq!(cracker.crack(crc32(90873)).uid(), None);
+
+    // max digits are capped
+    assert_eq!(cracker.with_max_digits(19).max_digits(), 12);
+}
+
+#[test]
+fn must_resolve_masked_uid() {
+    let mut resolver = UidResolver::new(UidCracker::new().with_max_digits(9)).with_capacity(1);
+
+    let mut value = danmu_msg();
+    value["info"][2][0] = json!(0);
+    let danmaku = resolver
+        .feed(&notification(&value))
+        .expect("unable to decode danmaku")
+        .expect("not a danmaku");
+    assert_eq!(danmaku.uid, 174102117);
+    assert_eq!(resolver.resolve(0x630b6aa4), CrackedUid::Unique(174102117));
+
+    // uids not masked are kept
+    let mut danmaku = danmaku;
+    danmaku.uid = 42;
+    assert!(!resolver.fill(&mut danmaku));
+    assert_eq!(danmaku.uid, 42);
+
+    danmaku.uid = 0;
+    danmaku.user_hash = Some(crc32(9617619));
+    assert!(resolver.fill(&mut danmaku));
+    assert_eq!(danmaku.uid, 9617619);
+
+    // ambiguous hashes are not filled in
+    let mut resolver = UidResolver::default();
+    danmaku.uid = 0;
+    danmaku.user_hash = Some(0x630b6aa4);
+    assert!(!resolver.fill(&mut danmaku));
+    assert_eq!(danmaku.uid, 0);
+    let candidates = resolver.resolve(0x630b6aa4);
+    assert!(candidates.candidates().contains(&174102117));
+    assert_eq!(resolver.resolve(0x630b6aa4), candidates);
+
+    danmaku.user_hash = None;
+    assert!(!resolver.fill(&mut danmaku));
+    assert!(resolver
+        .feed(&notification(&json!({"cmd": "LIVE"})))
+        .unwrap()
+        .is_none());
+}
@@ -9,6 +9,7 @@
 //! - `tokio` (default) - enable tokio support.
 //! - `async-std` - enable async-std support.
 //! - `not-send` - Remove `Send` constraints on traits and types. Useful for actix clients.
+//! - `protobuf` - decode protobuf payloads of newer notifications, e.g. `dm_v2` of `DANMU_MSG`.
 
 #![allow(
     clippy::cast_lossless,
@@ -17,9 +18,15 @@
     clippy::default_trait_access
 )]
 
+mod api;
 pub mod builder;
+pub mod chat;
 pub mod config;
 pub mod errors;
+pub mod event;
+pub mod open;
 pub mod packet;
+pub mod proxy;
 pub mod retry;
+pub mod room;
 pub mod stream;
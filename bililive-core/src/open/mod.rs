@@ -0,0 +1,278 @@
+//! Bilibili open live platform (开放平台).
+//!
+//! Streamer tools and interactive games connect through `live-open.biliapi.com` with an
+//! [`AppKey`](AppKey) instead of a user session. Requests are signed with HMAC-SHA256.
+//!
+//! * [`start`] starts an app session with the anchor's identity code, returning the websocket
+//!   auth body and servers.
+//! * [`heartbeat`] keeps the session alive. It must be called every 20 seconds, see
+//!   [`AppHeartbeatStream`](crate::stream::AppHeartbeatStream).
+//! * [`end`] ends the session.
+//!
+//! Messages pushed in an app session are decoded by [`OpenEvent`](crate::event::OpenEvent).
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use md5::{Digest, Md5};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use serde::de::IgnoredAny;
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::api::ApiResp;
+use crate::builder::{request_json, Request, Requester};
+use crate::config::ServerEndpoint;
+use crate::errors::{ApiError, BuildError};
+
+use self::types::StartInner;
+
+#[cfg(test)]
+mod tests;
+mod types;
+
+/// Base url of open platform apis.
+const API_BASE: &str = "https://live-open.biliapi.com";
+
+/// Access key of an open platform developer.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct AppKey {
+    access_key_id: String,
+    access_key_secret: String,
+}
+
+impl AppKey {
+    /// Construct a key from the access key id and secret.
+    #[must_use]
+    pub fn new(access_key_id: &str, access_key_secret: &str) -> Self {
+        Self {
+            access_key_id: access_key_id.to_string(),
+            access_key_secret: access_key_secret.to_string(),
+        }
+    }
+    /// The access key id.
+    #[must_use]
+    pub fn access_key_id(&self) -> &str {
+        &self.access_key_id
+    }
+}
+
+impl Debug for AppKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("AppKey")
+            .field("access_key_id", &self.access_key_id)
+            .field("access_key_secret", &"<redacted>")
+            .finish()
+    }
+}
+
+/// The anchor an app session is started for.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OpenAnchor {
+    /// Live room id (long version).
+    pub room_id: u64,
+    /// User id of the anchor. `0` if it's not disclosed to the app.
+    pub uid: u64,
+    /// Open id of the anchor, which is unique per app.
+    pub open_id: String,
+    /// User name of the anchor.
+    pub uname: String,
+    /// Avatar url of the anchor.
+    pub uface: String,
+}
+
+/// A started app session.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AppSession {
+    key: AppKey,
+    app_id: u64,
+    game_id: String,
+    auth_body: String,
+    links: Vec<String>,
+    anchor: OpenAnchor,
+}
+
+impl AppSession {
+    /// Restore a started session, e.g. one persisted before a restart.
+    #[must_use]
+    pub fn new(
+        key: AppKey,
+        app_id: u64,
+        game_id: &str,
+        auth_body: &str,
+        links: Vec<String>,
+        anchor: OpenAnchor,
+    ) -> Self {
+        Self {
+            key,
+            app_id,
+            game_id: game_id.to_string(),
+            auth_body: auth_body.to_string(),
+            links,
+            anchor,
+        }
+    }
+}
+
+impl AppSession {
+    /// Key the session is started with.
+    #[must_use]
+    pub const fn key(&self) -> &AppKey {
+        &self.key
+    }
+    /// App id.
+    #[must_use]
+    pub const fn app_id(&self) -> u64 {
+        self.app_id
+    }
+    /// Id of this session. It's empty for apps which are not interactive games.
+    #[must_use]
+    pub fn game_id(&self) -> &str {
+        &self.game_id
+    }
+    /// Payload of the room enter packet, which replaces the one built by
+    /// [`Packet::new_room_enter`](crate::packet::Packet::new_room_enter).
+    #[must_use]
+    pub fn auth_body(&self) -> &str {
+        &self.auth_body
+    }
+    /// Urls of danmaku servers to connect to.
+    #[must_use]
+    pub fn links(&self) -> &[String] {
+        &self.links
+    }
+    /// The anchor this session is started for.
+    #[must_use]
+    pub const fn anchor(&self) -> &OpenAnchor {
+        &self.anchor
+    }
+    /// Parse [`links`](AppSession::links) into server endpoints.
+    ///
+    /// # Errors
+    /// Returns an error if any link is not a valid server url.
+    pub fn servers(&self) -> Result<Vec<ServerEndpoint>, BuildError> {
+        self.links
+            .iter()
+            .map(|link| ServerEndpoint::parse(link))
+            .collect()
+    }
+}
+
+/// Encode bytes into lowercase hex.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Headers signing a request of given body.
+fn sign(key: &AppKey, body: &[u8], timestamp: u64, nonce: &str) -> Vec<(String, String)> {
+    // sorted by name, as required by the signature
+    let mut headers: Vec<(String, String)> = [
+        ("x-bili-accesskeyid", key.access_key_id.clone()),
+        ("x-bili-content-md5", hex(&Md5::digest(body))),
+        ("x-bili-signature-method", "HMAC-SHA256".to_string()),
+        ("x-bili-signature-nonce", nonce.to_string()),
+        ("x-bili-signature-version", "1.0".to_string()),
+        ("x-bili-timestamp", timestamp.to_string()),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect();
+    let canonical = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}", name, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.access_key_secret.as_bytes())
+        .expect("hmac accepts keys of any size");
+    mac.update(canonical.as_bytes());
+    headers.push((
+        "Authorization".to_string(),
+        hex(&mac.finalize().into_bytes()),
+    ));
+    headers.push(("Accept".to_string(), "application/json".to_string()));
+    headers
+}
+
+/// Build a signed request to an open platform api.
+fn signed_request(key: &AppKey, path: &str, body: Value) -> Request {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let nonce: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect();
+    let request = Request::post(&format!("{}{}", API_BASE, path)).json(body);
+    sign(key, &request.body().encode(), timestamp, &nonce)
+        .into_iter()
+        .fold(request, |request, (name, value)| {
+            request.header(&name, value)
+        })
+}
+
+async fn call<H: Requester>(
+    http: &H,
+    key: &AppKey,
+    path: &str,
+    body: Value,
+) -> Result<ApiResp, ApiError> {
+    request_json(http, signed_request(key, path, body))
+        .await
+        .map_err(ApiError::Http)
+}
+
+/// Start an app session with the identity code (身份码) of the anchor.
+///
+/// # Errors
+/// Returns an error when HTTP api request fails or the session is rejected.
+pub async fn start<H: Requester>(
+    http: &H,
+    key: &AppKey,
+    app_id: u64,
+    code: &str,
+) -> Result<AppSession, ApiError> {
+    let resp = call(
+        http,
+        key,
+        "/v2/app/start",
+        json!({"code": code, "app_id": app_id}),
+    )
+    .await?;
+    Ok(resp.into_data::<StartInner>()?.into_session(key, app_id))
+}
+
+/// Keep the app session alive.
+///
+/// # Errors
+/// Returns an error when HTTP api request fails or the session has expired.
+pub async fn heartbeat<H: Requester>(http: &H, session: &AppSession) -> Result<(), ApiError> {
+    let resp = call(
+        http,
+        &session.key,
+        "/v2/app/heartbeat",
+        json!({"game_id": session.game_id}),
+    )
+    .await?;
+    resp.into_data::<IgnoredAny>()?;
+    Ok(())
+}
+
+/// End the app session.
+///
+/// # Errors
+/// Returns an error when HTTP api request fails or the session can't be ended.
+pub async fn end<H: Requester>(http: &H, session: &AppSession) -> Result<(), ApiError> {
+    let resp = call(
+        http,
+        &session.key,
+        "/v2/app/end",
+        json!({"app_id": session.app_id, "game_id": session.game_id}),
+    )
+    .await?;
+    resp.into_data::<IgnoredAny>()?;
+    Ok(())
+}
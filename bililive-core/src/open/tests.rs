@@ -0,0 +1,180 @@
+use std::sync::Mutex;
+
+use futures::executor::block_on;
+use serde_json::json;
+
+use crate::builder::{BoxFuture, ConfigBuilder, Request, Requester, Response};
+use crate::config::ServerEndpoint;
+use crate::errors::{ApiError, BoxedError, BuildError};
+use crate::packet::{Operation, Packet};
+
+use super::{end, heartbeat, sign, start, AppKey, OpenAnchor};
+
+const START_RESP: &str = r#"{"code":0,"message":"0","request_id":"1","data":{"game_info":{"game_id":"e5a2b7b1-3f0c-4d6e-9e7a-2c7f0b6b9c1d"},"websocket_info":{"auth_body":"{\"roomid\":5440,\"protover\":2,\"uid\":0,\"key\":\"xyz\",\"group\":\"open\"}","wss_link":["wss://zj-cn-live-comet.chat.bilibili.com:2245/sub","wss://bd-bj-live-comet-06.chat.bilibili.com:443/sub"]},"anchor_info":{"room_id":5440,"uname":"主播","uface":"https://i0.hdslb.com/face.jpg","uid":9617619,"open_id":"39b8fedb-60a5-4e29-ac75-b16955f7e632"}}}"#;
+
+/// A requester that records requests and replies prepared responses in order.
+struct MockRequester {
+    requests: Mutex<Vec<Request>>,
+    responses: Mutex<Vec<&'static str>>,
+}
+
+impl MockRequester {
+    fn new(responses: &[&'static str]) -> Self {
+        Self {
+            requests: Mutex::new(vec![]),
+            responses: Mutex::new(responses.iter().rev().copied().collect()),
+        }
+    }
+}
+
+impl Requester for MockRequester {
+    fn request(&self, request: Request) -> BoxFuture<'_, Result<Response, BoxedError>> {
+        self.requests.lock().unwrap().push(request);
+        let body = self
+            .responses
+            .lock()
+            .unwrap()
+            .pop()
+            .expect("unexpected request");
+        Box::pin(async move { Ok(Response::new(200, vec![], body.as_bytes().to_vec())) })
+    }
+}
+
+#[test]
+fn must_sign_request() {
+    let headers = sign(
+        &AppKey::new("key", "secret"),
+        br#"{"app_id":1,"code":"ABCDEF"}"#,
+        1_700_000_000,
+        "nonce",
+    );
+    let header = |name: &str, value: &str| (name.to_string(), value.to_string());
+    assert_eq!(
+        headers,
+        [
+            header("x-bili-accesskeyid", "key"),
+            header("x-bili-content-md5", "8da099b77164d78667f2f8ed6e2f3a81"),
+            header("x-bili-signature-method", "HMAC-SHA256"),
+            header("x-bili-signature-nonce", "nonce"),
+            header("x-bili-signature-version", "1.0"),
+            header("x-bili-timestamp", "1700000000"),
+            header(
+                "Authorization",
+                "fa9b3155fffd3fa69680311833e4be7520bfbbbdf3541770c382fea0edb49cd3"
+            ),
+            header("Accept", "application/json"),
+        ]
+    );
+    assert!(!format!("{:?}", AppKey::new("key", "hunter2")).contains("hunter2"));
+}
+
+#[test]
+fn must_start_app_session() {
+    let http = MockRequester::new(&[START_RESP, r#"{"code":0,"message":"0","data":{}}"#]);
+    let key = AppKey::new("key", "secret");
+    let session = block_on(start(&http, &key, 1, "ABCDEF")).expect("unable to start session");
+    assert_eq!(session.app_id(), 1);
+    assert_eq!(session.game_id(), "e5a2b7b1-3f0c-4d6e-9e7a-2c7f0b6b9c1d");
+    assert!(session.auth_body().contains(r#""group":"open""#));
+    assert_eq!(
+        session.anchor(),
+        &OpenAnchor {
+            room_id: 5440,
+            uid: 9617619,
+            open_id: "39b8fedb-60a5-4e29-ac75-b16955f7e632".to_string(),
+            uname: "主播".to_string(),
+            uface: "https://i0.hdslb.com/face.jpg".to_string(),
+        }
+    );
+    assert_eq!(
+        session.servers().unwrap(),
+        [
+            ServerEndpoint::parse("wss://zj-cn-live-comet.chat.bilibili.com:2245/sub").unwrap(),
+            ServerEndpoint::parse("wss://bd-bj-live-comet-06.chat.bilibili.com/sub").unwrap(),
+        ]
+    );
+
+    block_on(end(&http, &session)).expect("unable to end session");
+
+    let requests = http.requests.lock().unwrap();
+    assert_eq!(
+        requests[0].url(),
+        "https://live-open.biliapi.com/v2/app/start"
+    );
+    assert_eq!(
+        requests[0].body().encode(),
+        br#"{"app_id":1,"code":"ABCDEF"}"#
+    );
+    let signature = requests[0]
+        .headers()
+        .iter()
+        .find(|(name, _)| name == "Authorization")
+        .map(|(_, value)| value.clone());
+    assert_eq!(signature.map(|signature| signature.len()), Some(64));
+    assert!(requests[0]
+        .headers()
+        .contains(&("x-bili-accesskeyid".to_string(), "key".to_string())));
+    assert_eq!(
+        requests[1].url(),
+        "https://live-open.biliapi.com/v2/app/end"
+    );
+    assert_eq!(
+        requests[1].body().encode(),
+        serde_json::to_vec(&json!({"app_id": 1, "game_id": session.game_id()})).unwrap()
+    );
+}
+
+#[test]
+fn must_report_heartbeat_error() {
+    let http = MockRequester::new(&[
+        START_RESP,
+        r#"{"code":0,"message":"0","data":{}}"#,
+        r#"{"code":7003,"message":"心跳过期或GameId错误","data":{}}"#,
+    ]);
+    let session = block_on(start(&http, &AppKey::new("key", "secret"), 1, "ABCDEF")).unwrap();
+    block_on(heartbeat(&http, &session)).expect("unable to send heartbeat");
+    assert!(matches!(
+        block_on(heartbeat(&http, &session)),
+        Err(ApiError::Api { code: 7003, .. })
+    ));
+    assert_eq!(
+        http.requests.lock().unwrap()[1].url(),
+        "https://live-open.biliapi.com/v2/app/heartbeat"
+    );
+}
+
+#[test]
+fn must_build_open_platform_config() {
+    let http = MockRequester::new(&[START_RESP]);
+    let config = block_on(
+        ConfigBuilder::<_, _, _, _, _>::new_with_client(http).open_platform(
+            &AppKey::new("key", "secret"),
+            1,
+            "ABCDEF",
+        ),
+    )
+    .expect("unable to start session")
+    .build()
+    .expect("unable to build config");
+    assert_eq!(config.room_id(), 5440);
+    assert_eq!(config.uid(), 9617619);
+    assert_eq!(config.servers().len(), 2);
+    let session = config.app_session().expect("no app session");
+
+    let packet = Packet::new_room_enter(&config);
+    assert_eq!(packet.op(), Operation::RoomEnter);
+    assert_eq!(packet.bytes(), session.auth_body().as_bytes());
+
+    let http = MockRequester::new(&[r#"{"code":7002,"message":"房间重复游戏","data":{}}"#]);
+    let result = block_on(
+        ConfigBuilder::<_, _, _, _, _>::new_with_client(http).open_platform(
+            &AppKey::new("key", "secret"),
+            1,
+            "ABCDEF",
+        ),
+    );
+    assert!(matches!(
+        result,
+        Err(BuildError::OpenPlatform(ApiError::Api { code: 7002, .. }))
+    ));
+}
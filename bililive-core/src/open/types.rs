@@ -0,0 +1,54 @@
+use serde::Deserialize;
+
+use super::{AppKey, AppSession, OpenAnchor};
+
+#[derive(Debug, Deserialize)]
+pub(super) struct StartInner {
+    game_info: GameInfo,
+    websocket_info: WebsocketInfo,
+    anchor_info: AnchorInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct GameInfo {
+    #[serde(default)]
+    game_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebsocketInfo {
+    auth_body: String,
+    wss_link: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnchorInfo {
+    room_id: u64,
+    #[serde(default)]
+    uid: u64,
+    #[serde(default)]
+    open_id: String,
+    #[serde(default)]
+    uname: String,
+    #[serde(default)]
+    uface: String,
+}
+
+impl StartInner {
+    pub(super) fn into_session(self, key: &AppKey, app_id: u64) -> AppSession {
+        AppSession::new(
+            key.clone(),
+            app_id,
+            &self.game_info.game_id,
+            &self.websocket_info.auth_body,
+            self.websocket_info.wss_link,
+            OpenAnchor {
+                room_id: self.anchor_info.room_id,
+                uid: self.anchor_info.uid,
+                open_id: self.anchor_info.open_id,
+                uname: self.anchor_info.uname,
+                uface: self.anchor_info.uface,
+            },
+        )
+    }
+}
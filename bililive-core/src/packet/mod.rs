@@ -3,13 +3,13 @@
 use std::convert::TryInto;
 use std::io::{Cursor, Read, Write};
 
-use brotli_decompressor::{BrotliDecompress, Decompressor};
+use brotli_decompressor::Decompressor;
 use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
 use nom::Err;
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{json, Value};
 
 pub use types::*;
 
@@ -92,22 +92,38 @@ impl Packet {
 }
 
 impl Packet {
+    /// Construct a room enter packet, whose payload is built from
+    /// [`StreamConfig::room_enter`](StreamConfig::room_enter).
+    ///
+    /// In an open platform app session, the [`auth body`](crate::open::AppSession::auth_body) is
+    /// sent as is instead.
     #[allow(clippy::missing_panics_doc)]
     #[must_use]
     pub fn new_room_enter(config: &StreamConfig) -> Self {
+        if let Some(session) = config.app_session() {
+            return Self::new(
+                Operation::RoomEnter,
+                Protocol::Json,
+                session.auth_body().as_bytes(),
+            );
+        }
+        let options = config.room_enter();
+        let mut payload = json!({
+            "uid": config.uid(),
+            "roomid": config.room_id(),
+            "protover": options.protover(),
+            "platform": options.platform(),
+            "type": options.room_type(),
+            "buvid": config.buvid(),
+            "key": config.token()
+        });
+        if let Value::Object(fields) = &mut payload {
+            fields.extend(options.extra().clone());
+        }
         Self::new(
             Operation::RoomEnter,
             Protocol::Json,
-            serde_json::to_vec(&json!({
-                "uid": config.uid(),
-                "roomid": config.room_id(),
-                "protover": 3,
-                "platform": "web",
-                "type": 2,
-                "buvid": config.buvid(),
-                "key": config.token()
-            }))
-            .unwrap(),
+            serde_json::to_vec(&payload).unwrap(),
         )
     }
 }
@@ -185,12 +201,8 @@ impl Packet {
     pub fn parse(input: &[u8]) -> IncompleteResult<(&[u8], Self)> {
         let mut decompressor: Box<dyn Read> = match parser::parse(input) {
             Ok((input, packet)) => match packet.protocol_version {
-                Protocol::Zlib => {
-                    Box::new(ZlibDecoder::new(Cursor::new(packet.data)))
-                }
-                Protocol::Brotli => {
-                    Box::new(Decompressor::new(Cursor::new(packet.data), 4096))
-                }
+                Protocol::Zlib => Box::new(ZlibDecoder::new(Cursor::new(packet.data))),
+                Protocol::Brotli => Box::new(Decompressor::new(Cursor::new(packet.data), 4096)),
                 _ => return IncompleteResult::Ok((input, packet)),
             },
             Err(Err::Incomplete(needed)) => return IncompleteResult::Incomplete(needed),
@@ -206,12 +218,9 @@ impl Packet {
 
         match parser::parse(&buf) {
             Ok((_, packet)) => IncompleteResult::Ok((input, packet)),
-            Err(Err::Incomplete(needed)) => {
-                IncompleteResult::Err(ParseError::PacketError(format!(
-                    "incomplete buffer: {:?} needed",
-                    needed
-                )))
-            }
+            Err(Err::Incomplete(needed)) => IncompleteResult::Err(ParseError::PacketError(
+                format!("incomplete buffer: {:?} needed", needed),
+            )),
             Err(Err::Error(e) | Err::Failure(e)) => {
                 IncompleteResult::Err(ParseError::PacketError(format!("{:?}", e)))
             }
@@ -2,6 +2,7 @@ use std::fs::read;
 
 use serde_json::json;
 
+use crate::config::{RoomEnterOptions, StreamConfig};
 use crate::errors::IncompleteResult;
 
 use super::types::{Operation, Protocol};
@@ -92,3 +93,47 @@ fn must_parse_buffer() {
     expected.set_seq_id(0);
     test_packet("tests/raw/buffer.packet", expected, true);
 }
+
+/// Encode the room enter packet of given config, and split it into header and body.
+fn encode_room_enter(config: &StreamConfig) -> (Vec<u8>, String) {
+    let mut buf = Packet::new_room_enter(config).encode();
+    let body = buf.split_off(16);
+    (buf, String::from_utf8(body).unwrap())
+}
+
+#[test]
+fn must_encode_room_enter() {
+    let config = StreamConfig::new(5440, 1016, "asdf".to_string(), "xdd".to_string(), vec![]);
+    let body = r#"{"buvid":"xdd","key":"asdf","platform":"web","protover":3,"roomid":5440,"type":2,"uid":1016}"#;
+    let (header, encoded_body) = encode_room_enter(&config);
+    assert_eq!(
+        header,
+        [
+            0, 0, 0, 108, // packet length
+            0, 16, // header length
+            0, 0, // protocol version
+            0, 0, 0, 7, // operation
+            0, 0, 0, 1 // sequence id
+        ]
+    );
+    assert_eq!(header[3] as usize, 16 + body.len());
+    assert_eq!(encoded_body, body);
+}
+
+#[test]
+fn must_encode_room_enter_with_options() {
+    let config = StreamConfig::new(5440, 0, "asdf".to_string(), String::new(), vec![])
+        .with_room_enter(
+            RoomEnterOptions::new()
+                .with_protover(2)
+                .with_platform("android")
+                .with_room_type(1)
+                .with_field("clientver", "1.0.0")
+                .with_field("uid", 1016),
+        );
+    let body = r#"{"buvid":"","clientver":"1.0.0","key":"asdf","platform":"android","protover":2,"roomid":5440,"type":1,"uid":1016}"#;
+    let (header, encoded_body) = encode_room_enter(&config);
+    assert_eq!(header[..4], ((16 + body.len()) as u32).to_be_bytes());
+    assert_eq!(header[4..], [0, 16, 0, 0, 0, 0, 0, 7, 0, 0, 0, 1]);
+    assert_eq!(encoded_body, body);
+}
@@ -1,3 +1,4 @@
+use std::fmt::{Debug, Formatter, Result as FmtResult};
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::SeqCst;
 use std::sync::Arc;
@@ -6,37 +7,78 @@ use crate::config::StreamConfig;
 
 /// Internal context for server picking during (re)connection.
 ///
-/// Implements a round-robin policy for server selection.
-#[derive(Debug, Clone)]
-pub struct RetryContext {
+/// Implements a round-robin policy for server selection. Servers are tried in the preference order
+/// of transports (see [`StreamConfig::urls`](StreamConfig::urls)), so that less preferred
+/// transports are used only after all servers in preferred ones are tried.
+///
+/// It also carries the connector `T` used to open connections.
+pub struct RetryContext<T> {
     config: StreamConfig,
+    servers: Arc<[String]>,
     cursor: Arc<AtomicUsize>,
+    connector: Arc<T>,
 }
 
-impl RetryContext {
+impl<T> RetryContext<T> {
+    /// Create a context connecting with given connector.
+    #[must_use]
+    pub fn new(config: StreamConfig, connector: T) -> Self {
+        Self {
+            servers: config.urls().into(),
+            config,
+            cursor: Arc::new(Default::default()),
+            connector: Arc::new(connector),
+        }
+    }
     /// Get the stream config.
     #[must_use]
     pub const fn config(&self) -> &StreamConfig {
         &self.config
     }
+    /// Get the connector.
+    #[must_use]
+    pub fn connector(&self) -> &T {
+        &self.connector
+    }
+    /// Get all servers to be picked from, in preference order.
+    #[must_use]
+    pub fn servers(&self) -> &[String] {
+        &self.servers
+    }
     /// Get the next server.
     #[allow(clippy::missing_panics_doc)]
     pub fn get(&mut self) -> &str {
         let cursor: usize = self
             .cursor
-            .fetch_update(SeqCst, SeqCst, |i| {
-                Some((i + 1) % self.config.servers().len())
-            })
+            .fetch_update(SeqCst, SeqCst, |i| Some((i + 1) % self.servers.len()))
             .unwrap();
-        &*self.config.servers()[cursor]
+        &self.servers[cursor]
     }
 }
 
-impl From<StreamConfig> for RetryContext {
-    fn from(config: StreamConfig) -> Self {
+impl<T> Clone for RetryContext<T> {
+    fn clone(&self) -> Self {
         Self {
-            config,
-            cursor: Arc::new(Default::default()),
+            config: self.config.clone(),
+            servers: self.servers.clone(),
+            cursor: self.cursor.clone(),
+            connector: self.connector.clone(),
         }
     }
 }
+
+impl<T> Debug for RetryContext<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("RetryContext")
+            .field("config", &self.config)
+            .field("servers", &self.servers)
+            .field("cursor", &self.cursor)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Default> From<StreamConfig> for RetryContext<T> {
+    fn from(config: StreamConfig) -> Self {
+        Self::new(config, T::default())
+    }
+}
@@ -8,18 +8,22 @@ use std::pin::Pin;
 
 use futures::SinkExt;
 use futures::{Sink, Stream};
+use log::warn;
 use stream_reconnect::UnderlyingStream;
 
 pub use config::RetryConfig;
 pub use context::RetryContext;
 pub use policy::BEBIterator;
 
+use crate::config::StreamConfig;
 use crate::errors::StreamError;
 use crate::packet::Packet;
 
 mod config;
 mod context;
 mod policy;
+#[cfg(test)]
+mod tests;
 
 /// Trait of helper objects to connect bilibili websocket server.
 ///
@@ -28,6 +32,9 @@ mod policy;
 /// An implementation of `WsStreamTrait` takes in a ws server url and decodes the data into a stream
 /// of [`Packet`](crate::packet::Packet) with heartbeat auto-response mechanism implemented
 /// (see [`HeartbeatStream`](crate::stream::HeartbeatStream) for details).
+///
+/// Implementors may carry state like TLS configurations, which is shared among all
+/// (re)connections through [`RetryContext`](RetryContext).
 #[cfg(feature = "not-send")]
 pub trait WsStreamTrait<E> {
     /// The returned stream type.
@@ -37,9 +44,16 @@ pub trait WsStreamTrait<E> {
         + Sized;
     /// Connect to bilibili websocket server.
     ///
+    /// Implementations should send [`headers`](StreamConfig::headers) along with websocket
+    /// handshakes, and connect through the [`proxy`](StreamConfig::proxy) if there's one.
+    ///
     /// # Errors
     /// Returns an error when websocket connection fails.
-    fn connect(url: &str) -> Pin<Box<dyn Future<Output = Result<Self::Stream, E>> + '_>>;
+    fn connect<'a>(
+        &'a self,
+        url: &'a str,
+        config: &'a StreamConfig,
+    ) -> Pin<Box<dyn Future<Output = Result<Self::Stream, E>> + 'a>>;
 }
 
 #[cfg(not(feature = "not-send"))]
@@ -52,9 +66,16 @@ pub trait WsStreamTrait<E> {
         + Send;
     /// Connect to bilibili websocket server.
     ///
+    /// Implementations should send [`headers`](StreamConfig::headers) along with websocket
+    /// handshakes, and connect through the [`proxy`](StreamConfig::proxy) if there's one.
+    ///
     /// # Errors
     /// Returns an error when websocket connection fails.
-    fn connect(url: &str) -> Pin<Box<dyn Future<Output = Result<Self::Stream, E>> + Send + '_>>;
+    fn connect<'a>(
+        &'a self,
+        url: &'a str,
+        config: &'a StreamConfig,
+    ) -> Pin<Box<dyn Future<Output = Result<Self::Stream, E>> + Send + 'a>>;
 }
 
 /// Wrapper for types implementing `WsStreamTrait`.
@@ -71,46 +92,70 @@ where
     ///
     /// # Errors
     /// Returns an error when websocket connection fails.
-    pub async fn connect(url: &str) -> Result<T::Stream, E> {
-        T::connect(url).await
+    pub async fn connect(connector: &T, url: &str, config: &StreamConfig) -> Result<T::Stream, E> {
+        connector.connect(url, config).await
+    }
+}
+
+/// Connect to the next available server and enter the room.
+///
+/// Servers are tried in order starting from the cursor of the context, until one of them is
+/// connected or all of them have failed.
+async fn establish<T, E>(mut ctor_arg: RetryContext<T>) -> Result<T::Stream, StreamError<E>>
+where
+    T: WsStreamTrait<E>,
+    E: std::error::Error,
+{
+    let attempts = ctor_arg.servers().len();
+    if attempts == 0 {
+        return Err(StreamError::IO(io::Error::new(
+            ErrorKind::NotFound,
+            "No server available in preferred transports.",
+        )));
     }
+
+    let mut attempt = 0;
+    let mut ws = loop {
+        attempt += 1;
+        let server = ctor_arg.get().to_string();
+        let e = match ctor_arg
+            .connector()
+            .connect(&server, ctor_arg.config())
+            .await
+        {
+            Ok(ws) => break ws,
+            Err(e) => e,
+        };
+        if attempt >= attempts {
+            return Err(StreamError::from_ws_error(e));
+        }
+        warn!("unable to connect to {}: {}, trying next server", server, e);
+    };
+    ws.send(Packet::new_room_enter(ctor_arg.config())).await?;
+    Ok(ws)
 }
 
 #[allow(clippy::type_complexity)]
-impl<T, E> UnderlyingStream<RetryContext, Result<Packet, StreamError<E>>, StreamError<E>>
+impl<T, E> UnderlyingStream<RetryContext<T>, Result<Packet, StreamError<E>>, StreamError<E>>
     for WsStream<T, E>
 where
-    T: WsStreamTrait<E>,
+    T: WsStreamTrait<E> + Send + Sync + 'static,
     E: std::error::Error,
 {
     type Stream = T::Stream;
 
     #[cfg(feature = "not-send")]
     fn establish(
-        mut ctor_arg: RetryContext,
+        ctor_arg: RetryContext<T>,
     ) -> Pin<Box<dyn Future<Output = Result<Self::Stream, StreamError<E>>>>> {
-        Box::pin(async move {
-            let server = ctor_arg.get();
-            let mut ws = Self::connect(server)
-                .await
-                .map_err(StreamError::from_ws_error)?;
-            ws.send(Packet::new_room_enter(ctor_arg.config())).await?;
-            Ok(ws)
-        })
+        Box::pin(async move { establish::<T, E>(ctor_arg).await })
     }
 
     #[cfg(not(feature = "not-send"))]
     fn establish(
-        mut ctor_arg: RetryContext,
+        ctor_arg: RetryContext<T>,
     ) -> Pin<Box<dyn Future<Output = Result<Self::Stream, StreamError<E>>> + Send>> {
-        Box::pin(async move {
-            let server = ctor_arg.get();
-            let mut ws = Self::connect(server)
-                .await
-                .map_err(StreamError::from_ws_error)?;
-            ws.send(Packet::new_room_enter(ctor_arg.config())).await?;
-            Ok(ws)
-        })
+        Box::pin(async move { establish::<T, E>(ctor_arg).await })
     }
 
     fn is_write_disconnect_error(err: &StreamError<E>) -> bool {
@@ -56,6 +56,7 @@ impl Iterator for BEBIterator {
             } else {
                 self.count
             });
+            self.count += 1;
             let between = Uniform::new_inclusive(0, max_delay * 100);
             let units = thread_rng().sample(between);
             Some(self.unit * units / 100)
@@ -0,0 +1,19 @@
+use std::time::Duration;
+
+use super::BEBIterator;
+
+#[test]
+fn must_backoff_exponentially() {
+    let delays: Vec<_> = BEBIterator::new(Duration::from_secs(1), 2, 5).collect();
+    assert_eq!(delays.len(), 5);
+    for (delay, max) in delays.into_iter().zip([1, 2, 4, 4, 4]) {
+        assert!(delay <= Duration::from_secs(max), "{delay:?} > {max}s");
+    }
+}
+
+#[test]
+fn must_give_up_after_fail_count() {
+    let mut policy = BEBIterator::default();
+    assert_eq!(policy.by_ref().count(), 10);
+    assert_eq!(policy.next(), None);
+}
@@ -0,0 +1,91 @@
+//! Live room information.
+//!
+//! Room information is fetched through a [`Requester`](crate::builder::Requester), so any HTTP
+//! client used by [`ConfigBuilder`](crate::builder::ConfigBuilder) can be reused here.
+//!
+//! * [`get_info_by_room`] queries `xlive/web-room/v1/index/getInfoByRoom`, which includes the anchor profile.
+//! * [`get_info`] queries the lighter `room/v1/Room/get_info`, which doesn't.
+//! * [`get_history`] queries recent danmaku via `xlive/web-room/v1/dM/gethistory`.
+
+pub(crate) use types::history_danmaku;
+pub use types::{AnchorInfo, LiveStatus, RoomInfo};
+
+use serde_json::Value;
+
+use crate::api::ApiResp;
+use crate::builder::{request_json, Request, Requester};
+use crate::errors::ApiError;
+use crate::event::Danmaku;
+
+use self::types::{HistoryInner, InfoByRoomInner, InfoInner};
+
+#[cfg(test)]
+mod tests;
+mod types;
+
+/// Fetch room information together with the anchor profile.
+///
+/// `room_id` can be either the short or the long room id.
+///
+/// # Errors
+/// Returns an error when HTTP api request fails or the room doesn't exist.
+pub async fn get_info_by_room<H: Requester>(http: &H, room_id: u64) -> Result<RoomInfo, ApiError> {
+    let resp: ApiResp = request_json(
+        http,
+        Request::get("https://api.live.bilibili.com/xlive/web-room/v1/index/getInfoByRoom")
+            .query("room_id", room_id),
+    )
+    .await
+    .map_err(ApiError::Http)?;
+    Ok(resp.into_data::<InfoByRoomInner>()?.into())
+}
+
+/// Fetch room information without the anchor profile.
+///
+/// This api is cheaper than [`get_info_by_room`] and is preferred for frequent live status polling.
+/// [`RoomInfo::anchor`](RoomInfo::anchor) is always `None`.
+///
+/// `room_id` can be either the short or the long room id.
+///
+/// # Errors
+/// Returns an error when HTTP api request fails or the room doesn't exist.
+pub async fn get_info<H: Requester>(http: &H, room_id: u64) -> Result<RoomInfo, ApiError> {
+    let resp: ApiResp = request_json(
+        http,
+        Request::get("https://api.live.bilibili.com/room/v1/Room/get_info")
+            .query("room_id", room_id),
+    )
+    .await
+    .map_err(ApiError::Http)?;
+    Ok(resp.into_data::<InfoInner>()?.into())
+}
+
+/// Fetch recent danmaku of the room, including the last messages of admins and other users.
+///
+/// Danmaku are returned in chronological order, with [`Danmaku::is_history`](Danmaku::is_history) set.
+///
+/// # Errors
+/// Returns an error when HTTP api request fails.
+pub async fn get_history<H: Requester>(http: &H, room_id: u64) -> Result<Vec<Danmaku>, ApiError> {
+    Ok(get_history_entries(http, room_id)
+        .await?
+        .into_iter()
+        .map(|(danmaku, _)| danmaku)
+        .collect())
+}
+
+/// Fetch recent danmaku of the room like [`get_history`](get_history), along with their original
+/// entries in the api response.
+pub(crate) async fn get_history_entries<H: Requester>(
+    http: &H,
+    room_id: u64,
+) -> Result<Vec<(Danmaku, Value)>, ApiError> {
+    let resp: ApiResp = request_json(
+        http,
+        Request::get("https://api.live.bilibili.com/xlive/web-room/v1/dM/gethistory")
+            .query("roomid", room_id),
+    )
+    .await
+    .map_err(ApiError::Http)?;
+    Ok(resp.into_data::<HistoryInner>()?.into_entries()?)
+}
@@ -0,0 +1,132 @@
+use crate::api::ApiResp;
+use crate::errors::ApiError;
+
+use super::types::{parse_cst_datetime, HistoryInner, InfoByRoomInner, InfoInner};
+use super::{AnchorInfo, LiveStatus, RoomInfo};
+
+#[test]
+fn must_parse_info_by_room() {
+    let data = include_str!("../../tests/getInfoByRoom.json");
+    let resp: ApiResp = serde_json::from_str(data).expect("unable to parse response");
+    let info: RoomInfo = resp
+        .into_data::<InfoByRoomInner>()
+        .expect("unable to parse room info")
+        .into();
+    assert_eq!(info.room_id, 5440);
+    assert_eq!(info.short_id, 6);
+    assert_eq!(info.uid, 9617619);
+    assert_eq!(info.live_status, LiveStatus::Live);
+    assert!(info.is_live());
+    assert_eq!(info.live_start_time, Some(1690027203));
+    assert_eq!(info.title, "哔哩哔哩英雄联盟赛事");
+    assert_eq!(info.area_id, 86);
+    assert_eq!(info.area_name, "英雄联盟");
+    assert_eq!(info.parent_area_id, 2);
+    assert_eq!(info.parent_area_name, "网游");
+    assert_eq!(info.online, 6234451);
+    assert_eq!(info.tags, ["英雄联盟", "LPL", "赛事"]);
+    assert_eq!(
+        info.anchor,
+        Some(AnchorInfo {
+            name: "哔哩哔哩英雄联盟赛事".to_string(),
+            face: "http://i0.hdslb.com/bfs/face/b5f2ba2d4e1f0e27f2d7aef4bcb66ae6d9ee0fc1.jpg"
+                .to_string(),
+            level: 40
+        })
+    );
+}
+
+#[test]
+fn must_parse_info() {
+    let data = include_str!("../../tests/getInfo.json");
+    let resp: ApiResp = serde_json::from_str(data).expect("unable to parse response");
+    let info: RoomInfo = resp
+        .into_data::<InfoInner>()
+        .expect("unable to parse room info")
+        .into();
+    assert_eq!(info.room_id, 5440);
+    assert_eq!(info.live_status, LiveStatus::Live);
+    assert_eq!(info.live_start_time, Some(1690027203));
+    assert_eq!(
+        info.cover,
+        "https://i0.hdslb.com/bfs/live/new_room_cover/0ad4d0e6b0d0d3a4f7f5d4a4e5e6c8d0c7c7f9e1.jpg"
+    );
+    assert_eq!(info.tags, ["英雄联盟", "LPL", "赛事"]);
+    assert_eq!(info.anchor, None);
+}
+
+#[test]
+fn must_parse_offline_info() {
+    let data = r#"{"code":0,"msg":"ok","message":"ok","data":{"uid":1,"room_id":1016,"short_id":0,"online":0,"live_status":0,"area_id":0,"parent_area_id":0,"parent_area_name":"","title":"","user_cover":"","keyframe":"","live_time":"0000-00-00 00:00:00","tags":"","area_name":""}}"#;
+    let resp: ApiResp = serde_json::from_str(data).expect("unable to parse response");
+    let info: RoomInfo = resp
+        .into_data::<InfoInner>()
+        .expect("unable to parse room info")
+        .into();
+    assert_eq!(info.live_status, LiveStatus::Offline);
+    assert_eq!(info.live_start_time, None);
+    assert!(info.tags.is_empty());
+}
+
+#[test]
+fn must_report_api_error() {
+    let data = r#"{"code":1,"msg":"房间不存在","message":"房间不存在","data":[]}"#;
+    let resp: ApiResp = serde_json::from_str(data).expect("unable to parse response");
+    match resp.into_data::<InfoInner>() {
+        Err(ApiError::Api { code, message }) => {
+            assert_eq!(code, 1);
+            assert_eq!(message, "房间不存在");
+        }
+        _ => panic!("api error expected"),
+    }
+}
+
+#[test]
+fn must_parse_cst_datetime() {
+    assert_eq!(parse_cst_datetime("1970-01-01 08:00:00"), Some(0));
+    assert_eq!(parse_cst_datetime("2023-07-22 20:00:03"), Some(1690027203));
+    assert_eq!(parse_cst_datetime("2024-02-29 23:59:59"), Some(1709222399));
+    assert_eq!(parse_cst_datetime("0000-00-00 00:00:00"), None);
+    assert_eq!(parse_cst_datetime(""), None);
+}
+
+#[test]
+fn must_parse_history() {
+    let data = include_str!("../../tests/getHistory.json");
+    let resp: ApiResp = serde_json::from_str(data).expect("unable to parse response");
+    let history = resp
+        .into_data::<HistoryInner>()
+        .expect("unable to parse history")
+        .into_entries()
+        .expect("unable to parse history entries");
+    let history: Vec<_> = history.into_iter().map(|(danmaku, _)| danmaku).collect();
+    assert_eq!(history.len(), 2);
+
+    // sorted in chronological order
+    let anonymous = &history[0];
+    assert_eq!(anonymous.text, "来了");
+    assert_eq!(anonymous.uid, 0);
+    assert_eq!(anonymous.uname, "张***");
+    assert_eq!(anonymous.user_hash, None);
+    assert_eq!(anonymous.timestamp, 1690027230000);
+    assert_eq!(anonymous.medal, None);
+    assert!(anonymous.is_history);
+
+    let danmaku = &history[1];
+    assert_eq!(danmaku.text, "晚上好");
+    assert_eq!(danmaku.uid, 174102117);
+    assert_eq!(danmaku.user_hash, Some(0x630b6aa4));
+    assert_eq!(danmaku.user_level, 20);
+    assert_eq!(danmaku.guard_level, 3);
+    assert_eq!(
+        danmaku
+            .medal
+            .as_ref()
+            .map(|medal| (medal.level, medal.anchor_room_id)),
+        Some((21, 5440))
+    );
+    assert_eq!(
+        danmaku.check_info.as_ref().map(|info| info.ct.as_str()),
+        Some("A1B2C3D4")
+    );
+}
@@ -0,0 +1,328 @@
+use flate2::Crc;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::event::{CheckInfo, Danmaku, FansMedal};
+
+/// Live status of a room.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize)]
+#[serde(from = "u8")]
+pub enum LiveStatus {
+    /// The room is not streaming.
+    Offline,
+    /// The room is streaming.
+    Live,
+    /// The room is replaying recorded videos (轮播).
+    Round,
+}
+
+impl From<u8> for LiveStatus {
+    fn from(i: u8) -> Self {
+        match i {
+            1 => Self::Live,
+            2 => Self::Round,
+            _ => Self::Offline,
+        }
+    }
+}
+
+/// Profile of the anchor of a live room.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AnchorInfo {
+    /// User name.
+    pub name: String,
+    /// Avatar url.
+    pub face: String,
+    /// Anchor (live) level.
+    pub level: u32,
+}
+
+/// Information of a live room.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RoomInfo {
+    /// Live room id (long version).
+    pub room_id: u64,
+    /// Short room id. `0` if the room has none.
+    pub short_id: u64,
+    /// User id of the anchor.
+    pub uid: u64,
+    /// Live status.
+    pub live_status: LiveStatus,
+    /// Unix timestamp (in seconds) when the current live started. `None` if not streaming.
+    pub live_start_time: Option<u64>,
+    /// Room title.
+    pub title: String,
+    /// Area (sub-category) id.
+    pub area_id: u64,
+    /// Area (sub-category) name.
+    pub area_name: String,
+    /// Parent area (category) id.
+    pub parent_area_id: u64,
+    /// Parent area (category) name.
+    pub parent_area_name: String,
+    /// Online count (popularity) reported by the api.
+    pub online: u64,
+    /// Cover url.
+    pub cover: String,
+    /// Keyframe (live screenshot) url.
+    pub keyframe: String,
+    /// Room tags.
+    pub tags: Vec<String>,
+    /// Anchor profile. Only available via [`get_info_by_room`](super::get_info_by_room).
+    pub anchor: Option<AnchorInfo>,
+}
+
+impl RoomInfo {
+    /// Whether the room is streaming now.
+    #[must_use]
+    pub fn is_live(&self) -> bool {
+        self.live_status == LiveStatus::Live
+    }
+}
+
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Parse a `YYYY-MM-DD hh:mm:ss` datetime in China Standard Time (UTC+8) into a unix timestamp.
+pub(super) fn parse_cst_datetime(s: &str) -> Option<u64> {
+    let (date, time) = s.split_once(' ')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if year == 0 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // days from civil, see http://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let ts = days * 86400 + hour * 3600 + minute * 60 + second - 8 * 3600;
+    u64::try_from(ts).ok()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InfoByRoomInner {
+    room_info: InfoByRoomRoom,
+    anchor_info: InfoByRoomAnchor,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct InfoByRoomRoom {
+    room_id: u64,
+    #[serde(default)]
+    short_id: u64,
+    uid: u64,
+    live_status: LiveStatus,
+    #[serde(default)]
+    live_start_time: u64,
+    title: String,
+    area_id: u64,
+    area_name: String,
+    parent_area_id: u64,
+    parent_area_name: String,
+    #[serde(default)]
+    online: u64,
+    #[serde(default)]
+    cover: String,
+    #[serde(default)]
+    keyframe: String,
+    #[serde(default)]
+    tags: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct InfoByRoomAnchor {
+    base_info: InfoByRoomAnchorBase,
+    #[serde(default)]
+    live_info: InfoByRoomAnchorLive,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct InfoByRoomAnchorBase {
+    uname: String,
+    face: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct InfoByRoomAnchorLive {
+    #[serde(default)]
+    level: u32,
+}
+
+impl From<InfoByRoomInner> for RoomInfo {
+    fn from(resp: InfoByRoomInner) -> Self {
+        let room = resp.room_info;
+        let anchor = resp.anchor_info;
+        Self {
+            room_id: room.room_id,
+            short_id: room.short_id,
+            uid: room.uid,
+            live_status: room.live_status,
+            live_start_time: Some(room.live_start_time).filter(|ts| *ts != 0),
+            title: room.title,
+            area_id: room.area_id,
+            area_name: room.area_name,
+            parent_area_id: room.parent_area_id,
+            parent_area_name: room.parent_area_name,
+            online: room.online,
+            cover: room.cover,
+            keyframe: room.keyframe,
+            tags: split_tags(&room.tags),
+            anchor: Some(AnchorInfo {
+                name: anchor.base_info.uname,
+                face: anchor.base_info.face,
+                level: anchor.live_info.level,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InfoInner {
+    room_id: u64,
+    #[serde(default)]
+    short_id: u64,
+    uid: u64,
+    live_status: LiveStatus,
+    #[serde(default)]
+    live_time: String,
+    title: String,
+    area_id: u64,
+    area_name: String,
+    parent_area_id: u64,
+    parent_area_name: String,
+    #[serde(default)]
+    online: u64,
+    #[serde(default)]
+    user_cover: String,
+    #[serde(default)]
+    keyframe: String,
+    #[serde(default)]
+    tags: String,
+}
+
+impl From<InfoInner> for RoomInfo {
+    fn from(resp: InfoInner) -> Self {
+        Self {
+            room_id: resp.room_id,
+            short_id: resp.short_id,
+            uid: resp.uid,
+            live_status: resp.live_status,
+            live_start_time: parse_cst_datetime(&resp.live_time),
+            title: resp.title,
+            area_id: resp.area_id,
+            area_name: resp.area_name,
+            parent_area_id: resp.parent_area_id,
+            parent_area_name: resp.parent_area_name,
+            online: resp.online,
+            cover: resp.user_cover,
+            keyframe: resp.keyframe,
+            tags: split_tags(&resp.tags),
+            anchor: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryInner {
+    #[serde(default)]
+    admin: Vec<Value>,
+    #[serde(default)]
+    room: Vec<Value>,
+}
+
+impl HistoryInner {
+    /// All entries in chronological order, along with their original json.
+    pub fn into_entries(self) -> Result<Vec<(Danmaku, Value)>, serde_json::Error> {
+        let mut entries = self
+            .admin
+            .into_iter()
+            .chain(self.room)
+            .map(|entry| Ok((history_danmaku(&entry)?, entry)))
+            .collect::<Result<Vec<_>, serde_json::Error>>()?;
+        entries.sort_by_key(|(danmaku, _)| danmaku.timestamp);
+        Ok(entries)
+    }
+}
+
+/// Decode an entry of the danmaku history.
+pub fn history_danmaku(entry: &Value) -> Result<Danmaku, serde_json::Error> {
+    HistoryEntry::deserialize(entry).map(Into::into)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct HistoryEntry {
+    text: String,
+    #[serde(default)]
+    uid: u64,
+    #[serde(default)]
+    nickname: String,
+    #[serde(default)]
+    timeline: String,
+    #[serde(default)]
+    isadmin: u8,
+    #[serde(default)]
+    medal: Value,
+    #[serde(default)]
+    user_level: Value,
+    #[serde(default)]
+    guard_level: u8,
+    check_info: Option<HistoryCheckInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct HistoryCheckInfo {
+    ts: u64,
+    ct: String,
+}
+
+impl From<HistoryEntry> for Danmaku {
+    fn from(entry: HistoryEntry) -> Self {
+        let timestamp = entry
+            .check_info
+            .as_ref()
+            .map(|info| info.ts)
+            .or_else(|| parse_cst_datetime(&entry.timeline))
+            .unwrap_or_default()
+            * 1000;
+        let user_hash = (entry.uid != 0).then(|| {
+            let mut crc = Crc::new();
+            crc.update(entry.uid.to_string().as_bytes());
+            crc.sum()
+        });
+        Self {
+            text: entry.text,
+            uid: entry.uid,
+            uname: entry.nickname,
+            face: String::new(),
+            user_hash,
+            timestamp,
+            mode: 1,
+            font_size: 25,
+            color: 0xff_ff_ff,
+            is_admin: entry.isadmin != 0,
+            user_level: entry.user_level.get(0).and_then(Value::as_u64).unwrap_or(0) as u32,
+            guard_level: entry.guard_level,
+            medal: FansMedal::from_array(&entry.medal),
+            check_info: entry.check_info.map(|info| CheckInfo {
+                ts: info.ts,
+                ct: info.ct,
+            }),
+            reply: None,
+            emoticons: Vec::new(),
+            is_history: true,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::FutureExt;
+use futures::{Sink, Stream};
+use log::{debug, warn};
+
+use crate::builder::{BoxFuture, Requester};
+use crate::errors::{ApiError, StreamError};
+use crate::open::{self, AppSession};
+use crate::packet::Packet;
+
+use super::waker::wake_after;
+
+type HeartbeatFuture = BoxFuture<'static, Result<(), ApiError>>;
+
+/// Interval of app heartbeats. Sessions without heartbeats in 60 seconds are closed remotely.
+const APP_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Wrapper that keeps an open platform app session alive on a [`Packet`](crate::packet::Packet)
+/// stream.
+///
+/// Apart from the websocket heartbeat (see [`HeartbeatStream`](super::HeartbeatStream)), the open
+/// platform requires an [`app heartbeat`](crate::open::heartbeat) every 20 seconds over http.
+/// `AppHeartbeatStream` sends it in the background while the stream is polled.
+///
+/// Failed app heartbeats are logged and retried in the next interval. The session is not ended
+/// when the stream is dropped, call [`open::end`](crate::open::end) for that.
+pub struct AppHeartbeatStream<T, H, E> {
+    /// underlying bilibili stream
+    stream: T,
+    /// http client to send app heartbeats with
+    http: Arc<H>,
+    session: Arc<AppSession>,
+    /// pending app heartbeat request
+    beating: Option<HeartbeatFuture>,
+    /// last time when app heartbeat is sent
+    last_hb: Option<Instant>,
+    __marker: PhantomData<E>,
+}
+
+impl<T: Unpin, H, E> Unpin for AppHeartbeatStream<T, H, E> {}
+
+impl<T, H, E> AppHeartbeatStream<T, H, E> {
+    /// Add app heartbeat mechanism to the underlying bililive stream of given app session.
+    pub fn new(stream: T, http: H, session: AppSession) -> Self {
+        Self {
+            stream,
+            http: Arc::new(http),
+            session: Arc::new(session),
+            beating: None,
+            last_hb: None,
+            __marker: PhantomData,
+        }
+    }
+
+    /// The app session kept alive.
+    #[must_use]
+    pub fn session(&self) -> &AppSession {
+        &self.session
+    }
+}
+
+impl<T, H, E> AppHeartbeatStream<T, H, E>
+where
+    H: Requester + 'static,
+{
+    fn beat(&mut self) {
+        debug!("sending app heartbeat");
+        let http = self.http.clone();
+        let session = self.session.clone();
+        self.beating = Some(Box::pin(
+            async move { open::heartbeat(&*http, &session).await },
+        ));
+    }
+}
+
+impl<T, H, E> Stream for AppHeartbeatStream<T, H, E>
+where
+    T: Stream<Item = Result<Packet, StreamError<E>>> + Unpin,
+    H: Requester + 'static,
+{
+    type Item = Result<Packet, StreamError<E>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let now = Instant::now();
+        let need_hb = self
+            .last_hb
+            .is_none_or(|last_hb| now - last_hb >= APP_HEARTBEAT_INTERVAL);
+        if need_hb && self.beating.is_none() {
+            self.beat();
+            self.last_hb = Some(now);
+            wake_after(cx.waker(), APP_HEARTBEAT_INTERVAL);
+        }
+
+        if let Some(beating) = &mut self.beating {
+            if let Poll::Ready(result) = beating.poll_unpin(cx) {
+                self.beating = None;
+                if let Err(e) = result {
+                    warn!("unable to send app heartbeat: {}", e);
+                }
+            }
+        }
+
+        Pin::new(&mut self.stream).poll_next(cx)
+    }
+}
+
+impl<T, H, E> Sink<Packet> for AppHeartbeatStream<T, H, E>
+where
+    T: Sink<Packet, Error = StreamError<E>> + Unpin,
+{
+    type Error = StreamError<E>;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Packet) -> Result<(), Self::Error> {
+        Pin::new(&mut self.stream).start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
+}
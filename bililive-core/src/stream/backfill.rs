@@ -0,0 +1,171 @@
+use std::collections::{HashSet, VecDeque};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::FutureExt;
+use futures::{Sink, Stream};
+use log::{debug, warn};
+use serde_json::Value;
+
+use crate::builder::{BoxFuture, Requester};
+use crate::errors::{ApiError, StreamError};
+use crate::event::Danmaku;
+use crate::packet::{Operation, Packet};
+use crate::room;
+
+type HistoryFuture = BoxFuture<'static, Result<Vec<(Danmaku, Value)>, ApiError>>;
+
+/// How many recently delivered danmaku are remembered for deduplication.
+const SEEN_CAPACITY: usize = 256;
+
+/// Wrapper that backfills recent danmaku history on a [`Packet`](crate::packet::Packet) stream.
+///
+/// Each time the room is entered, which happens on connect and after each reconnect, recent
+/// danmaku are fetched via [`room::get_history`](crate::room::get_history) and yielded as
+/// notification packets of the synthetic command `BILILIVE_DANMAKU_HISTORY`, whose `data` field is
+/// the original entry in the `gethistory` api response:
+///
+/// ```json
+/// {"cmd": "BILILIVE_DANMAKU_HISTORY", "data": {"text": "...", "uid": 0, "nickname": "...", ...}}
+/// ```
+///
+/// They decode into [`Danmaku`](crate::event::Danmaku) like live `DANMU_MSG` do, with
+/// [`is_history`](crate::event::Danmaku::is_history) set.
+///
+/// Danmaku that have already been delivered are skipped.
+pub struct BackfillStream<T, H, E> {
+    /// underlying bilibili stream
+    stream: T,
+    /// http client to fetch history with
+    http: Arc<H>,
+    room_id: u64,
+    /// pending history request
+    fetching: Option<HistoryFuture>,
+    /// history packets to be yielded
+    backlog: VecDeque<Packet>,
+    /// keys of recently delivered danmaku
+    seen: HashSet<String>,
+    seen_order: VecDeque<String>,
+    __marker: PhantomData<E>,
+}
+
+impl<T: Unpin, H, E> Unpin for BackfillStream<T, H, E> {}
+
+impl<T, H, E> BackfillStream<T, H, E> {
+    /// Add history backfill mechanism to the underlying bililive stream of the given room.
+    pub fn new(stream: T, http: H, room_id: u64) -> Self {
+        Self {
+            stream,
+            http: Arc::new(http),
+            room_id,
+            fetching: None,
+            backlog: VecDeque::new(),
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+            __marker: PhantomData,
+        }
+    }
+
+    /// Remember a delivered danmaku. Returns `false` if it has been delivered before.
+    fn remember(&mut self, danmaku: &Danmaku) -> bool {
+        let key = danmaku.dedup_key();
+        if self.seen.contains(&key) {
+            return false;
+        }
+        if self.seen_order.len() >= SEEN_CAPACITY {
+            if let Some(oldest) = self.seen_order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(key.clone());
+        self.seen_order.push_back(key);
+        true
+    }
+}
+
+impl<T, H, E> BackfillStream<T, H, E>
+where
+    H: Requester + 'static,
+{
+    fn fetch(&mut self) {
+        debug!("fetching danmaku history");
+        let http = self.http.clone();
+        let room_id = self.room_id;
+        self.fetching = Some(Box::pin(async move {
+            room::get_history_entries(&*http, room_id).await
+        }));
+    }
+}
+
+impl<T, H, E> Stream for BackfillStream<T, H, E>
+where
+    T: Stream<Item = Result<Packet, StreamError<E>>> + Unpin,
+    H: Requester + 'static,
+{
+    type Item = Result<Packet, StreamError<E>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(fetching) = &mut self.fetching {
+            if let Poll::Ready(history) = fetching.poll_unpin(cx) {
+                self.fetching = None;
+                match history {
+                    Ok(history) => {
+                        for (danmaku, entry) in history {
+                            if self.remember(&danmaku) {
+                                self.backlog.push_back(Danmaku::history_packet(&entry));
+                            }
+                        }
+                        debug!("{} danmaku backfilled", self.backlog.len());
+                    }
+                    Err(e) => warn!("unable to fetch danmaku history: {}", e),
+                }
+            }
+        }
+
+        if let Some(packet) = self.backlog.pop_front() {
+            return Poll::Ready(Some(Ok(packet)));
+        }
+
+        loop {
+            let item = futures::ready!(Pin::new(&mut self.stream).poll_next(cx));
+            if let Some(Ok(packet)) = &item {
+                if packet.op() == Operation::RoomEnterResponse {
+                    self.fetch();
+                    // make sure the request is polled
+                    cx.waker().wake_by_ref();
+                } else if let Ok(Some(danmaku)) = Danmaku::from_packet(packet) {
+                    if !self.remember(&danmaku) {
+                        debug!("duplicated danmaku, dropping");
+                        continue;
+                    }
+                }
+            }
+            return Poll::Ready(item);
+        }
+    }
+}
+
+impl<T, H, E> Sink<Packet> for BackfillStream<T, H, E>
+where
+    T: Sink<Packet, Error = StreamError<E>> + Unpin,
+{
+    type Error = StreamError<E>;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Packet) -> Result<(), Self::Error> {
+        Pin::new(&mut self.stream).start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
+}
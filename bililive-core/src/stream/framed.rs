@@ -0,0 +1,144 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::ready;
+use futures::{Sink, Stream};
+use log::{debug, warn};
+
+use crate::errors::{IncompleteResult, StreamError};
+use crate::packet::Packet;
+
+/// Size of each read from the underlying byte stream.
+const READ_CHUNK: usize = 8192;
+
+/// A stream/sink interface to underlying byte stream, e.g. a raw tcp connection. Encodes/decodes bilibili live packets.
+///
+/// Unlike websocket transports, packets are framed directly over the byte stream, so they are
+/// split by the packet length in their headers.
+pub struct FramedStream<T> {
+    /// underlying byte stream
+    stream: T,
+    /// received bytes not yet parsed
+    read_buf: Vec<u8>,
+    /// encoded bytes not yet written
+    write_buf: Vec<u8>,
+}
+
+impl<T> FramedStream<T> {
+    /// Convert a byte stream into a [`FramedStream`](FramedStream).
+    pub const fn new(stream: T) -> Self {
+        Self {
+            stream,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+        }
+    }
+
+    /// Consume the wrapper and return the underlying byte stream.
+    ///
+    /// Buffered bytes not yet parsed or written are dropped.
+    pub fn into_inner(self) -> T {
+        self.stream
+    }
+}
+
+impl<T: AsyncWrite + Unpin> FramedStream<T> {
+    /// Write all pending bytes into the underlying stream.
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write_buf.is_empty() {
+            let n = ready!(Pin::new(&mut self.stream).poll_write(cx, &self.write_buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_buf.drain(..n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Stream for FramedStream<T>
+where
+    T: AsyncRead + Unpin,
+{
+    type Item = Result<Packet, StreamError<io::Error>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            // try to parse a packet from buffered bytes
+            if !this.read_buf.is_empty() {
+                let parsed = match Packet::parse(&this.read_buf) {
+                    IncompleteResult::Ok((remaining, pack)) => {
+                        Some(Ok((this.read_buf.len() - remaining.len(), pack)))
+                    }
+                    IncompleteResult::Incomplete(needed) => {
+                        debug!("incomplete packet, {:?} needed", needed);
+                        None
+                    }
+                    IncompleteResult::Err(e) => Some(Err(e)),
+                };
+                match parsed {
+                    Some(Ok((consumed, pack))) => {
+                        this.read_buf.drain(..consumed);
+                        debug!("packet parsed, {} bytes remaining", this.read_buf.len());
+                        return Poll::Ready(Some(Ok(pack)));
+                    }
+                    Some(Err(e)) => {
+                        warn!("error occurred when parsing incoming packet");
+                        // the stream can't be resynchronized
+                        this.read_buf.clear();
+                        return Poll::Ready(Some(Err(e.into())));
+                    }
+                    None => {}
+                }
+            }
+
+            // poll the underlying byte stream for more data
+            let mut chunk = [0; READ_CHUNK];
+            match ready!(Pin::new(&mut this.stream).poll_read(cx, &mut chunk)) {
+                Ok(0) => {
+                    // underlying stream closing
+                    return Poll::Ready(None);
+                }
+                Ok(n) => this.read_buf.extend_from_slice(&chunk[..n]),
+                Err(e) => {
+                    // underlying io error, closing connection
+                    warn!("error occurred when receiving data: {:?}", e);
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
+
+impl<T> Sink<Packet> for FramedStream<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    type Error = StreamError<io::Error>;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_write_buf(cx).map_err(StreamError::IO)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Packet) -> Result<(), Self::Error> {
+        self.write_buf.extend(item.encode());
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.poll_write_buf(cx)).map_err(StreamError::IO)?;
+        Pin::new(&mut self.stream)
+            .poll_flush(cx)
+            .map_err(StreamError::IO)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.poll_write_buf(cx)).map_err(StreamError::IO)?;
+        Pin::new(&mut self.stream)
+            .poll_close(cx)
+            .map_err(StreamError::IO)
+    }
+}
@@ -0,0 +1,191 @@
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::{Sink, Stream};
+use log::debug;
+
+use crate::errors::StreamError;
+use crate::event::{GiftCombo, GiftEvent, MergingCombo};
+use crate::packet::Packet;
+
+use super::waker::wake_after;
+
+/// A combo that hasn't finished yet.
+struct PendingCombo {
+    combo: MergingCombo,
+    /// last time when a gift or progress of the combo is received
+    last_seen: Instant,
+}
+
+/// Wrapper that merges gift combos on a [`Packet`](crate::packet::Packet) stream.
+///
+/// A combo of gifts is pushed as dozens of `SEND_GIFT` and `COMBO_SEND` notifications. Once no
+/// more of them arrive for the quiet period, `GiftComboStream` yields a notification packet which
+/// decodes into [`GiftEvent::ComboFinished`](crate::event::GiftEvent::ComboFinished), carrying the
+/// merged [`GiftCombo`](crate::event::GiftCombo).
+///
+/// All packets of the underlying stream are passed through, so gifts can still be handled one by
+/// one. Pending combos are flushed when the underlying stream ends.
+///
+/// Finished combos are only yielded while the stream is polled. They are checked on a timer, so
+/// they're yielded in time even if the underlying stream stays pending, unless no runtime feature
+/// is enabled (see [`new`](Self::new)). A stream which reconnects on errors, like the ones made by
+/// `connect_with_retry`, rarely ends, so its pending combos are never flushed. Take them by [`flush_combos`](Self::flush_combos) before dropping
+/// the stream.
+pub struct GiftComboStream<T, E> {
+    /// underlying bilibili stream
+    stream: T,
+    quiet_period: Duration,
+    combos: Vec<PendingCombo>,
+    /// when the scheduled wake up happens
+    wake_at: Option<Instant>,
+    /// whether the underlying stream has ended
+    terminated: bool,
+    __marker: PhantomData<E>,
+}
+
+impl<T: Unpin, E> Unpin for GiftComboStream<T, E> {}
+
+impl<T, E> GiftComboStream<T, E> {
+    /// Add gift combo merging mechanism to the underlying bililive stream.
+    ///
+    /// A combo is finished if no more gifts of it arrive in `quiet_period`. Bilibili keeps combos
+    /// open for about 3 seconds after the last click.
+    ///
+    /// The timer checking for finished combos is spawned on the runtime enabled by the `tokio` or
+    /// `async-std` feature. Without either of them, e.g. in `actix-bililive`, no timer is armed and
+    /// a finished combo is only yielded along with the next packet of the underlying stream. On a
+    /// live connection, heartbeat responses arrive about every 30 seconds.
+    pub fn new(stream: T, quiet_period: Duration) -> Self {
+        Self {
+            stream,
+            quiet_period,
+            combos: Vec::new(),
+            wake_at: None,
+            terminated: false,
+            __marker: PhantomData,
+        }
+    }
+
+    /// Take all pending combos in the order they started, finishing them early.
+    ///
+    /// Named apart from [`SinkExt::flush`](futures::SinkExt::flush), which flushes sent packets.
+    pub fn flush_combos(&mut self) -> Vec<GiftCombo> {
+        self.combos
+            .drain(..)
+            .map(|pending| pending.combo.finish())
+            .collect()
+    }
+
+    /// Take the oldest pending combo as a packet.
+    fn pop_combo(&mut self) -> Option<Result<Packet, StreamError<E>>> {
+        if self.combos.is_empty() {
+            return None;
+        }
+        Some(Ok(self.combos.remove(0).combo.finish().to_packet()))
+    }
+
+    /// Merge a gift event into pending combos.
+    fn track(&mut self, event: &GiftEvent, now: Instant) {
+        let Some(combo) = MergingCombo::start(event) else {
+            return;
+        };
+        if let Some(pending) = self
+            .combos
+            .iter_mut()
+            .find(|pending| pending.combo.batch_combo_id() == combo.batch_combo_id())
+        {
+            pending.combo.merge(event);
+            pending.last_seen = now;
+        } else {
+            self.combos.push(PendingCombo {
+                combo,
+                last_seen: now,
+            });
+        }
+    }
+
+    /// Take a finished combo, or schedule a wake up when the next one finishes.
+    fn poll_finished(&mut self, cx: &Context<'_>, now: Instant) -> Option<GiftCombo> {
+        if let Some(idx) = self
+            .combos
+            .iter()
+            .position(|pending| now - pending.last_seen >= self.quiet_period)
+        {
+            return Some(self.combos.remove(idx).combo.finish());
+        }
+
+        let deadline = self
+            .combos
+            .iter()
+            .map(|pending| pending.last_seen + self.quiet_period)
+            .min()?;
+        // a wake up which has yet to happen will re-check pending combos anyway
+        if self.wake_at.is_none_or(|wake_at| wake_at <= now) {
+            wake_after(cx.waker(), deadline - now);
+            self.wake_at = Some(deadline);
+        }
+        None
+    }
+}
+
+impl<T, E> Stream for GiftComboStream<T, E>
+where
+    T: Stream<Item = Result<Packet, StreamError<E>>> + Unpin,
+{
+    type Item = Result<Packet, StreamError<E>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.terminated {
+            return Poll::Ready(self.pop_combo());
+        }
+
+        let now = Instant::now();
+        if let Some(combo) = self.poll_finished(cx, now) {
+            debug!("gift combo {} finished", combo.batch_combo_id);
+            return Poll::Ready(Some(Ok(combo.to_packet())));
+        }
+
+        match Pin::new(&mut self.stream).poll_next(cx) {
+            Poll::Ready(Some(Ok(packet))) => {
+                // malformed gifts are left to consumers
+                if let Ok(Some(event)) = GiftEvent::from_packet(&packet) {
+                    self.track(&event, now);
+                    // make sure the combo finishes even if no more packets arrive
+                    self.poll_finished(cx, now);
+                }
+                Poll::Ready(Some(Ok(packet)))
+            }
+            Poll::Ready(None) => {
+                self.terminated = true;
+                Poll::Ready(self.pop_combo())
+            }
+            poll => poll,
+        }
+    }
+}
+
+impl<T, E> Sink<Packet> for GiftComboStream<T, E>
+where
+    T: Sink<Packet, Error = StreamError<E>> + Unpin,
+{
+    type Error = StreamError<E>;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Packet) -> Result<(), Self::Error> {
+        Pin::new(&mut self.stream).start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
+}
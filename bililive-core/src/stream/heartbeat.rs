@@ -12,7 +12,7 @@ use log::debug;
 use crate::errors::StreamError;
 use crate::packet::{Operation, Packet, Protocol};
 
-use super::waker::WakerProxy;
+use super::waker::{wake_after, WakerProxy};
 
 /// Wrapper that implement heartbeat auto-response mechanism on a [`Packet`](crate::packet::Packet) stream.
 ///
@@ -72,7 +72,7 @@ where
         let now = Instant::now();
         let need_hb = self
             .last_hb
-            .map_or(true, |last_hb| now - last_hb >= Duration::from_secs(30));
+            .is_none_or(|last_hb| now - last_hb >= Duration::from_secs(30));
 
         if need_hb {
             // we need to send heartbeat, so push it into the sink
@@ -87,22 +87,7 @@ where
 
             // Schedule current task to be waken in case there's no incoming
             // websocket message in a long time.
-            #[cfg(feature = "tokio")]
-            {
-                let waker = cx.waker().clone();
-                tokio1::spawn(async {
-                    tokio1::time::sleep(Duration::from_secs(30)).await;
-                    waker.wake();
-                });
-            }
-            #[cfg(feature = "async-std")]
-            {
-                let waker = cx.waker().clone();
-                async_std1::task::spawn(async {
-                    async_std1::task::sleep(Duration::from_secs(30)).await;
-                    waker.wake();
-                });
-            }
+            wake_after(cx.waker(), Duration::from_secs(30));
 
             // ensure that heartbeat is sent
             ready!(self.with_context(|cx, s| Pin::new(s).poll_flush(cx)))?;
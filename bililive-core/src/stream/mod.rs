@@ -1,6 +1,16 @@
 //! Stream types.
 
+pub use app_heartbeat::AppHeartbeatStream;
+pub use backfill::BackfillStream;
+pub use framed::FramedStream;
+pub use gift_combo::GiftComboStream;
 pub use heartbeat::HeartbeatStream;
 
+mod app_heartbeat;
+mod backfill;
+mod framed;
+mod gift_combo;
 mod heartbeat;
+#[cfg(test)]
+mod tests;
 pub mod waker;
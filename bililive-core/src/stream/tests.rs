@@ -0,0 +1,396 @@
+use std::io;
+
+use futures::executor::block_on;
+use futures::io::Cursor;
+use futures::{stream, SinkExt, StreamExt};
+use serde_json::json;
+
+use crate::builder::{BoxFuture, Request, Requester, Response};
+use crate::errors::{BoxedError, StreamError};
+use crate::event::Danmaku;
+use crate::packet::{Operation, Packet, Protocol};
+
+use super::{BackfillStream, FramedStream};
+
+/// A requester that always replies with the danmaku history fixture.
+struct HistoryRequester;
+
+impl Requester for HistoryRequester {
+    fn request(&self, request: Request) -> BoxFuture<'_, Result<Response, BoxedError>> {
+        assert!(request.url().contains("gethistory"));
+        Box::pin(async {
+            Ok(Response::new(
+                200,
+                vec![],
+                include_bytes!("../../tests/getHistory.json").to_vec(),
+            ))
+        })
+    }
+}
+
+fn danmu_msg(text: &str, ct: &str) -> Packet {
+    let value = json!({
+        "cmd": "DANMU_MSG",
+        "info": [[0, 1, 25, 16777215, 1690027262123i64], text, [174102117, "vioIet・伊芙加登"], [], [20], [], 0, 0, null, {"ts": 1690027262, "ct": ct}]
+    });
+    Packet::new(
+        Operation::Notification,
+        Protocol::Json,
+        serde_json::to_vec(&value).unwrap(),
+    )
+}
+
+#[test]
+fn must_backfill_history() {
+    let room_enter = Packet::new(
+        Operation::RoomEnterResponse,
+        Protocol::Json,
+        serde_json::to_vec(&json!({"code": 0})).unwrap(),
+    );
+    let packets = vec![
+        room_enter.clone(),
+        // already delivered in history
+        danmu_msg("晚上好", "A1B2C3D4"),
+        danmu_msg("新消息", "C9D0E1F2"),
+    ];
+    let inner = stream::iter(packets.into_iter().map(Ok::<_, StreamError<io::Error>>));
+    let stream = BackfillStream::new(inner, HistoryRequester, 5440);
+
+    let packets: Vec<Packet> = block_on(stream.map(|packet| packet.unwrap()).collect());
+    assert_eq!(packets[0], room_enter);
+    let cmds: Vec<String> = packets[1..]
+        .iter()
+        .map(|packet| {
+            let value: serde_json::Value = packet.json().unwrap();
+            value["cmd"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(
+        cmds,
+        [
+            "BILILIVE_DANMAKU_HISTORY",
+            "BILILIVE_DANMAKU_HISTORY",
+            "DANMU_MSG"
+        ]
+    );
+    let texts: Vec<(String, bool)> = packets[1..]
+        .iter()
+        .map(|packet| {
+            let danmaku = Danmaku::from_packet(packet).unwrap().unwrap();
+            (danmaku.text, danmaku.is_history)
+        })
+        .collect();
+    assert_eq!(
+        texts,
+        [
+            ("来了".to_string(), true),
+            ("晚上好".to_string(), true),
+            ("新消息".to_string(), false)
+        ]
+    );
+}
+
+#[test]
+fn must_frame_packets() {
+    let first = Packet::new(
+        Operation::HeartBeatResponse,
+        Protocol::Heartbeat,
+        vec![0, 0, 0, 1],
+    );
+    let second = danmu_msg("hello", "A1B2C3D4");
+
+    let mut buf = first.encode();
+    buf.extend(second.encode());
+    // a truncated packet at the end of the stream
+    buf.extend(&first.encode()[..10]);
+
+    let stream = FramedStream::new(Cursor::new(buf));
+    let packets: Vec<_> = block_on(stream.collect());
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].as_ref().unwrap(), &first);
+    assert_eq!(packets[1].as_ref().unwrap(), &second);
+
+    let mut sink = FramedStream::new(Cursor::new(Vec::new()));
+    block_on(async {
+        sink.send(first.clone()).await.unwrap();
+        sink.send(second.clone()).await.unwrap();
+    });
+    let mut expected = first.encode();
+    expected.extend(second.encode());
+    assert_eq!(sink.into_inner().into_inner(), expected);
+}
+
+#[cfg(feature = "tokio")]
+mod app_heartbeat {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use futures::{stream, StreamExt};
+
+    use crate::builder::{BoxFuture, Request, Requester, Response};
+    use crate::errors::{BoxedError, StreamError};
+    use crate::open::{AppKey, AppSession, OpenAnchor};
+    use crate::packet::Packet;
+    use crate::stream::AppHeartbeatStream;
+
+    use super::danmu_msg;
+
+    /// A requester that accepts app heartbeats and records the sessions they are sent for.
+    #[derive(Clone, Default)]
+    struct AppHeartbeatRequester(Arc<Mutex<Vec<String>>>);
+
+    impl Requester for AppHeartbeatRequester {
+        fn request(&self, request: Request) -> BoxFuture<'_, Result<Response, BoxedError>> {
+            assert!(request.url().ends_with("/v2/app/heartbeat"));
+            self.0
+                .lock()
+                .unwrap()
+                .push(String::from_utf8(request.body().encode()).unwrap());
+            Box::pin(async {
+                Ok(Response::new(
+                    200,
+                    vec![],
+                    br#"{"code":0,"data":{}}"#.to_vec(),
+                ))
+            })
+        }
+    }
+
+    #[test]
+    fn must_send_app_heartbeat() {
+        let http = AppHeartbeatRequester::default();
+        let session = AppSession::new(
+            AppKey::new("key", "secret"),
+            1,
+            "game",
+            "{}",
+            vec![],
+            OpenAnchor {
+                room_id: 5440,
+                uid: 0,
+                open_id: String::new(),
+                uname: String::new(),
+                uface: String::new(),
+            },
+        );
+        let packets = vec![
+            danmu_msg("晚上好", "A1B2C3D4"),
+            danmu_msg("新消息", "C9D0E1F2"),
+        ];
+        let inner = stream::iter(
+            packets
+                .clone()
+                .into_iter()
+                .map(Ok::<_, StreamError<io::Error>>),
+        );
+        let stream = AppHeartbeatStream::new(inner, http.clone(), session);
+        assert_eq!(stream.session().game_id(), "game");
+
+        let runtime = tokio1::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let received: Vec<Packet> =
+            runtime.block_on(stream.map(|packet| packet.unwrap()).collect());
+        assert_eq!(received, packets);
+        // heartbeats are sent once per interval
+        assert_eq!(*http.0.lock().unwrap(), [r#"{"game_id":"game"}"#]);
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod gift_combo {
+    use std::io;
+    use std::time::Duration;
+
+    use futures::{stream, StreamExt};
+    use serde_json::json;
+
+    use crate::errors::StreamError;
+    use crate::event::{GiftCombo, GiftEvent};
+    use crate::packet::{Operation, Packet, Protocol};
+    use crate::stream::GiftComboStream;
+
+    use super::danmu_msg;
+
+    fn send_gift(batch_combo_id: &str, num: u32) -> Packet {
+        let value = json!({
+            "cmd": "SEND_GIFT",
+            "data": {
+                "action": "投喂", "batch_combo_id": batch_combo_id, "coin_type": "gold",
+                "giftId": 31036, "giftName": "小花花", "num": num, "price": 100,
+                "total_coin": 100 * num, "timestamp": 1690027262,
+                "uid": 174102117, "uname": "vioIet・伊芙加登"
+            }
+        });
+        Packet::new(
+            Operation::Notification,
+            Protocol::Json,
+            serde_json::to_vec(&value).unwrap(),
+        )
+    }
+
+    fn combo_send(batch_combo_id: &str, batch_combo_num: u32) -> Packet {
+        let value = json!({
+            "cmd": "COMBO_SEND",
+            "data": {
+                "action": "投喂", "batch_combo_id": batch_combo_id,
+                "batch_combo_num": batch_combo_num, "combo_total_coin": 100 * batch_combo_num,
+                "gift_id": 31036, "gift_name": "小花花",
+                "uid": 174102117, "uname": "vioIet・伊芙加登"
+            }
+        });
+        Packet::new(
+            Operation::Notification,
+            Protocol::Json,
+            serde_json::to_vec(&value).unwrap(),
+        )
+    }
+
+    fn finished(packet: &Packet) -> Option<GiftCombo> {
+        match GiftEvent::from_packet(packet).unwrap() {
+            Some(GiftEvent::ComboFinished(combo)) => Some(combo),
+            _ => None,
+        }
+    }
+
+    fn runtime() -> tokio1::runtime::Runtime {
+        tokio1::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn must_merge_gift_combo() {
+        let packets = vec![
+            send_gift("combo-a", 1),
+            send_gift("combo-b", 1),
+            send_gift("combo-a", 1),
+            danmu_msg("晚上好", "A1B2C3D4"),
+            // some gifts of the combo are missed
+            combo_send("combo-a", 5),
+        ];
+        let inner = stream::iter(
+            packets
+                .clone()
+                .into_iter()
+                .map(Ok::<_, StreamError<io::Error>>),
+        );
+        let stream = GiftComboStream::new(inner, Duration::from_secs(60));
+
+        let received: Vec<Packet> =
+            runtime().block_on(stream.map(|packet| packet.unwrap()).collect());
+        // all packets are passed through, and pending combos are flushed at the end
+        assert_eq!(received[..packets.len()], packets);
+        let combos: Vec<_> = received[packets.len()..]
+            .iter()
+            .map(|packet| finished(packet).expect("not a finished combo"))
+            .collect();
+        assert_eq!(
+            combos
+                .iter()
+                .map(|combo| (combo.batch_combo_id.as_str(), combo.num, combo.total_coin))
+                .collect::<Vec<_>>(),
+            [("combo-a", 5, 500), ("combo-b", 1, 100)]
+        );
+    }
+
+    #[test]
+    fn must_merge_combo_progress_before_gifts() {
+        let packets = vec![
+            // the progress overtakes the gifts it counts
+            combo_send("combo-a", 2),
+            send_gift("combo-a", 1),
+            send_gift("combo-a", 1),
+            combo_send("combo-b", 1),
+            send_gift("combo-b", 1),
+            send_gift("combo-b", 2),
+        ];
+        let inner = stream::iter(packets.into_iter().map(Ok::<_, StreamError<io::Error>>));
+        let stream = GiftComboStream::new(inner, Duration::from_secs(60));
+
+        let combos: Vec<GiftCombo> = runtime().block_on(
+            stream
+                .filter_map(|packet| async move { finished(&packet.unwrap()) })
+                .collect(),
+        );
+        assert_eq!(
+            combos
+                .iter()
+                .map(|combo| (combo.batch_combo_id.as_str(), combo.num, combo.total_coin))
+                .collect::<Vec<_>>(),
+            [("combo-a", 2, 200), ("combo-b", 3, 300)]
+        );
+    }
+
+    #[test]
+    fn must_finish_combo_after_quiet_period() {
+        let inner = stream::iter([send_gift("combo-a", 1), send_gift("combo-a", 2)])
+            .chain(stream::once(async {
+                tokio1::time::sleep(Duration::from_millis(200)).await;
+                danmu_msg("晚上好", "A1B2C3D4")
+            }))
+            .chain(stream::pending())
+            .map(Ok::<_, StreamError<io::Error>>)
+            .boxed();
+        let stream = GiftComboStream::new(inner, Duration::from_millis(50));
+
+        let received: Vec<Packet> =
+            runtime().block_on(stream.take(4).map(|packet| packet.unwrap()).collect());
+        let combo = finished(&received[2]).expect("combo is not finished in time");
+        assert_eq!((combo.num, combo.total_coin), (3, 300));
+        assert_eq!(received[3], danmu_msg("晚上好", "A1B2C3D4"));
+    }
+
+    #[test]
+    fn must_finish_combo_while_pending() {
+        let inner = stream::iter([send_gift("combo-a", 1)])
+            .chain(stream::pending())
+            .map(Ok::<_, StreamError<io::Error>>)
+            .boxed();
+        let stream = GiftComboStream::new(inner, Duration::from_millis(50));
+
+        // no more packets arrive after the gift
+        let received: Vec<Packet> = runtime()
+            .block_on(async {
+                let received = stream.take(2).map(|packet| packet.unwrap()).collect();
+                tokio1::time::timeout(Duration::from_secs(5), received).await
+            })
+            .expect("combo is not finished in time");
+        let combo = finished(&received[1]).expect("not a finished combo");
+        assert_eq!((combo.num, combo.total_coin), (1, 100));
+    }
+
+    #[test]
+    fn must_flush_pending_combos() {
+        let packets = vec![
+            send_gift("combo-a", 1),
+            send_gift("combo-b", 1),
+            combo_send("combo-a", 3),
+        ];
+        let inner = stream::iter(packets.clone())
+            .chain(stream::pending())
+            .map(Ok::<_, StreamError<io::Error>>)
+            .boxed();
+        let mut stream = GiftComboStream::new(inner, Duration::from_secs(60));
+
+        let received: Vec<Packet> = runtime().block_on(
+            stream
+                .by_ref()
+                .take(packets.len())
+                .map(|packet| packet.unwrap())
+                .collect(),
+        );
+        assert_eq!(received, packets);
+        assert_eq!(
+            stream
+                .flush_combos()
+                .iter()
+                .map(|combo| (combo.batch_combo_id.as_str(), combo.num))
+                .collect::<Vec<_>>(),
+            [("combo-a", 3), ("combo-b", 1)]
+        );
+        assert!(stream.flush_combos().is_empty());
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::sync::Arc;
 use std::task::{Wake, Waker};
+use std::time::Duration;
 
 use futures::task::AtomicWaker;
 
@@ -38,3 +39,24 @@ impl Wake for WakerProxy {
         self.tx_waker.wake();
     }
 }
+
+/// Schedule the task to be waken after given duration on the enabled runtime, in case there's no
+/// incoming message in a long time.
+pub(crate) fn wake_after(waker: &Waker, dur: Duration) {
+    #[cfg(feature = "tokio")]
+    {
+        let waker = waker.clone();
+        tokio1::spawn(async move {
+            tokio1::time::sleep(dur).await;
+            waker.wake();
+        });
+    }
+    #[cfg(feature = "async-std")]
+    {
+        let waker = waker.clone();
+        async_std1::task::spawn(async move {
+            async_std1::task::sleep(dur).await;
+            waker.wake();
+        });
+    }
+}
@@ -13,7 +13,7 @@ struct ExportType {
     avatar: String,
 }
 
-/**
+/*
 println!("Face: {}", face);
 println!("Name: {}", name);
 println!("Userhash: {}", userhash);
@@ -33,6 +33,7 @@ async fn run() {
             .await
             .unwrap()
             .build()
+            .unwrap()
     } else {
         ConfigBuilder::new()
             .by_uid(90873)
@@ -42,6 +43,7 @@ async fn run() {
             .await
             .unwrap()
             .build()
+            .unwrap()
     };
 
     info!("room_id: {}", config.room_id());
@@ -55,7 +57,7 @@ async fn run() {
         bililive::connect::tokio::connect_with_retry(config.clone(), RetryConfig::default())
             .await
             .unwrap();
-    #[cfg(feature = "async-std")]
+    #[cfg(all(feature = "async-std", not(feature = "tokio")))]
     let mut stream =
         bililive::connect::async_std::connect_with_retry(config, RetryConfig::default())
             .await
@@ -83,7 +85,7 @@ async fn run() {
                     if let Some(cmd) = json.get("cmd") {
                         if cmd == "DANMU_MSG" {
                             if let Some(info) = json.get("info").and_then(|v| v.as_array()) {
-                                if let Some(user_info) = info.get(0).and_then(|v| v.as_array()) {
+                                if let Some(user_info) = info.first().and_then(|v| v.as_array()) {
                                     if let Some(user) =
                                         user_info.get(15).and_then(|v| v.get("user"))
                                     {
@@ -101,8 +103,18 @@ async fn run() {
                                                 .and_then(|v| v.get("extra"))
                                                 .and_then(|v| v.as_str())
                                                 .and_then(|v| serde_json::from_str::<Value>(v).ok())
-                                                .and_then(|v| Some((v.get("user_hash")?.clone(), v.get("content")?.clone())))
-                                                .and_then(|(v1, v2)| Some((v1.as_str()?.to_string(), v2.as_str()?.to_string())))
+                                                .and_then(|v| {
+                                                    Some((
+                                                        v.get("user_hash")?.clone(),
+                                                        v.get("content")?.clone(),
+                                                    ))
+                                                })
+                                                .and_then(|(v1, v2)| {
+                                                    Some((
+                                                        v1.as_str()?.to_string(),
+                                                        v2.as_str()?.to_string(),
+                                                    ))
+                                                })
                                                 .unwrap_or((String::new(), String::new()));
 
                                             println!(
@@ -127,13 +139,14 @@ async fn run() {
                     }
                 }
             }
-            Err(e) => {
+            Err(_e) => {
                 // info!("err: {:?}", e);
             }
         }
     }
 }
 
+#[allow(clippy::needless_return)]
 fn main() {
     #[cfg(feature = "tokio")]
     {
@@ -143,6 +156,6 @@ fn main() {
             .unwrap();
         return runtime.block_on(run());
     }
-    #[cfg(feature = "async-std")]
+    #[cfg(all(feature = "async-std", not(feature = "tokio")))]
     return async_std::task::block_on(run());
 }
@@ -1,33 +1,109 @@
-use std::future::Future;
-use std::pin::Pin;
-
+use async_std::net::TcpStream;
 use http_client::h1::H1Client as Client;
+use http_client::http_types;
 use http_client::HttpClient;
-use serde::de::DeserializeOwned;
-
-use crate::core::builder::Requester;
 
-use super::BoxedError;
+use crate::core::builder::{BoxFuture, Method, ProxyRequester, Request, Requester, Response};
+use crate::core::config::USER_AGENT;
+use crate::core::errors::{BoxedError, BuildError};
+use crate::core::proxy::Proxy;
 
 #[derive(Debug, Default)]
-pub struct H1Client(Client);
+pub struct H1Client {
+    client: Client,
+    proxy: Option<Proxy>,
+}
+
+impl H1Client {
+    /// Construct a client sending requests through given proxy.
+    ///
+    /// # Errors
+    /// This never fails. The result is kept for parity with other http clients.
+    pub fn with_proxy(proxy: &Proxy) -> Result<Self, BuildError> {
+        Ok(Self {
+            client: Client::new(),
+            proxy: Some(proxy.clone()),
+        })
+    }
+}
+
+impl ProxyRequester for H1Client {
+    fn with_proxy(proxy: &Proxy) -> Result<Self, BuildError> {
+        Self::with_proxy(proxy)
+    }
+}
 
 impl From<Client> for H1Client {
     fn from(client: Client) -> Self {
-        Self(client)
+        Self {
+            client,
+            proxy: None,
+        }
     }
 }
 
+/// Send a request over a tunnel through the proxy.
+async fn send_through(
+    proxy: &Proxy,
+    req: http_types::Request,
+) -> Result<http_types::Response, BoxedError> {
+    let url = req.url();
+    let host = url.host_str().ok_or("missing host")?.to_string();
+    let port = url.port_or_known_default().ok_or("missing port")?;
+    let https = url.scheme() == "https";
+
+    let mut stream = TcpStream::connect(proxy.addr()).await?;
+    proxy.tunnel(&mut stream, &host, port).await?;
+    if https {
+        #[cfg(feature = "async-native-tls")]
+        {
+            let stream = async_native_tls::connect(host.as_str(), stream).await?;
+            return Ok(async_h1::connect(stream, req).await?);
+        }
+        #[cfg(not(feature = "async-native-tls"))]
+        return Err("https is not supported without tls".into());
+    }
+    Ok(async_h1::connect(stream, req).await?)
+}
+
 impl Requester for H1Client {
-    fn get_json<T: DeserializeOwned>(
-        &self,
-        url: &str,
-    ) -> Pin<Box<dyn Future<Output = Result<T, BoxedError>> + Send + '_>> {
-        let req = http_client::Request::get(url);
+    fn request(&self, request: Request) -> BoxFuture<'_, Result<Response, BoxedError>> {
         Box::pin(async move {
-            Ok(serde_json::from_slice(
-                &*self.0.send(req).await?.body_bytes().await?,
-            )?)
+            let url = request.full_url()?;
+            let method = match request.method() {
+                Method::Get => http_types::Method::Get,
+                Method::Post => http_types::Method::Post,
+            };
+            let mut req = http_types::Request::new(method, url);
+            for (name, value) in request.headers() {
+                req.append_header(name.as_str(), value.as_str());
+            }
+            if !request.has_header("User-Agent") {
+                req.insert_header("User-Agent", USER_AGENT);
+            }
+            if let Some(cookie) = request.cookie_header() {
+                req.insert_header("Cookie", cookie);
+            }
+            if let Some(content_type) = request.body().content_type() {
+                req.set_body(request.body().encode());
+                req.insert_header("Content-Type", content_type);
+            }
+
+            let mut resp = match &self.proxy {
+                Some(proxy) => send_through(proxy, req).await?,
+                None => self.client.send(req).await?,
+            };
+            let status = u16::from(resp.status());
+            let headers = resp
+                .iter()
+                .flat_map(|(name, values)| {
+                    values
+                        .iter()
+                        .map(move |value| (name.to_string(), value.to_string()))
+                })
+                .collect();
+            let body = resp.body_bytes().await?;
+            Ok(Response::new(status, headers, body))
         })
     }
 }
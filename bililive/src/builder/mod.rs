@@ -6,8 +6,14 @@
 //!
 //! [`by_uid`](ConfigBuilder::by_uid) fetches room id by given user id.
 //!
+//! [`by_room_id`](ConfigBuilder::by_room_id) resolves the real room id by given short or long room id.
+//!
+//! [`by_url`](ConfigBuilder::by_url) resolves the real room id by given live room url.
+//!
 //! [`fetch_conf`](ConfigBuilder::fetch_conf) fetches danmaku server token and list without any input parameter.
 //!
+//! [`new_with_proxy`](ConfigBuilder::new_with_proxy) sends api requests through a proxy, and sets it in the built config.
+//!
 //! # Example
 //!
 //! ```rust
@@ -23,7 +29,7 @@
 //!     .await?
 //!     .fetch_conf()
 //!     .await?
-//!     .build()
+//!     .build()?
 //! # )
 //! # };
 //! #
@@ -31,13 +37,27 @@
 //! ```
 
 #[cfg(feature = "h1-client")]
+#[cfg(not(feature = "reqwest"))]
 mod h1;
 #[cfg(feature = "reqwest")]
 mod reqwest;
 #[cfg(test)]
 pub(crate) mod tests;
 
-type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+/// The HTTP client used by [`ConfigBuilder`](ConfigBuilder).
+///
+/// It implements [`Requester`](bililive_core::builder::Requester), so it can also be passed to other
+/// http-based apis like [`room`](bililive_core::room).
+#[cfg(feature = "reqwest")]
+pub type HttpClient = reqwest::ReqwestClient;
+
+/// The HTTP client used by [`ConfigBuilder`](ConfigBuilder).
+///
+/// It implements [`Requester`](bililive_core::builder::Requester), so it can also be passed to other
+/// http-based apis like [`room`](bililive_core::room).
+#[cfg(feature = "h1-client")]
+#[cfg(not(feature = "reqwest"))]
+pub type HttpClient = h1::H1Client;
 
 /// `bililive` stream config builder.
 ///
@@ -49,10 +69,13 @@ type BoxedError = Box<dyn std::error::Error + Send + Sync>;
 ///
 /// [`by_uid`](ConfigBuilder::by_uid) fetches room id by given user id.
 ///
+/// [`by_room_id`](ConfigBuilder::by_room_id) resolves the real room id by given short or long room id.
+///
+/// [`by_url`](ConfigBuilder::by_url) resolves the real room id by given live room url.
+///
 /// [`fetch_conf`](ConfigBuilder::fetch_conf) fetches danmaku server token and list without any input parameter.
 #[cfg(feature = "reqwest")]
-pub type ConfigBuilder<R, U, T, S> =
-    bililive_core::builder::ConfigBuilder<reqwest::ReqwestClient, R, U, T, S>;
+pub type ConfigBuilder<R, U, T, S> = bililive_core::builder::ConfigBuilder<HttpClient, R, U, T, S>;
 
 /// `bililive` stream config builder.
 ///
@@ -64,8 +87,11 @@ pub type ConfigBuilder<R, U, T, S> =
 ///
 /// [`by_uid`](ConfigBuilder::by_uid) fetches room id by given user id.
 ///
+/// [`by_room_id`](ConfigBuilder::by_room_id) resolves the real room id by given short or long room id.
+///
+/// [`by_url`](ConfigBuilder::by_url) resolves the real room id by given live room url.
+///
 /// [`fetch_conf`](ConfigBuilder::fetch_conf) fetches danmaku server token and list without any input parameter.
 #[cfg(feature = "h1-client")]
 #[cfg(not(feature = "reqwest"))]
-pub type ConfigBuilder<R, U, T, S> =
-    bililive_core::builder::ConfigBuilder<h1::H1Client, R, U, T, S>;
+pub type ConfigBuilder<R, U, T, S> = bililive_core::builder::ConfigBuilder<HttpClient, R, U, T, S>;
@@ -1,84 +1,82 @@
-use std::collections::HashMap;
-use std::future::Future;
-use std::pin::Pin;
-use std::str::FromStr;
+use reqwest::header::{CONTENT_TYPE, COOKIE, USER_AGENT};
+use reqwest::Client;
 
-use reqwest::{Client, RequestBuilder};
-use serde::de::DeserializeOwned;
-use url::Url;
-
-use crate::core::builder::Requester;
-
-use super::BoxedError;
+use crate::core::builder::{BoxFuture, Method, ProxyRequester, Request, Requester, Response};
+use crate::core::config;
+use crate::core::errors::{BoxedError, BuildError};
+use crate::core::proxy::Proxy;
 
 #[derive(Debug, Default)]
 pub struct ReqwestClient(Client);
 
-impl From<Client> for ReqwestClient {
-    fn from(client: Client) -> Self {
-        Self(client)
+impl ReqwestClient {
+    /// Construct a client sending requests through given proxy.
+    ///
+    /// # Errors
+    /// Returns an error if reqwest rejects the proxy, or the TLS backend cannot be initialized.
+    pub fn with_proxy(proxy: &Proxy) -> Result<Self, BuildError> {
+        let mut proxy_conf =
+            reqwest::Proxy::all(proxy.url()).map_err(|e| BuildError::InvalidProxy {
+                url: proxy.url(),
+                reason: e.to_string(),
+            })?;
+        if let Some((username, password)) = proxy.credential() {
+            proxy_conf = proxy_conf.basic_auth(username, password);
+        }
+        let client = Client::builder()
+            .proxy(proxy_conf)
+            .build()
+            .map_err(|e| BuildError::Http(e.into()))?;
+        Ok(Self(client))
     }
 }
 
-fn hashmap_to_cookies_str(cookies: HashMap<String, String>) -> String {
-    cookies
-        .iter()
-        .map(|(key, value)| format!("{key}={value}"))
-        .collect::<Vec<_>>()
-        .join("; ")
+impl ProxyRequester for ReqwestClient {
+    fn with_proxy(proxy: &Proxy) -> Result<Self, BuildError> {
+        Self::with_proxy(proxy)
+    }
 }
 
-impl Requester for ReqwestClient {
-    fn get_json<T: DeserializeOwned>(
-        &self,
-        url: &str,
-    ) -> Pin<Box<dyn Future<Output = Result<T, BoxedError>> + Send + '_>> {
-        let url = Url::from_str(url).unwrap();
-        Box::pin(async move {
-            Ok(serde_json::from_slice(
-                &*self.0.get(url).send().await?.bytes().await?,
-            )?)
-        })
+impl From<Client> for ReqwestClient {
+    fn from(client: Client) -> Self {
+        Self(client)
     }
+}
 
-    fn get_json_with_parameters<T: DeserializeOwned>(
-        &self,
-        url: &str,
-        parameters: HashMap<String, String>,
-        cookies: HashMap<String, String>,
-    ) -> Pin<Box<dyn Future<Output = Result<T, BoxedError>> + Send + '_>> {
-        let url = Url::from_str(url).unwrap();
+impl Requester for ReqwestClient {
+    fn request(&self, request: Request) -> BoxFuture<'_, Result<Response, BoxedError>> {
         Box::pin(async move {
-            Ok(serde_json::from_slice(
-                &*self
-                    .0
-                    .get(url)
-                    .query(&parameters)
-                    .header("Cookie", hashmap_to_cookies_str(cookies))
-                    .send()
-                    .await?
-                    .bytes()
-                    .await?,
-            )?)
-        })
-    }
+            let url = request.full_url()?;
+            let mut builder = match request.method() {
+                Method::Get => self.0.get(url),
+                Method::Post => self.0.post(url),
+            };
+            for (name, value) in request.headers() {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+            if !request.has_header(USER_AGENT.as_str()) {
+                builder = builder.header(USER_AGENT, config::USER_AGENT);
+            }
+            if let Some(cookie) = request.cookie_header() {
+                builder = builder.header(COOKIE, cookie);
+            }
+            if let Some(content_type) = request.body().content_type() {
+                builder = builder
+                    .header(CONTENT_TYPE, content_type)
+                    .body(request.body().encode());
+            }
 
-    fn get_cookie(
-        &self,
-        url: String,
-        cookie_name: String,
-    ) -> Pin<Box<dyn Future<Output = Result<String, BoxedError>> + Send + '_>> {
-        let url = Url::from_str(url.as_str()).unwrap();
-        Box::pin(async move {
-            Ok(self
-                .0
-                .get(url.as_str())
-                .send()
-                .await?
-                .cookies()
-                .into_iter()
-                .find(|cookie| cookie.name() == cookie_name)
-                .map_or("".to_string(), |x| x.value().to_string()))
+            let resp = builder.send().await?;
+            let status = resp.status().as_u16();
+            let headers = resp
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect();
+            let body = resp.bytes().await?.to_vec();
+            Ok(Response::new(status, headers, body))
         })
     }
 }
@@ -1,6 +1,13 @@
-use bililive_core::config::StreamConfig;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
 
-use super::ConfigBuilder;
+use bililive_core::builder::{Request, Requester};
+use bililive_core::config::{StreamConfig, USER_AGENT};
+use bililive_core::proxy::{Proxy, ProxyKind};
+
+use super::{ConfigBuilder, HttpClient};
 
 pub(crate) async fn build_real_config(override_servers: bool) -> StreamConfig {
     let builder = ConfigBuilder::new()
@@ -15,7 +22,325 @@ pub(crate) async fn build_real_config(override_servers: bool) -> StreamConfig {
     } else {
         builder
     };
-    builder.build()
+    builder.build().expect("invalid config")
+}
+
+/// A local HTTP stand-in that replies one request with `response` and reports the raw request it received.
+pub(crate) fn serve(response: &'static str) -> (String, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("unable to bind");
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("unable to accept");
+        let mut reader = BufReader::new(stream);
+
+        let mut head = String::new();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            head.push_str(&line);
+            if line == "\r\n" {
+                break;
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        let mut stream = reader.into_inner();
+        stream.write_all(response.as_bytes()).unwrap();
+        stream.flush().unwrap();
+        tx.send(head + &String::from_utf8(body).unwrap()).unwrap();
+    });
+    (format!("http://{}", addr), rx)
+}
+
+/// A local proxy stand-in which requires `user:pass` authentication and reports the target of each
+/// connection it proxies.
+///
+/// The http proxy accepts both `CONNECT` requests and plain requests in absolute form.
+pub(crate) fn serve_proxy(kind: ProxyKind) -> (Proxy, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("unable to bind");
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for client in listener.incoming() {
+            let client = client.unwrap();
+            let tx = tx.clone();
+            thread::spawn(move || match kind {
+                ProxyKind::Http => proxy_http(client, &tx),
+                ProxyKind::Socks5 => proxy_socks5(client, &tx),
+            });
+        }
+    });
+    let proxy = match kind {
+        ProxyKind::Http => Proxy::http("127.0.0.1", port),
+        ProxyKind::Socks5 => Proxy::socks5("127.0.0.1", port),
+    };
+    (proxy.auth("user", "pass"), rx)
+}
+
+fn read_exact(stream: &mut TcpStream, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; len];
+    stream.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn proxy_http(mut client: TcpStream, tx: &mpsc::Sender<String>) {
+    let mut head = vec![];
+    while !head.ends_with(b"\r\n\r\n") {
+        match read_exact(&mut client, 1) {
+            Ok(byte) => head.extend(byte),
+            Err(_) => return,
+        }
+    }
+    let head = String::from_utf8(head).unwrap();
+    let (line, headers) = head.split_once("\r\n").unwrap();
+    let (auth, headers): (Vec<_>, Vec<_>) = headers.split_inclusive("\r\n").partition(|header| {
+        header
+            .to_ascii_lowercase()
+            .starts_with("proxy-authorization:")
+    });
+    // `dXNlcjpwYXNz` is `user:pass` in base64
+    if !auth
+        .iter()
+        .any(|header| header.trim_end().ends_with(": Basic dXNlcjpwYXNz"))
+    {
+        let _ = client
+            .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\nContent-Length: 0\r\n\r\n");
+        return;
+    }
+
+    let mut parts = line.splitn(3, ' ');
+    let (method, target, version) = (
+        parts.next().unwrap(),
+        parts.next().unwrap(),
+        parts.next().unwrap(),
+    );
+    if method == "CONNECT" {
+        tx.send(target.to_string()).unwrap();
+        match TcpStream::connect(target) {
+            Ok(upstream) => {
+                client
+                    .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                    .unwrap();
+                relay(client, upstream);
+            }
+            Err(_) => {
+                let _ = client.write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\n\r\n");
+            }
+        }
+    } else {
+        let target = target.strip_prefix("http://").unwrap();
+        let (authority, path) = target.split_at(target.find('/').unwrap_or(target.len()));
+        tx.send(authority.to_string()).unwrap();
+        let mut upstream = TcpStream::connect(authority).unwrap();
+        let path = if path.is_empty() { "/" } else { path };
+        write!(
+            upstream,
+            "{} {} {}\r\n{}",
+            method,
+            path,
+            version,
+            headers.concat()
+        )
+        .unwrap();
+        relay(client, upstream);
+    }
+}
+
+fn proxy_socks5(mut client: TcpStream, tx: &mpsc::Sender<String>) {
+    let Ok(greeting) = read_exact(&mut client, 2) else {
+        return;
+    };
+    let methods = read_exact(&mut client, greeting[1] as usize).unwrap();
+    if !methods.contains(&0x02) {
+        let _ = client.write_all(&[0x05, 0xff]);
+        return;
+    }
+    client.write_all(&[0x05, 0x02]).unwrap();
+
+    let username_len = read_exact(&mut client, 2).unwrap()[1] as usize;
+    let username = read_exact(&mut client, username_len).unwrap();
+    let password_len = read_exact(&mut client, 1).unwrap()[0] as usize;
+    let password = read_exact(&mut client, password_len).unwrap();
+    if username != b"user" || password != b"pass" {
+        let _ = client.write_all(&[0x01, 0x01]);
+        return;
+    }
+    client.write_all(&[0x01, 0x00]).unwrap();
+
+    let host = match read_exact(&mut client, 4).unwrap()[3] {
+        0x01 => read_exact(&mut client, 4)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("."),
+        0x03 => {
+            let len = read_exact(&mut client, 1).unwrap()[0] as usize;
+            String::from_utf8(read_exact(&mut client, len).unwrap()).unwrap()
+        }
+        atyp => panic!("unsupported address type {}", atyp),
+    };
+    let port = read_exact(&mut client, 2).unwrap();
+    let target = format!("{}:{}", host, u16::from_be_bytes([port[0], port[1]]));
+
+    tx.send(target.clone()).unwrap();
+    match TcpStream::connect(target) {
+        Ok(upstream) => {
+            client
+                .write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+                .unwrap();
+            relay(client, upstream);
+        }
+        Err(_) => {
+            let _ = client.write_all(&[0x05, 0x05, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+        }
+    }
+}
+
+/// Relay data between two connections until both sides are closed.
+fn relay(client: TcpStream, upstream: TcpStream) {
+    let (mut client_read, mut upstream_write) =
+        (client.try_clone().unwrap(), upstream.try_clone().unwrap());
+    thread::spawn(move || {
+        let _ = io::copy(&mut client_read, &mut upstream_write);
+        let _ = upstream_write.shutdown(Shutdown::Write);
+    });
+    let (mut upstream_read, mut client_write) = (upstream, client);
+    let _ = io::copy(&mut upstream_read, &mut client_write);
+    let _ = client_write.shutdown(Shutdown::Write);
+}
+
+async fn request_with_client() {
+    let (url, rx) = serve(concat!(
+        "HTTP/1.1 200 OK\r\n",
+        "Content-Type: application/json\r\n",
+        "Set-Cookie: buvid3=ABCD-1234infoc; path=/; domain=.bilibili.com\r\n",
+        "Set-Cookie: b_nut=1690027262; path=/\r\n",
+        "Content-Length: 11\r\n",
+        "Connection: close\r\n",
+        "\r\n",
+        r#"{"code":0}"#,
+        "\n"
+    ));
+    let resp = HttpClient::default()
+        .request(
+            Request::post(&format!("{}/msg/send", url))
+                .query("id", 5440)
+                .query("type", 0)
+                .header("X-Test", "bililive")
+                .cookie("SESSDATA", "sess")
+                .cookie("bili_jct", "csrf")
+                .form([("msg", "hello world")]),
+        )
+        .await
+        .expect("unable to make request");
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.cookie("buvid3"), Some("ABCD-1234infoc"));
+    assert_eq!(resp.cookie("b_nut"), Some("1690027262"));
+    assert_eq!(
+        resp.json::<serde_json::Value>().unwrap(),
+        serde_json::json!({"code": 0})
+    );
+
+    let req = rx.recv().unwrap();
+    let lower = req.to_ascii_lowercase();
+    assert!(req.starts_with("POST /msg/send?id=5440&type=0 HTTP/1.1\r\n"));
+    assert!(lower.contains("x-test: bililive\r\n"));
+    assert!(req.contains(&format!("{}\r\n", USER_AGENT)));
+    assert!(lower.contains("cookie: sessdata=sess; bili_jct=csrf\r\n"));
+    assert!(lower.contains("content-type: application/x-www-form-urlencoded\r\n"));
+    assert!(req.ends_with("\r\n\r\nmsg=hello+world"));
+}
+
+async fn request_with_error_status() {
+    let (url, rx) = serve(concat!(
+        "HTTP/1.1 412 Precondition Failed\r\n",
+        "Content-Length: 0\r\n",
+        "Connection: close\r\n",
+        "\r\n"
+    ));
+    let resp = HttpClient::default()
+        .request(Request::get(&url).header("User-Agent", "bililive"))
+        .await
+        .expect("unable to make request");
+    assert_eq!(resp.status(), 412);
+    assert!(!resp.is_success());
+    assert!(resp.body().is_empty());
+    let req = rx.recv().unwrap().to_ascii_lowercase();
+    assert!(req.starts_with("get / http/1.1\r\n"));
+    assert_eq!(req.matches("user-agent:").count(), 1);
+    assert!(req.contains("user-agent: bililive\r\n"));
+}
+
+async fn request_unreachable() {
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let client = HttpClient::default();
+    assert!(client
+        .request(Request::get(&format!("http://{}", addr)))
+        .await
+        .is_err());
+    assert!(client.request(Request::get("not a url")).await.is_err());
+}
+
+async fn request_through_proxy(kind: ProxyKind) {
+    let (url, rx) = serve(concat!(
+        "HTTP/1.1 200 OK\r\n",
+        "Content-Length: 10\r\n",
+        "Connection: close\r\n",
+        "\r\n",
+        r#"{"code":0}"#
+    ));
+    let (proxy, targets) = serve_proxy(kind);
+    let resp = HttpClient::with_proxy(&proxy)
+        .expect("unable to build client")
+        .request(Request::get(&format!("{}/room", url)))
+        .await
+        .expect("unable to make request");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.body(), br#"{"code":0}"#);
+    assert!(rx.recv().unwrap().starts_with("GET /room HTTP/1.1\r\n"));
+    assert_eq!(targets.recv().unwrap(), url.trim_start_matches("http://"));
+
+    let wrong = Proxy::parse(&proxy.url()).unwrap().auth("user", "wrong");
+    let result = HttpClient::with_proxy(&wrong)
+        .expect("unable to build client")
+        .request(Request::get(&url))
+        .await;
+    // plain http requests may be forwarded without tunnelling, and get the status of the proxy
+    assert!(result.map_or(true, |resp| resp.status() == 407));
+}
+
+async fn build_through_proxy() {
+    let (proxy, targets) = serve_proxy(ProxyKind::Http);
+    // the api may be unreachable from the stand-in, but the request must go through it
+    let _ = ConfigBuilder::new_with_proxy(proxy.clone())
+        .expect("unable to build client")
+        .by_room_id(6)
+        .await;
+    assert_eq!(targets.recv().unwrap(), "api.live.bilibili.com:443");
+
+    let config = ConfigBuilder::new_with_proxy(proxy.clone())
+        .expect("unable to build client")
+        .room_id(5440)
+        .uid(0)
+        .token("")
+        .buvid("")
+        .servers(&["wss://broadcastlv.chat.bilibili.com/sub".to_string()])
+        .build()
+        .expect("invalid config");
+    assert_eq!(config.proxy(), Some(&proxy));
 }
 
 #[cfg(feature = "tokio")]
@@ -29,3 +354,35 @@ async fn must_build_real_config_tokio() {
 async fn must_build_real_config_async_std() {
     build_real_config(false).await;
 }
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn must_request_tokio() {
+    request_with_client().await;
+    request_with_error_status().await;
+    request_unreachable().await;
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn must_request_through_proxy_tokio() {
+    request_through_proxy(ProxyKind::Http).await;
+    request_through_proxy(ProxyKind::Socks5).await;
+    build_through_proxy().await;
+}
+
+#[cfg(feature = "async-std")]
+#[async_std::test]
+async fn must_request_async_std() {
+    request_with_client().await;
+    request_with_error_status().await;
+    request_unreachable().await;
+}
+
+#[cfg(feature = "async-std")]
+#[async_std::test]
+async fn must_request_through_proxy_async_std() {
+    request_through_proxy(ProxyKind::Http).await;
+    request_through_proxy(ProxyKind::Socks5).await;
+    build_through_proxy().await;
+}
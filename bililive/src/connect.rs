@@ -1,72 +1,346 @@
 //! Connection related functions and types.
 macro_rules! impl_connect_mod {
     ($adapter:ident) => {
+        use std::fmt::{Debug, Formatter, Result as FmtResult};
         use std::future::Future;
-        use std::pin::Pin;
-        use std::str::FromStr;
+        use std::io;
+        use std::sync::Arc;
 
-        use async_tungstenite::tungstenite::error::Error as WsError;
-        use async_tungstenite::$adapter::{connect_async, ConnectStream};
+        use async_tungstenite::tungstenite::client::IntoClientRequest;
+        use async_tungstenite::tungstenite::error::{Error as WsError, UrlError};
+        use async_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+        use async_tungstenite::$adapter::ClientStream;
         use async_tungstenite::WebSocketStream;
         use stream_reconnect::{ReconnectStream, UnderlyingStream};
-        use url::Url;
 
+        use crate::builder::HttpClient;
+        use crate::core::builder::BoxFuture;
         use crate::core::config::StreamConfig;
         use crate::core::errors::StreamError;
+        use crate::core::open::AppSession;
         use crate::core::packet::Packet;
+        use crate::core::proxy::Proxy;
         use crate::core::retry::{RetryConfig, RetryContext, WsStream, WsStreamTrait};
-        use crate::core::stream::HeartbeatStream;
-        use crate::stream::CodecStream;
+        use crate::core::stream::{
+            AppHeartbeatStream, BackfillStream, FramedStream, HeartbeatStream,
+        };
+        use crate::stream::{CodecStream, TransportStream};
 
+        /// A duplex byte stream which connections are established over.
+        pub trait Socket: AsyncRead + AsyncWrite + Unpin + Send {}
+
+        impl<T: AsyncRead + AsyncWrite + Unpin + Send> Socket for T {}
+
+        /// A boxed [`Socket`](Socket).
+        pub type BoxedSocket = Box<dyn Socket>;
         /// Raw websocket stream type.
-        pub type InnerStream = WebSocketStream<ConnectStream>;
+        pub type InnerStream = WebSocketStream<ClientStream<BoxedSocket>>;
         /// Bililive stream type.
-        pub type DefaultStream = HeartbeatStream<CodecStream<InnerStream>, WsError>;
+        ///
+        /// It's either over websocket or raw tcp, depending on the transport of the connected server.
+        pub type DefaultStream =
+            HeartbeatStream<TransportStream<InnerStream, TcpInnerStream>, WsError>;
         /// Bililive stream type with auto-reconnect mechanism.
         pub type RetryStream = ReconnectStream<
             WsStream<Connector, WsError>,
-            RetryContext,
+            RetryContext<Connector>,
             Result<Packet, StreamError<WsError>>,
             StreamError<WsError>,
         >;
 
-        #[doc(hidden)]
-        pub struct Connector;
+        /// Bililive stream type with danmaku history backfill.
+        pub type DefaultBackfillStream = BackfillStream<DefaultStream, HttpClient, WsError>;
+        /// Bililive stream type with auto-reconnect mechanism and danmaku history backfill.
+        pub type RetryBackfillStream = BackfillStream<RetryStream, HttpClient, WsError>;
+
+        /// Bililive stream type with open live platform app heartbeats.
+        pub type DefaultAppStream = AppHeartbeatStream<DefaultStream, HttpClient, WsError>;
+        /// Bililive stream type with auto-reconnect mechanism and open live platform app heartbeats.
+        pub type RetryAppStream = AppHeartbeatStream<RetryStream, HttpClient, WsError>;
+
+        type Dialer =
+            Arc<dyn Fn(&str, u16) -> BoxFuture<'static, io::Result<BoxedSocket>> + Send + Sync>;
+
+        /// Connector to bilibili danmaku servers.
+        ///
+        /// The default connector opens tcp sockets to servers and applies TLS from the enabled crate
+        /// feature. Use [`ConnectorBuilder`](ConnectorBuilder) to customize them.
+        #[derive(Clone, Default)]
+        pub struct Connector {
+            tls: Option<Arc<TlsConnector>>,
+            dialer: Option<Dialer>,
+        }
+
+        impl Debug for Connector {
+            fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+                f.debug_struct("Connector")
+                    .field("tls", &self.tls.is_some())
+                    .field("dialer", &self.dialer.is_some())
+                    .finish()
+            }
+        }
+
+        impl Connector {
+            /// Create a builder of connectors.
+            #[must_use]
+            pub fn builder() -> ConnectorBuilder {
+                ConnectorBuilder::new()
+            }
+
+            /// Connect to bilibili live room with this connector.
+            ///
+            /// See [`connect`](connect) for details.
+            ///
+            /// # Errors
+            /// Returns an error when connections to all servers fail.
+            pub async fn connect(
+                &self,
+                config: StreamConfig,
+            ) -> Result<DefaultStream, StreamError<WsError>> {
+                WsStream::<Self, WsError>::establish(RetryContext::new(config, self.clone())).await
+            }
+
+            /// Connect to bilibili live room with auto retry, using this connector for every
+            /// (re)connection.
+            ///
+            /// # Errors
+            /// Returns an error when websocket connection fails.
+            pub async fn connect_with_retry(
+                &self,
+                stream_config: StreamConfig,
+                retry_config: RetryConfig,
+            ) -> Result<RetryStream, StreamError<WsError>> {
+                let inner: RetryStream = ReconnectStream::connect_with_options(
+                    RetryContext::new(stream_config, self.clone()),
+                    retry_config.into(),
+                )
+                .await?;
+                Ok(inner)
+            }
+
+            /// Open a stream to `host:port`, through the proxy if there's one.
+            async fn open_socket(
+                &self,
+                host: &str,
+                port: u16,
+                proxy: Option<&Proxy>,
+            ) -> io::Result<BoxedSocket> {
+                match proxy {
+                    Some(proxy) => {
+                        let socket = self.dial(proxy.host(), proxy.port()).await?;
+                        tunnel(proxy, socket, host, port).await
+                    }
+                    None => self.dial(host, port).await,
+                }
+            }
+
+            async fn dial(&self, host: &str, port: u16) -> io::Result<BoxedSocket> {
+                match &self.dialer {
+                    Some(dialer) => dialer(host, port).await,
+                    None => dial_tcp(host, port).await,
+                }
+            }
+        }
 
         impl WsStreamTrait<WsError> for Connector {
             type Stream = DefaultStream;
-            fn connect(
-                url: &str,
-            ) -> Pin<Box<dyn Future<Output = Result<Self::Stream, WsError>> + Send + '_>> {
-                let url = Url::from_str(url).unwrap();
+            fn connect<'a>(
+                &'a self,
+                url: &'a str,
+                config: &'a StreamConfig,
+            ) -> BoxFuture<'a, Result<Self::Stream, WsError>> {
                 Box::pin(async move {
-                    Ok(HeartbeatStream::new(CodecStream::new(
-                        connect_async(url).await?.0,
-                    )))
+                    let stream = if let Some(addr) = url.strip_prefix("tcp://") {
+                        let (host, port) = addr
+                            .rsplit_once(':')
+                            .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+                            .ok_or(WsError::Url(UrlError::UnableToConnect(url.to_string())))?;
+                        let socket = self.open_socket(host, port, config.proxy()).await?;
+                        TransportStream::Tcp(FramedStream::new(tcp_inner_stream(socket)))
+                    } else {
+                        let mut request = url.into_client_request()?;
+                        for (name, value) in config.headers() {
+                            request.headers_mut().insert(
+                                HeaderName::from_bytes(name.as_bytes())?,
+                                HeaderValue::from_str(value)?,
+                            );
+                        }
+                        let uri = request.uri();
+                        let host = uri
+                            .host()
+                            .ok_or(WsError::Url(UrlError::NoHostName))?
+                            .to_string();
+                        let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+                            Some("wss") => 443,
+                            _ => 80,
+                        });
+                        let socket = self.open_socket(&host, port, config.proxy()).await?;
+                        let stream = ws_handshake(request, socket, self.tls.as_deref()).await?;
+                        TransportStream::Ws(CodecStream::new(stream))
+                    };
+                    Ok(HeartbeatStream::new(stream))
                 })
             }
         }
 
+        /// Builder of [`Connector`](Connector).
+        #[derive(Debug, Default)]
+        pub struct ConnectorBuilder {
+            connector: Connector,
+        }
+
+        impl ConnectorBuilder {
+            /// Create a builder with the default tls configuration and tcp dialer.
+            #[must_use]
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            /// Set the TLS connector used for secure websocket connections, e.g. one with pinned
+            /// certificates or a custom client config.
+            #[must_use]
+            pub fn tls(mut self, tls: TlsConnector) -> Self {
+                self.connector.tls = Some(Arc::new(tls));
+                self
+            }
+
+            /// Set the function used to open a duplex stream to `host:port`.
+            ///
+            /// It replaces the default tcp dialer, which allows connecting over pre-established or
+            /// in-memory streams. When a proxy is set in the stream config, the dialer is asked for a
+            /// stream to the proxy, and the connection is tunnelled over it.
+            #[must_use]
+            pub fn dialer<F, Fut, S>(mut self, dialer: F) -> Self
+            where
+                F: Fn(&str, u16) -> Fut + Send + Sync + 'static,
+                Fut: Future<Output = io::Result<S>> + Send + 'static,
+                S: Socket + 'static,
+            {
+                self.connector.dialer = Some(Arc::new(move |host, port| {
+                    let fut = dialer(host, port);
+                    Box::pin(async move { Ok(Box::new(fut.await?) as BoxedSocket) })
+                }));
+                self
+            }
+
+            /// Build the connector.
+            #[must_use]
+            pub fn build(self) -> Connector {
+                self.connector
+            }
+        }
+
         /// Connect to bilibili live room.
         ///
+        /// Servers are tried in the preference order of transports, see
+        /// [`StreamConfig::urls`](crate::core::config::StreamConfig::urls). Websocket handshakes
+        /// carry [`StreamConfig::headers`](crate::core::config::StreamConfig::headers), and
+        /// connections go through [`StreamConfig::proxy`](crate::core::config::StreamConfig::proxy)
+        /// if there's one.
+        ///
+        /// This uses the default [`Connector`](Connector).
+        ///
         /// # Errors
-        /// Returns an error when websocket connection fails.
+        /// Returns an error when connections to all servers fail.
         pub async fn connect(config: StreamConfig) -> Result<DefaultStream, StreamError<WsError>> {
-            WsStream::<Connector, WsError>::establish(config.into()).await
+            Connector::default().connect(config).await
         }
 
         /// Connect to bilibili live room with auto retry.
         ///
+        /// This uses the default [`Connector`](Connector).
+        ///
         /// # Errors
         /// Returns an error when websocket connection fails.
         pub async fn connect_with_retry(
             stream_config: StreamConfig,
             retry_config: RetryConfig,
         ) -> Result<RetryStream, StreamError<WsError>> {
-            let inner: RetryStream =
-                ReconnectStream::connect_with_options(stream_config.into(), retry_config.into())
-                    .await?;
-            Ok(inner)
+            Connector::default()
+                .connect_with_retry(stream_config, retry_config)
+                .await
+        }
+
+        /// Connect to bilibili live room, and backfill recent danmaku history after entering the room.
+        ///
+        /// See [`BackfillStream`](crate::core::stream::BackfillStream) for details.
+        ///
+        /// # Errors
+        /// Returns an error when websocket connection fails.
+        pub async fn connect_with_backfill(
+            config: StreamConfig,
+            http: HttpClient,
+        ) -> Result<DefaultBackfillStream, StreamError<WsError>> {
+            let room_id = config.room_id();
+            Ok(BackfillStream::new(connect(config).await?, http, room_id))
+        }
+
+        /// Connect to bilibili live room with auto retry, and backfill recent danmaku history after
+        /// entering the room, which happens on connect and after each reconnect.
+        ///
+        /// See [`BackfillStream`](crate::core::stream::BackfillStream) for details.
+        ///
+        /// # Errors
+        /// Returns an error when websocket connection fails.
+        pub async fn connect_with_retry_and_backfill(
+            stream_config: StreamConfig,
+            retry_config: RetryConfig,
+            http: HttpClient,
+        ) -> Result<RetryBackfillStream, StreamError<WsError>> {
+            let room_id = stream_config.room_id();
+            Ok(BackfillStream::new(
+                connect_with_retry(stream_config, retry_config).await?,
+                http,
+                room_id,
+            ))
+        }
+
+        /// Connect to bilibili live room as an open live platform app, and keep the app session alive.
+        ///
+        /// The session is taken from [`StreamConfig::app_session`](crate::core::config::StreamConfig::app_session).
+        /// See [`AppHeartbeatStream`](crate::core::stream::AppHeartbeatStream) for details.
+        ///
+        /// # Errors
+        /// Returns an error when the config carries no app session, or websocket connection fails.
+        pub async fn connect_with_app_heartbeat(
+            config: StreamConfig,
+            http: HttpClient,
+        ) -> Result<DefaultAppStream, StreamError<WsError>> {
+            let session = app_session(&config)?;
+            Ok(AppHeartbeatStream::new(
+                connect(config).await?,
+                http,
+                session,
+            ))
+        }
+
+        /// Connect to bilibili live room as an open live platform app with auto retry, and keep the
+        /// app session alive.
+        ///
+        /// See [`connect_with_app_heartbeat`](connect_with_app_heartbeat) for details.
+        ///
+        /// # Errors
+        /// Returns an error when the config carries no app session, or websocket connection fails.
+        pub async fn connect_with_retry_and_app_heartbeat(
+            stream_config: StreamConfig,
+            retry_config: RetryConfig,
+            http: HttpClient,
+        ) -> Result<RetryAppStream, StreamError<WsError>> {
+            let session = app_session(&stream_config)?;
+            Ok(AppHeartbeatStream::new(
+                connect_with_retry(stream_config, retry_config).await?,
+                http,
+                session,
+            ))
+        }
+
+        fn app_session(config: &StreamConfig) -> io::Result<AppSession> {
+            config.app_session().cloned().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "stream config has no open platform app session",
+                )
+            })
         }
     };
 }
@@ -74,11 +348,107 @@ macro_rules! impl_connect_mod {
 #[cfg(feature = "tokio")]
 pub mod tokio {
     //! `tokio` integration.
+    use ::tokio::io::{AsyncRead, AsyncWrite};
+    use ::tokio::net::TcpStream;
+    use async_tungstenite::tokio::{client_async_tls_with_connector, TokioAdapter};
+    use async_tungstenite::tungstenite::handshake::client::Request;
+
     impl_connect_mod!(tokio);
+
+    /// TLS connector applied to secure websocket connections.
+    #[cfg(feature = "tokio-native-tls")]
+    pub type TlsConnector = tokio_native_tls::TlsConnector;
+
+    /// TLS connector applied to secure websocket connections.
+    ///
+    /// It can be built from a custom `rustls::ClientConfig`.
+    #[cfg(not(feature = "tokio-native-tls"))]
+    pub type TlsConnector = tokio_rustls::TlsConnector;
+
+    /// Raw tcp socket type.
+    pub type TcpInnerStream = TokioAdapter<BoxedSocket>;
+
+    async fn dial_tcp(host: &str, port: u16) -> io::Result<BoxedSocket> {
+        Ok(Box::new(TcpStream::connect((host, port)).await?))
+    }
+
+    async fn tunnel(
+        proxy: &Proxy,
+        socket: BoxedSocket,
+        host: &str,
+        port: u16,
+    ) -> io::Result<BoxedSocket> {
+        let mut socket = TokioAdapter::new(socket);
+        proxy.tunnel(&mut socket, host, port).await?;
+        Ok(socket.into_inner())
+    }
+
+    async fn ws_handshake(
+        request: Request,
+        socket: BoxedSocket,
+        tls: Option<&TlsConnector>,
+    ) -> Result<InnerStream, WsError> {
+        Ok(
+            client_async_tls_with_connector(request, socket, tls.cloned())
+                .await?
+                .0,
+        )
+    }
+
+    fn tcp_inner_stream(socket: BoxedSocket) -> TcpInnerStream {
+        TokioAdapter::new(socket)
+    }
 }
 
 #[cfg(feature = "async-std")]
 pub mod async_std {
     //! `async_std` integration.
+    use ::async_std::net::TcpStream;
+    use async_tungstenite::async_std::client_async_tls;
+    use async_tungstenite::client_async;
+    use async_tungstenite::stream::Stream;
+    use async_tungstenite::tungstenite::handshake::client::Request;
+    use futures::io::{AsyncRead, AsyncWrite};
+
     impl_connect_mod!(async_std);
+
+    /// TLS connector applied to secure websocket connections.
+    pub type TlsConnector = async_native_tls::TlsConnector;
+
+    /// Raw tcp socket type.
+    pub type TcpInnerStream = BoxedSocket;
+
+    async fn dial_tcp(host: &str, port: u16) -> io::Result<BoxedSocket> {
+        Ok(Box::new(TcpStream::connect((host, port)).await?))
+    }
+
+    async fn tunnel(
+        proxy: &Proxy,
+        mut socket: BoxedSocket,
+        host: &str,
+        port: u16,
+    ) -> io::Result<BoxedSocket> {
+        proxy.tunnel(&mut socket, host, port).await?;
+        Ok(socket)
+    }
+
+    async fn ws_handshake(
+        request: Request,
+        socket: BoxedSocket,
+        tls: Option<&TlsConnector>,
+    ) -> Result<InnerStream, WsError> {
+        let stream = match tls {
+            Some(tls) if request.uri().scheme_str() == Some("wss") => {
+                let host = request.uri().host().unwrap_or_default().to_string();
+                let stream = tls.connect(host, socket).await.map_err(io::Error::other)?;
+                Stream::Tls(stream)
+            }
+            _ => return Ok(client_async_tls(request, socket).await?.0),
+        };
+        Ok(client_async(request, stream).await?.0)
+    }
+
+    const fn tcp_inner_stream(socket: BoxedSocket) -> TcpInnerStream {
+        socket
+    }
 }
@@ -1,7 +1,19 @@
 //! Error types.
 use async_tungstenite::tungstenite::Error as WsError;
+use thiserror::Error;
 
-pub use crate::core::errors::{BuildError, IncompleteResult, ParseError};
+pub use crate::core::errors::{ApiError, BuildError, IncompleteResult, ParseError};
 
 /// Errors that may occur when consuming a stream.
 pub type StreamError = crate::core::errors::StreamError<WsError>;
+
+/// Errors that may occur when watching a live room.
+#[derive(Debug, Error)]
+pub enum WatchError {
+    #[error("api error: {0}")]
+    Api(#[from] ApiError),
+    #[error("stream error: {0}")]
+    Stream(#[from] StreamError),
+    #[error("danmaku stream closed")]
+    Closed,
+}
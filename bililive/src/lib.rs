@@ -1,6 +1,6 @@
 //! A simple stream-based bilibili live client library backed by [async-tungstenite](https://github.com/sdroege/async-tungstenite).
 //!
-//! *Minimum supported rust version: 1.56.0*
+//! *Minimum supported rust version: 1.82.0*
 //!
 //! ## Runtime Support
 //!
@@ -21,6 +21,12 @@
 //! - Handles heartbeat packets automatically.
 //! - Auto retry when connection fails (optional).
 //! - Decompresses `Zlib` payloads automatically.
+//! - Connects over raw TCP where websockets are blocked (optional).
+//! - Connects through HTTP or SOCKS5 proxies (optional).
+//! - Custom TLS configurations and transports via `Connector`.
+//! - Joins rooms as an open live platform (开放平台) app, with app heartbeats (optional).
+//! - Backfills recent danmaku history on (re)connect (optional).
+//! - Watches the live status and connects when the room goes live (see [`watch`](crate::watch)).
 //!
 //! ## Example
 //!
@@ -42,7 +48,8 @@
 //!     .fetch_conf()
 //!     .await
 //!     .unwrap()
-//!     .build();
+//!     .build()
+//!     .unwrap();
 //!
 //! let mut stream = connect_with_retry(config, RetryConfig::default()).await.unwrap();
 //! while let Some(e) = stream.next().await {
@@ -65,22 +72,22 @@
 //! ## Crate Features
 //!
 //! * `tokio-native-tls`(default): Enables `tokio` support with TLS implemented
-//! via [tokio-native-tls](https://crates.io/crates/tokio-native-tls).
+//!   via [tokio-native-tls](https://crates.io/crates/tokio-native-tls).
 //! * `tokio-rustls-native-certs`: Enables `tokio` support with TLS implemented
-//! via [tokio-rustls](https://crates.io/crates/tokio-rustls) and uses native system certificates found
-//! with [rustls-native-certs](https://github.com/rustls/rustls-native-certs).
+//!   via [tokio-rustls](https://crates.io/crates/tokio-rustls) and uses native system certificates found
+//!   with [rustls-native-certs](https://github.com/rustls/rustls-native-certs).
 //! * `tokio-rustls-webpki-roots`: Enables `tokio` support with TLS implemented
-//! via [tokio-rustls](https://crates.io/crates/tokio-rustls) and uses the
-//! certificates [webpki-roots](https://github.com/rustls/webpki-roots) provides.
+//!   via [tokio-rustls](https://crates.io/crates/tokio-rustls) and uses the
+//!   certificates [webpki-roots](https://github.com/rustls/webpki-roots) provides.
 //! * `async-native-tls`: Enables `async_std` support with TLS implemented
-//! via [async-native-tls](https://crates.io/crates/async-native-tls).
+//!   via [async-native-tls](https://crates.io/crates/async-native-tls).
 
 #![allow(clippy::default_trait_access, clippy::module_name_repetitions)]
 
 pub use bililive_core as core;
 
 #[doc(inline)]
-pub use crate::builder::ConfigBuilder;
+pub use crate::builder::{ConfigBuilder, HttpClient};
 pub use crate::core::packet::*;
 pub use crate::core::retry::RetryConfig;
 
@@ -88,3 +95,4 @@ mod builder;
 pub mod connect;
 pub mod errors;
 pub mod stream;
+pub mod watch;
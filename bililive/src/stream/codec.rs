@@ -22,9 +22,7 @@ impl<T> CodecStream<T> {
     ///
     /// You may want to use `connect` or `connect_with_retry` in [`connect`](crate::connect) module instead.
     pub const fn new(stream: T) -> Self {
-        Self {
-            stream
-        }
+        Self { stream }
     }
 }
 
@@ -1,12 +1,18 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
 use std::time::Duration;
 
 use async_tungstenite::tungstenite::Error as WsError;
 use futures::{Future, Sink, SinkExt, Stream, StreamExt};
 
-use crate::builder::tests::build_real_config;
-use crate::core::errors::StreamError;
+use crate::builder::tests::{build_real_config, serve, serve_proxy};
+use crate::core::config::{ServerEndpoint, StreamConfig, Transport, ORIGIN, USER_AGENT};
+use crate::core::errors::{IncompleteResult, StreamError};
 use crate::core::packet::{Operation, Packet, Protocol};
-use crate::core::retry::RetryConfig;
+use crate::core::proxy::ProxyKind;
+use crate::core::retry::{RetryConfig, WsStream, WsStreamTrait};
 
 async fn must_future_timeout(dur: Duration, fut: impl Future) {
     if cfg!(feature = "tokio") {
@@ -60,6 +66,162 @@ async fn test_stream(
     stream.close().await.expect("unable to close stream");
 }
 
+/// Packets replied by danmaku server stand-ins after entering the room.
+fn room_enter_reply() -> Vec<u8> {
+    let mut resp = Packet::new(
+        Operation::RoomEnterResponse,
+        Protocol::Json,
+        b"{\"code\":0}".to_vec(),
+    )
+    .encode();
+    resp.extend(
+        Packet::new(
+            Operation::Notification,
+            Protocol::Json,
+            b"{\"cmd\":\"LIVE\"}".to_vec(),
+        )
+        .encode(),
+    );
+    resp
+}
+
+/// A local tcp stand-in of the danmaku server.
+///
+/// It accepts one connection, replies the room enter packet and a notification, and then closes the
+/// connection. Websocket ports of the returned endpoint are closed, so that connectors must fall back
+/// to tcp.
+fn serve_tcp() -> StreamConfig {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("unable to bind");
+    let port = listener.local_addr().unwrap().port();
+    let closed_port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    thread::spawn(move || {
+        let (mut socket, _) = listener.accept().expect("unable to accept");
+
+        let mut buf = vec![];
+        let room_enter = loop {
+            let mut chunk = [0; 1024];
+            let n = socket.read(&mut chunk).unwrap();
+            assert_ne!(n, 0, "connection closed before room enter");
+            buf.extend_from_slice(&chunk[..n]);
+            match Packet::parse(&buf) {
+                IncompleteResult::Ok((_, packet)) => break packet,
+                IncompleteResult::Incomplete(_) => continue,
+                IncompleteResult::Err(e) => panic!("unable to parse packet: {}", e),
+            }
+        };
+        assert_eq!(room_enter.op(), Operation::RoomEnter);
+
+        socket.write_all(&room_enter_reply()).unwrap();
+        thread::sleep(Duration::from_millis(100));
+    });
+    StreamConfig::new(
+        5440,
+        0,
+        String::new(),
+        String::new(),
+        vec![ServerEndpoint::new(
+            "127.0.0.1",
+            port,
+            closed_port,
+            closed_port,
+        )],
+    )
+}
+
+async fn test_tcp_stream(stream: impl Stream<Item = Result<Packet, StreamError<WsError>>> + Unpin) {
+    let packets: Vec<_> = stream.collect().await;
+    assert_eq!(packets.len(), 2);
+    let packets: Vec<_> = packets
+        .into_iter()
+        .map(|packet| packet.expect("stream error"))
+        .collect();
+    assert_eq!(packets[0].op(), Operation::RoomEnterResponse);
+    assert_eq!(packets[1].op(), Operation::Notification);
+    assert_eq!(packets[1].bytes(), br#"{"cmd":"LIVE"}"#);
+}
+
+/// Connect to a local HTTP stand-in which rejects the upgrade with given connector, optionally
+/// through a proxy of given kind, and report the handshake it received.
+async fn handshake<T: WsStreamTrait<WsError>>(connector: &T, proxy: Option<ProxyKind>) -> String {
+    let (url, rx) = serve(concat!(
+        "HTTP/1.1 403 Forbidden\r\n",
+        "Content-Length: 0\r\n",
+        "Connection: close\r\n",
+        "\r\n"
+    ));
+    let addr = url.trim_start_matches("http://").to_string();
+    let url = format!("ws://{}/sub", addr);
+    let config = StreamConfig::new(5440, 0, String::new(), "xdd".to_string(), vec![])
+        .with_header("X-Test", "bililive");
+    let (config, targets) = match proxy {
+        Some(kind) => {
+            let (proxy, targets) = serve_proxy(kind);
+            (config.with_proxy(proxy), Some(targets))
+        }
+        None => (config, None),
+    };
+    assert!(WsStream::<T, WsError>::connect(connector, &url, &config)
+        .await
+        .is_err());
+    if let Some(targets) = targets {
+        assert_eq!(targets.recv().unwrap(), addr);
+    }
+    rx.recv().unwrap().to_ascii_lowercase()
+}
+
+/// Route the connections of given config through a local proxy stand-in.
+///
+/// Returns the proxied config, the address of the tcp server, and the targets reported by the
+/// proxy.
+fn through_proxy(
+    config: StreamConfig,
+    kind: ProxyKind,
+) -> (StreamConfig, String, mpsc::Receiver<String>) {
+    let (proxy, targets) = serve_proxy(kind);
+    let addr = format!("127.0.0.1:{}", config.servers()[0].port().unwrap());
+    (config.with_proxy(proxy), addr, targets)
+}
+
+fn check_tcp_targets(addr: &str, targets: &mpsc::Receiver<String>) {
+    // both websocket transports are tried and fail before tcp
+    let targets: Vec<_> = targets.try_iter().collect();
+    assert_eq!(targets.len(), 3);
+    assert_eq!(targets[2], addr);
+}
+
+/// A config whose servers can't be resolved, so that connections must go through a custom dialer.
+fn unresolvable_config(port: u16) -> StreamConfig {
+    StreamConfig::new(
+        5440,
+        0,
+        String::new(),
+        String::new(),
+        vec![ServerEndpoint::new(
+            "danmaku.invalid",
+            port,
+            port + 1,
+            port + 2,
+        )],
+    )
+    .with_transports(&[Transport::Tcp])
+}
+
+fn check_handshake(req: &str) {
+    assert!(req.starts_with("get /sub http/1.1\r\n"));
+    assert_eq!(req.matches("user-agent:").count(), 1);
+    assert!(req.contains(&format!(
+        "user-agent: {}\r\n",
+        USER_AGENT.to_ascii_lowercase()
+    )));
+    assert!(req.contains(&format!("origin: {}\r\n", ORIGIN)));
+    assert!(req.contains("cookie: buvid3=xdd\r\n"));
+    assert!(req.contains("x-test: bililive\r\n"));
+}
+
 async fn test_stream_heartbeat(
     mut stream: impl Stream<Item = Result<Packet, StreamError<WsError>>>
         + Sink<Packet, Error = StreamError<WsError>>
@@ -148,3 +310,136 @@ async fn must_hb_async_std() {
         .expect("unable to establish connection");
     test_stream_heartbeat(stream).await;
 }
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn must_fallback_to_tcp_tokio() {
+    let stream = crate::connect::tokio::connect(serve_tcp())
+        .await
+        .expect("unable to establish connection");
+    test_tcp_stream(stream).await;
+}
+
+#[cfg(feature = "async-std")]
+#[async_std::test]
+async fn must_fallback_to_tcp_async_std() {
+    let stream = crate::connect::async_std::connect(serve_tcp())
+        .await
+        .expect("unable to establish connection");
+    test_tcp_stream(stream).await;
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn must_send_handshake_headers_tokio() {
+    check_handshake(&handshake(&crate::connect::tokio::Connector::default(), None).await);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn must_connect_through_proxy_tokio() {
+    for kind in [ProxyKind::Http, ProxyKind::Socks5] {
+        check_handshake(&handshake(&crate::connect::tokio::Connector::default(), Some(kind)).await);
+
+        let (config, addr, targets) = through_proxy(serve_tcp(), kind);
+        let stream = crate::connect::tokio::connect(config)
+            .await
+            .expect("unable to establish connection");
+        test_tcp_stream(stream).await;
+        check_tcp_targets(&addr, &targets);
+    }
+}
+
+#[cfg(feature = "async-std")]
+#[async_std::test]
+async fn must_send_handshake_headers_async_std() {
+    check_handshake(&handshake(&crate::connect::async_std::Connector::default(), None).await);
+}
+
+#[cfg(feature = "async-std")]
+#[async_std::test]
+async fn must_connect_through_proxy_async_std() {
+    for kind in [ProxyKind::Http, ProxyKind::Socks5] {
+        check_handshake(
+            &handshake(&crate::connect::async_std::Connector::default(), Some(kind)).await,
+        );
+
+        let (config, addr, targets) = through_proxy(serve_tcp(), kind);
+        let stream = crate::connect::async_std::connect(config)
+            .await
+            .expect("unable to establish connection");
+        test_tcp_stream(stream).await;
+        check_tcp_targets(&addr, &targets);
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn must_connect_in_memory_tokio() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (hosts_tx, hosts) = mpsc::channel();
+    let hosts_tx = std::sync::Mutex::new(hosts_tx);
+    let connector = crate::connect::tokio::Connector::builder()
+        .dialer(move |host, port| {
+            hosts_tx
+                .lock()
+                .unwrap()
+                .send(format!("{}:{}", host, port))
+                .unwrap();
+            let (client, mut server) = tokio::io::duplex(4096);
+            async move {
+                tokio::spawn(async move {
+                    let mut buf = vec![0; 1024];
+                    let mut len = 0;
+                    while !matches!(Packet::parse(&buf[..len]), IncompleteResult::Ok(_)) {
+                        len += server.read(&mut buf[len..]).await.unwrap();
+                    }
+                    server.write_all(&room_enter_reply()).await.unwrap();
+                });
+                Ok(client)
+            }
+        })
+        .build();
+
+    let stream = connector
+        .connect(unresolvable_config(2243))
+        .await
+        .expect("unable to establish connection");
+    test_tcp_stream(stream).await;
+
+    let mut stream = connector
+        .connect_with_retry(unresolvable_config(2243), RetryConfig::default())
+        .await
+        .expect("unable to establish connection");
+    let packet = stream.next().await.unwrap().expect("stream error");
+    assert_eq!(packet.op(), Operation::RoomEnterResponse);
+    let packet = stream.next().await.unwrap().expect("stream error");
+    assert_eq!(packet.op(), Operation::Notification);
+
+    assert_eq!(
+        hosts.try_iter().collect::<Vec<_>>(),
+        ["danmaku.invalid:2243", "danmaku.invalid:2243"]
+    );
+}
+
+#[cfg(feature = "async-std")]
+#[async_std::test]
+async fn must_connect_with_dialer_async_std() {
+    let port = serve_tcp().servers()[0].port().unwrap();
+    let (hosts_tx, hosts) = mpsc::channel();
+    let hosts_tx = std::sync::Mutex::new(hosts_tx);
+    let connector = crate::connect::async_std::Connector::builder()
+        .dialer(move |host, _| {
+            hosts_tx.lock().unwrap().send(host.to_string()).unwrap();
+            async_std::net::TcpStream::connect(("127.0.0.1", port))
+        })
+        .build();
+
+    let stream = connector
+        .connect(unresolvable_config(port))
+        .await
+        .expect("unable to establish connection");
+    test_tcp_stream(stream).await;
+    assert_eq!(hosts.try_iter().collect::<Vec<_>>(), ["danmaku.invalid"]);
+}
@@ -0,0 +1,86 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_tungstenite::tungstenite::Error as WsError;
+use async_tungstenite::tungstenite::Message;
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::ready;
+use futures::{Sink, Stream};
+
+use crate::core::errors::StreamError;
+use crate::core::packet::Packet;
+use crate::core::stream::FramedStream;
+
+use super::CodecStream;
+
+/// A stream/sink interface over either a websocket or a raw tcp connection.
+///
+/// Errors of raw tcp connections are reported as [`WsError::Io`](WsError::Io).
+pub enum TransportStream<W, T> {
+    /// Websocket connection.
+    Ws(CodecStream<W>),
+    /// Raw tcp connection.
+    Tcp(FramedStream<T>),
+}
+
+fn from_tcp_error(e: StreamError<io::Error>) -> StreamError<WsError> {
+    match e {
+        StreamError::Parse(e) => StreamError::Parse(e),
+        StreamError::WebSocket(e) | StreamError::IO(e) => StreamError::IO(e),
+    }
+}
+
+impl<W, T> Stream for TransportStream<W, T>
+where
+    W: Stream<Item = Result<Message, WsError>> + Unpin,
+    T: AsyncRead + Unpin,
+{
+    type Item = Result<Packet, StreamError<WsError>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.get_mut() {
+            Self::Ws(s) => Pin::new(s).poll_next(cx),
+            Self::Tcp(s) => match ready!(Pin::new(s).poll_next(cx)) {
+                Some(item) => Poll::Ready(Some(item.map_err(from_tcp_error))),
+                None => Poll::Ready(None),
+            },
+        }
+    }
+}
+
+impl<W, T> Sink<Packet> for TransportStream<W, T>
+where
+    W: Sink<Message, Error = WsError> + Unpin,
+    T: AsyncWrite + Unpin,
+{
+    type Error = StreamError<WsError>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Ws(s) => Pin::new(s).poll_ready(cx),
+            Self::Tcp(s) => Pin::new(s).poll_ready(cx).map_err(from_tcp_error),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Packet) -> Result<(), Self::Error> {
+        match self.get_mut() {
+            Self::Ws(s) => Pin::new(s).start_send(item),
+            Self::Tcp(s) => Pin::new(s).start_send(item).map_err(from_tcp_error),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Ws(s) => Pin::new(s).poll_flush(cx),
+            Self::Tcp(s) => Pin::new(s).poll_flush(cx).map_err(from_tcp_error),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Ws(s) => Pin::new(s).poll_close(cx),
+            Self::Tcp(s) => Pin::new(s).poll_close(cx).map_err(from_tcp_error),
+        }
+    }
+}
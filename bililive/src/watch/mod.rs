@@ -0,0 +1,466 @@
+//! Live status watcher.
+//!
+//! A watcher idles until the live room starts streaming, connects to the danmaku server for the
+//! duration of the live session, and disconnects after the session ends.
+//!
+//! The live status is polled via [`room::get_info`](crate::core::room::get_info) every
+//! [`interval`](WatchConfig::interval). Once connected, `LIVE` and `PREPARING` notifications are
+//! used to detect session changes without waiting for the next poll, and polls keep running in
+//! case a notification is missed, e.g. during a reconnection.
+//!
+//! Sessions are identified by their start time. A live room is not connected until the api
+//! reports when the session started, and an unknown start time never ends the current session.
+//!
+//! # Example
+//!
+//! ```rust
+//! # #[cfg(feature = "tokio")]
+//! use bililive::watch::tokio::watch;
+//! use bililive::watch::{WatchConfig, WatchEvent};
+//! use bililive::ConfigBuilder;
+//! use futures::StreamExt;
+//!
+//! # #[cfg(feature = "tokio")]
+//! # async fn test() {
+//! let config = ConfigBuilder::new()
+//!     .by_room_id(6)
+//!     .await
+//!     .unwrap()
+//!     .fetch_conf()
+//!     .await
+//!     .unwrap()
+//!     .build()
+//!     .unwrap();
+//!
+//! let mut watcher = watch(config, WatchConfig::default()).unwrap();
+//! while let Some(event) = watcher.next().await {
+//!     match event {
+//!         Ok(WatchEvent::LiveStart(session)) => println!("live started: {}", session.id),
+//!         Ok(WatchEvent::Packet(packet)) => println!("packet: {:?}", packet),
+//!         Ok(WatchEvent::LiveEnd(session)) => println!("live ended: {}", session.id),
+//!         Err(e) => println!("error: {}", e),
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::future::{self, Either};
+use futures::{Stream, StreamExt};
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::core::builder::Requester;
+use crate::core::config::StreamConfig;
+use crate::core::packet::{Operation, Packet};
+use crate::core::retry::{BEBIterator, RetryConfig};
+use crate::core::room;
+use crate::errors::{StreamError, WatchError};
+
+#[cfg(test)]
+mod tests;
+
+/// The configuration of a live status watcher.
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    interval: Duration,
+    backoff: BEBIterator,
+    retry: RetryConfig,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            backoff: BEBIterator::default(),
+            retry: RetryConfig::default(),
+        }
+    }
+}
+
+impl WatchConfig {
+    /// Set the live status polling interval. By default it's 30 seconds.
+    #[must_use]
+    pub const fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+    /// Set the retry policy when polling or connecting fails, or the danmaku stream closes.
+    ///
+    /// The policy restarts once a packet is received. If the policy is exhausted, the error is
+    /// yielded and the policy restarts.
+    #[must_use]
+    pub fn backoff(mut self, backoff: BEBIterator) -> Self {
+        self.backoff = backoff;
+        self
+    }
+    /// Set the retry configuration of the danmaku stream during a live session.
+    #[must_use]
+    pub fn retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+/// A live session.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct LiveSession {
+    /// Session id.
+    ///
+    /// It's derived from the room id and the start time, so it's stable across reconnections and
+    /// watcher restarts.
+    pub id: String,
+    /// Live room id (long version).
+    pub room_id: u64,
+    /// Unix timestamp (in seconds) when the live started.
+    pub start_time: u64,
+}
+
+impl LiveSession {
+    fn new(room_id: u64, start_time: u64) -> Self {
+        Self {
+            id: format!("{}-{}", room_id, start_time),
+            room_id,
+            start_time,
+        }
+    }
+}
+
+/// Events yielded by a live status watcher.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum WatchEvent {
+    /// A live session started, and the danmaku stream is connected.
+    LiveStart(LiveSession),
+    /// A packet received during a live session.
+    Packet(Packet),
+    /// A live session ended, and the danmaku stream is disconnected.
+    LiveEnd(LiveSession),
+}
+
+#[derive(Deserialize)]
+struct LiveNotification {
+    cmd: String,
+    live_time: Option<u64>,
+}
+
+struct Watcher<H, C, S, SF, St> {
+    http: H,
+    config: StreamConfig,
+    watch_config: WatchConfig,
+    connect: C,
+    sleep: S,
+    stream: Option<St>,
+    poll: Option<Pin<Box<SF>>>,
+    session: Option<LiveSession>,
+    last_ended: Option<LiveSession>,
+    backoff: Option<BEBIterator>,
+    pending: VecDeque<WatchEvent>,
+}
+
+impl<H, C, CF, S, SF, St> Watcher<H, C, S, SF, St>
+where
+    H: Requester,
+    C: Fn(StreamConfig, RetryConfig) -> CF,
+    CF: Future<Output = Result<St, StreamError>>,
+    S: Fn(Duration) -> SF,
+    SF: Future<Output = ()>,
+    St: Stream<Item = Result<Packet, StreamError>> + Unpin,
+{
+    async fn next(&mut self) -> Option<Result<WatchEvent, WatchError>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+
+            if let Some(stream) = &mut self.stream {
+                let poll = self
+                    .poll
+                    .get_or_insert_with(|| Box::pin((self.sleep)(self.watch_config.interval)));
+                let item = match future::select(stream.next(), poll.as_mut()).await {
+                    Either::Left((item, _)) => Some(item),
+                    Either::Right(_) => None,
+                };
+                match item {
+                    Some(Some(Ok(packet))) => {
+                        self.backoff = None;
+                        self.inspect(packet);
+                    }
+                    Some(Some(Err(e))) => return Some(Err(e.into())),
+                    Some(None) => {
+                        // retries are exhausted, check whether the session is still alive
+                        debug!("danmaku stream closed");
+                        self.stream = None;
+                        self.poll = None;
+                        if let Some(e) = self.retry_later(WatchError::Closed).await {
+                            return Some(Err(e));
+                        }
+                    }
+                    None => {
+                        self.poll = None;
+                        self.reconcile().await;
+                    }
+                }
+                continue;
+            }
+
+            let info = match room::get_info(&self.http, self.config.room_id()).await {
+                Ok(info) => info,
+                Err(e) => match self.retry_later(e.into()).await {
+                    Some(e) => return Some(Err(e)),
+                    None => continue,
+                },
+            };
+
+            if !info.is_live() {
+                self.backoff = None;
+                if self.session.is_some() {
+                    self.end_session();
+                } else {
+                    (self.sleep)(self.watch_config.interval).await;
+                }
+                continue;
+            }
+
+            let start_time = match (info.live_start_time, &self.session) {
+                (Some(start_time), _) => start_time,
+                (None, Some(session)) => session.start_time,
+                (None, None) => {
+                    debug!("live start time unknown, wait for the next poll");
+                    (self.sleep)(self.watch_config.interval).await;
+                    continue;
+                }
+            };
+            if self
+                .last_ended
+                .as_ref()
+                .is_some_and(|session| session.start_time == start_time)
+            {
+                // the session has ended, but the api hasn't caught up yet
+                (self.sleep)(self.watch_config.interval).await;
+                continue;
+            }
+
+            match (self.connect)(self.config.clone(), self.watch_config.retry.clone()).await {
+                Ok(stream) => {
+                    self.stream = Some(stream);
+                }
+                Err(e) => match self.retry_later(e.into()).await {
+                    Some(e) => return Some(Err(e)),
+                    None => continue,
+                },
+            }
+
+            match &self.session {
+                Some(session) if session.start_time == start_time => {
+                    debug!("danmaku stream reconnected");
+                }
+                _ => self.start_session(start_time),
+            }
+        }
+    }
+
+    /// Inspect a packet for live status changes and queue it.
+    fn inspect(&mut self, packet: Packet) {
+        let notification = if packet.op() == Operation::Notification {
+            packet.json::<LiveNotification>().ok()
+        } else {
+            None
+        };
+        self.pending.push_back(WatchEvent::Packet(packet));
+
+        match notification {
+            Some(LiveNotification { cmd, .. }) if cmd == "PREPARING" => {
+                self.end_session();
+            }
+            Some(LiveNotification {
+                cmd,
+                live_time: Some(start_time),
+            }) if cmd == "LIVE"
+                && self
+                    .session
+                    .as_ref()
+                    .is_none_or(|session| session.start_time != start_time) =>
+            {
+                self.start_session(start_time);
+            }
+            _ => {}
+        }
+    }
+
+    /// Poll the live status while connected, and reconcile it with the current session.
+    ///
+    /// The api may lag behind notifications, so only a later start time starts a new session.
+    async fn reconcile(&mut self) {
+        let info = match room::get_info(&self.http, self.config.room_id()).await {
+            Ok(info) => info,
+            Err(e) => {
+                warn!("unable to poll live status: {}", e);
+                return;
+            }
+        };
+        if !info.is_live() {
+            debug!("room is offline, but no PREPARING notification is received");
+            self.end_session();
+        } else if let Some(start_time) = info.live_start_time {
+            if self
+                .session
+                .as_ref()
+                .is_none_or(|session| session.start_time < start_time)
+            {
+                self.start_session(start_time);
+            }
+        }
+    }
+
+    /// Disconnect, and end the current session if there's one.
+    fn end_session(&mut self) {
+        self.stream = None;
+        self.poll = None;
+        if let Some(session) = self.session.take() {
+            self.last_ended = Some(session.clone());
+            self.pending.push_back(WatchEvent::LiveEnd(session));
+        }
+    }
+
+    /// End the current session if there's one, and start a new session.
+    fn start_session(&mut self, start_time: u64) {
+        if let Some(session) = self.session.take() {
+            self.pending.push_back(WatchEvent::LiveEnd(session));
+        }
+        let session = LiveSession::new(self.config.room_id(), start_time);
+        self.session = Some(session.clone());
+        self.pending.push_back(WatchEvent::LiveStart(session));
+    }
+
+    /// Wait according to the backoff policy.
+    ///
+    /// Returns the error if the policy is exhausted.
+    async fn retry_later(&mut self, e: WatchError) -> Option<WatchError> {
+        let delay = self
+            .backoff
+            .get_or_insert_with(|| self.watch_config.backoff.clone())
+            .next();
+        if let Some(delay) = delay {
+            warn!("watcher error: {}, retry in {:?}", e, delay);
+            (self.sleep)(delay).await;
+            None
+        } else {
+            self.backoff = None;
+            Some(e)
+        }
+    }
+}
+
+/// Construct a watcher stream from given requester, connect function and sleep function.
+pub(crate) fn watch_with<H, C, CF, S, SF, St>(
+    http: H,
+    config: StreamConfig,
+    watch_config: WatchConfig,
+    connect: C,
+    sleep: S,
+) -> impl Stream<Item = Result<WatchEvent, WatchError>> + Unpin
+where
+    H: Requester,
+    C: Fn(StreamConfig, RetryConfig) -> CF,
+    CF: Future<Output = Result<St, StreamError>>,
+    S: Fn(Duration) -> SF,
+    SF: Future<Output = ()>,
+    St: Stream<Item = Result<Packet, StreamError>> + Unpin,
+{
+    let watcher = Watcher {
+        http,
+        config,
+        watch_config,
+        connect,
+        sleep,
+        stream: None,
+        poll: None,
+        session: None,
+        last_ended: None,
+        backoff: None,
+        pending: VecDeque::new(),
+    };
+    Box::pin(futures::stream::unfold(watcher, |mut watcher| async move {
+        watcher.next().await.map(|item| (item, watcher))
+    }))
+}
+
+macro_rules! impl_watch_mod {
+    ($adapter:ident, $sleep:path) => {
+        use futures::Stream;
+
+        use crate::builder::HttpClient;
+        use crate::connect::$adapter::Connector;
+        use crate::core::builder::Requester;
+        use crate::core::config::StreamConfig;
+        use crate::errors::{BuildError, WatchError};
+
+        use super::{watch_with, WatchConfig, WatchEvent};
+
+        /// Watch the live status of the room in `config`.
+        ///
+        /// The returned stream connects to the danmaku server when the room goes live and yields
+        /// packets received until the live session ends. `config` is reused for every session.
+        ///
+        /// This uses the default [`Connector`](crate::connect::$adapter::Connector), and an
+        /// [`HttpClient`](crate::HttpClient) sending requests through
+        /// [`StreamConfig::proxy`](crate::core::config::StreamConfig::proxy) if there's one. See
+        /// [`watch_with_connector`](watch_with_connector) to customize them.
+        ///
+        /// # Errors
+        /// Returns an error if the http client can't be built with the proxy.
+        pub fn watch(
+            config: StreamConfig,
+            watch_config: WatchConfig,
+        ) -> Result<impl Stream<Item = Result<WatchEvent, WatchError>> + Unpin, BuildError> {
+            let http = match config.proxy() {
+                Some(proxy) => HttpClient::with_proxy(proxy)?,
+                None => HttpClient::default(),
+            };
+            Ok(watch_with_connector(
+                config,
+                watch_config,
+                http,
+                Connector::default(),
+            ))
+        }
+
+        /// Watch the live status of the room in `config`, polling it with `http` and connecting
+        /// to the danmaku server with `connector`.
+        ///
+        /// See [`watch`](watch) for details.
+        pub fn watch_with_connector<H: Requester>(
+            config: StreamConfig,
+            watch_config: WatchConfig,
+            http: H,
+            connector: Connector,
+        ) -> impl Stream<Item = Result<WatchEvent, WatchError>> + Unpin {
+            watch_with(
+                http,
+                config,
+                watch_config,
+                move |config, retry| {
+                    let connector = connector.clone();
+                    async move { connector.connect_with_retry(config, retry).await }
+                },
+                $sleep,
+            )
+        }
+    };
+}
+
+#[cfg(feature = "tokio")]
+pub mod tokio {
+    //! `tokio` integration.
+    impl_watch_mod!(tokio, ::tokio::time::sleep);
+}
+
+#[cfg(feature = "async-std")]
+pub mod async_std {
+    //! `async_std` integration.
+    impl_watch_mod!(async_std, ::async_std::task::sleep);
+}
@@ -0,0 +1,347 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures::executor::block_on;
+use futures::stream::LocalBoxStream;
+use futures::{future, stream, StreamExt};
+use serde_json::json;
+
+use crate::core::builder::{BoxFuture, Request, Requester, Response};
+use crate::core::config::StreamConfig;
+use crate::core::errors::BoxedError;
+use crate::core::packet::{Operation, Packet, Protocol};
+use crate::core::retry::BEBIterator;
+use crate::errors::{StreamError, WatchError};
+
+use super::{watch_with, LiveSession, WatchConfig, WatchEvent};
+
+/// A requester that replies `get_info` queries with prepared responses.
+#[derive(Default)]
+struct MockRequester(Mutex<VecDeque<serde_json::Value>>);
+
+impl MockRequester {
+    fn new(responses: impl IntoIterator<Item = serde_json::Value>) -> Self {
+        Self(Mutex::new(responses.into_iter().collect()))
+    }
+}
+
+impl Requester for MockRequester {
+    fn request(&self, _request: Request) -> BoxFuture<'_, Result<Response, BoxedError>> {
+        let resp = self.0.lock().unwrap().pop_front();
+        Box::pin(async move {
+            let resp = resp.ok_or("no more responses")?;
+            Ok(Response::new(200, vec![], serde_json::to_vec(&resp)?))
+        })
+    }
+}
+
+fn info(live: bool, live_time: &str) -> serde_json::Value {
+    json!({
+        "code": 0,
+        "message": "ok",
+        "data": {
+            "uid": 9617619,
+            "room_id": 5440,
+            "short_id": 6,
+            "live_status": u8::from(live),
+            "live_time": live_time,
+            "title": "",
+            "area_id": 0,
+            "area_name": "",
+            "parent_area_id": 0,
+            "parent_area_name": ""
+        }
+    })
+}
+
+fn notification(value: &serde_json::Value) -> Packet {
+    Packet::new(
+        Operation::Notification,
+        Protocol::Json,
+        serde_json::to_vec(value).unwrap(),
+    )
+}
+
+fn config() -> StreamConfig {
+    StreamConfig::new(5440, 0, String::new(), String::new(), vec![])
+}
+
+type PacketStream = LocalBoxStream<'static, Result<Packet, StreamError>>;
+
+/// A connection which yields `packets` and then closes.
+fn closing(packets: Vec<Packet>) -> PacketStream {
+    stream::iter(packets.into_iter().map(Ok)).boxed_local()
+}
+
+/// A connection which yields `packets` and then stays open.
+fn lasting(packets: Vec<Packet>) -> PacketStream {
+    closing(packets).chain(stream::pending()).boxed_local()
+}
+
+fn collect(
+    http: MockRequester,
+    sessions: Vec<Vec<Packet>>,
+    count: usize,
+) -> (Vec<WatchEvent>, usize) {
+    collect_streams(http, sessions.into_iter().map(closing).collect(), count)
+}
+
+fn collect_streams(
+    http: MockRequester,
+    sessions: Vec<PacketStream>,
+    count: usize,
+) -> (Vec<WatchEvent>, usize) {
+    let sessions = Mutex::new(VecDeque::from(sessions));
+    let connections = Mutex::new(0);
+    let watcher = watch_with(
+        http,
+        config(),
+        WatchConfig::default(),
+        |_, _| {
+            *connections.lock().unwrap() += 1;
+            let packets = sessions
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_else(|| closing(vec![]));
+            future::ready(Ok::<_, StreamError>(packets))
+        },
+        |_| future::ready(()),
+    );
+    let events = block_on(
+        watcher
+            .take(count)
+            .map(|event| event.expect("watch error"))
+            .collect(),
+    );
+    let connections = *connections.lock().unwrap();
+    (events, connections)
+}
+
+#[test]
+fn must_watch_session() {
+    let danmaku = notification(&json!({"cmd": "DANMU_MSG", "info": []}));
+    let preparing = notification(&json!({"cmd": "PREPARING", "roomid": "5440"}));
+    let http = MockRequester::new([
+        info(false, "0000-00-00 00:00:00"),
+        info(true, "2023-07-22 20:00:03"),
+        // the api hasn't caught up with the end of the session
+        info(true, "2023-07-22 20:00:03"),
+        info(false, "0000-00-00 00:00:00"),
+        info(true, "2023-07-22 22:00:00"),
+    ]);
+
+    let (events, connections) = collect(
+        http,
+        vec![vec![danmaku.clone(), preparing.clone()], vec![]],
+        5,
+    );
+    let session = LiveSession::new(5440, 1690027203);
+    assert_eq!(session.id, "5440-1690027203");
+    assert_eq!(
+        events,
+        [
+            WatchEvent::LiveStart(session.clone()),
+            WatchEvent::Packet(danmaku),
+            WatchEvent::Packet(preparing),
+            WatchEvent::LiveEnd(session),
+            WatchEvent::LiveStart(LiveSession::new(5440, 1690034400)),
+        ]
+    );
+    assert_eq!(connections, 2);
+}
+
+#[test]
+fn must_reconnect_in_session() {
+    let danmaku = notification(&json!({"cmd": "DANMU_MSG", "info": []}));
+    let http = MockRequester::new([
+        json!({"code": -412, "message": "请求被拦截"}),
+        info(true, "2023-07-22 20:00:03"),
+        info(true, "2023-07-22 20:00:03"),
+        info(false, "0000-00-00 00:00:00"),
+    ]);
+
+    let (events, connections) =
+        collect(http, vec![vec![danmaku.clone()], vec![danmaku.clone()]], 4);
+    let session = LiveSession::new(5440, 1690027203);
+    assert_eq!(
+        events,
+        [
+            WatchEvent::LiveStart(session.clone()),
+            WatchEvent::Packet(danmaku.clone()),
+            WatchEvent::Packet(danmaku),
+            WatchEvent::LiveEnd(session),
+        ]
+    );
+    assert_eq!(connections, 2);
+}
+
+#[test]
+fn must_follow_live_notification() {
+    let live = notification(&json!({"cmd": "LIVE", "live_time": 1690034400, "roomid": 5440}));
+    let http = MockRequester::new([info(true, "2023-07-22 20:00:03")]);
+
+    let (events, _) = collect(http, vec![vec![live.clone()]], 4);
+    let old_session = LiveSession::new(5440, 1690027203);
+    let new_session = LiveSession::new(5440, 1690034400);
+    assert_eq!(
+        events,
+        [
+            WatchEvent::LiveStart(old_session.clone()),
+            WatchEvent::Packet(live),
+            WatchEvent::LiveEnd(old_session),
+            WatchEvent::LiveStart(new_session),
+        ]
+    );
+}
+
+#[test]
+fn must_keep_session_of_unknown_start_time() {
+    let danmaku = notification(&json!({"cmd": "DANMU_MSG", "info": []}));
+    let http = MockRequester::new([
+        // not connected until the start time is known
+        info(true, "0000-00-00 00:00:00"),
+        info(true, "2023-07-22 20:00:03"),
+        info(true, "0000-00-00 00:00:00"),
+        info(false, "0000-00-00 00:00:00"),
+    ]);
+
+    let (events, connections) =
+        collect(http, vec![vec![danmaku.clone()], vec![danmaku.clone()]], 4);
+    let session = LiveSession::new(5440, 1690027203);
+    assert_eq!(
+        events,
+        [
+            WatchEvent::LiveStart(session.clone()),
+            WatchEvent::Packet(danmaku.clone()),
+            WatchEvent::Packet(danmaku),
+            WatchEvent::LiveEnd(session),
+        ]
+    );
+    assert_eq!(connections, 2);
+}
+
+#[test]
+fn must_end_session_on_missed_preparing() {
+    let danmaku = notification(&json!({"cmd": "DANMU_MSG", "info": []}));
+    let http = MockRequester::new([
+        info(true, "2023-07-22 20:00:03"),
+        info(true, "2023-07-22 20:00:03"),
+        // the PREPARING notification is lost
+        info(false, "0000-00-00 00:00:00"),
+        info(false, "0000-00-00 00:00:00"),
+    ]);
+
+    let (events, connections) = collect_streams(http, vec![lasting(vec![danmaku.clone()])], 3);
+    let session = LiveSession::new(5440, 1690027203);
+    assert_eq!(
+        events,
+        [
+            WatchEvent::LiveStart(session.clone()),
+            WatchEvent::Packet(danmaku),
+            WatchEvent::LiveEnd(session),
+        ]
+    );
+    assert_eq!(connections, 1);
+}
+
+#[test]
+fn must_follow_polled_session() {
+    let http = MockRequester::new([
+        info(true, "2023-07-22 20:00:03"),
+        // a new session started, but no LIVE notification is received
+        info(true, "2023-07-22 21:00:00"),
+    ]);
+
+    let (events, connections) = collect_streams(http, vec![lasting(vec![])], 3);
+    let first = LiveSession::new(5440, 1690027203);
+    let second = LiveSession::new(5440, 1690030800);
+    assert_eq!(
+        events,
+        [
+            WatchEvent::LiveStart(first.clone()),
+            WatchEvent::LiveEnd(first),
+            WatchEvent::LiveStart(second),
+        ]
+    );
+    assert_eq!(connections, 1);
+}
+
+#[test]
+fn must_ignore_lagging_poll() {
+    let live = notification(&json!({"cmd": "LIVE", "live_time": 1690034400, "roomid": 5440}));
+    let http = MockRequester::new([
+        info(true, "2023-07-22 20:00:03"),
+        // the api hasn't caught up with the LIVE notification
+        info(true, "2023-07-22 20:00:03"),
+        info(false, "0000-00-00 00:00:00"),
+    ]);
+
+    let (events, _) = collect_streams(http, vec![lasting(vec![live.clone()])], 5);
+    let old_session = LiveSession::new(5440, 1690027203);
+    let new_session = LiveSession::new(5440, 1690034400);
+    assert_eq!(
+        events,
+        [
+            WatchEvent::LiveStart(old_session.clone()),
+            WatchEvent::Packet(live),
+            WatchEvent::LiveEnd(old_session),
+            WatchEvent::LiveStart(new_session.clone()),
+            WatchEvent::LiveEnd(new_session),
+        ]
+    );
+}
+
+#[test]
+fn must_back_off_when_stream_keeps_closing() {
+    let danmaku = notification(&json!({"cmd": "DANMU_MSG", "info": []}));
+    // the room stays live, but the server closes every connection right away
+    let http = MockRequester::new((0..6).map(|_| info(true, "2023-07-22 20:00:03")));
+    let interval = Duration::from_secs(30);
+    let watch_config = WatchConfig::default()
+        .interval(interval)
+        .backoff(BEBIterator::new(Duration::from_secs(1), 2, 3));
+
+    let sessions = Mutex::new(VecDeque::from([
+        closing(vec![]),
+        closing(vec![]),
+        closing(vec![danmaku.clone()]),
+    ]));
+    let calls = Mutex::new(vec![]);
+    let watcher = watch_with(
+        http,
+        config(),
+        watch_config,
+        |_, _| {
+            calls.lock().unwrap().push("connect");
+            let packets = sessions
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_else(|| closing(vec![]));
+            future::ready(Ok::<_, StreamError>(packets))
+        },
+        |duration| {
+            // live status polls while connected are not backoffs
+            if duration != interval {
+                calls.lock().unwrap().push("sleep");
+            }
+            future::ready(())
+        },
+    );
+    let events: Vec<_> = block_on(watcher.take(3).collect());
+
+    let session = LiveSession::new(5440, 1690027203);
+    assert!(matches!(&events[0], Ok(WatchEvent::LiveStart(s)) if *s == session));
+    assert!(matches!(&events[1], Ok(WatchEvent::Packet(p)) if *p == danmaku));
+    // the backoff restarts after the packet, and gives up after 3 more closed connections
+    assert!(matches!(events[2], Err(WatchError::Closed)));
+    assert_eq!(
+        *calls.lock().unwrap(),
+        [
+            "connect", "sleep", "connect", "sleep", "connect", "sleep", "connect", "sleep",
+            "connect", "sleep", "connect"
+        ]
+    );
+}